@@ -78,7 +78,7 @@ mod supabase_startup_snapshot {
         }
     }
 
-    impl deno_websocket::WebSocketPermissions for Permissions {
+    impl sb_websocket::WebSocketPermissions for Permissions {
         fn check_net_url(
             &mut self,
             _url: &deno_core::url::Url,
@@ -171,7 +171,12 @@ mod supabase_startup_snapshot {
         let user_agent = String::from("supabase");
         let fs = Arc::new(deno_fs::RealFs);
         let mut extensions: Vec<Extension> = vec![
-            sb_core_permissions::init_ops_and_esm(false),
+            sb_core_permissions::init_ops_and_esm(
+                false,
+                Default::default(),
+                Default::default(),
+                Default::default(),
+            ),
             deno_webidl::deno_webidl::init_ops_and_esm(),
             deno_console::deno_console::init_ops_and_esm(),
             deno_url::deno_url::init_ops_and_esm(),
@@ -186,7 +191,7 @@ mod supabase_startup_snapshot {
                 root_cert_store_provider: None,
                 ..Default::default()
             }),
-            deno_websocket::deno_websocket::init_ops_and_esm::<Permissions>(user_agent, None, None),
+            sb_websocket::deno_websocket::init_ops_and_esm::<Permissions>(user_agent, None, None),
             // TODO: support providing a custom seed for crypto
             deno_crypto::deno_crypto::init_ops_and_esm(None),
             deno_broadcast_channel::deno_broadcast_channel::init_ops_and_esm(
@@ -1,8 +1,11 @@
 use crate::{
+    event_sinks::EventSinkConfig,
     rt_worker::{worker_ctx::TerminationToken, worker_pool::WorkerPoolPolicy},
     server::{Server, ServerHealth, WorkerEntrypoints},
 };
 use anyhow::Error;
+use event_worker::channel::EventChannelOpts;
+use sb_core::permissions::FsAccessRules;
 use tokio::sync::mpsc::Sender;
 
 #[allow(clippy::too_many_arguments)]
@@ -11,11 +14,14 @@ pub async fn start_server(
     port: u16,
     main_service_path: String,
     event_worker_path: Option<String>,
+    event_sinks: Vec<EventSinkConfig>,
+    events_channel_opts: EventChannelOpts,
     user_worker_policy: Option<WorkerPoolPolicy>,
     import_map_path: Option<String>,
     no_module_cache: bool,
     callback_tx: Option<Sender<ServerHealth>>,
     entrypoints: WorkerEntrypoints,
+    main_fs_access_rules: FsAccessRules,
     termination_token: Option<TerminationToken>,
 ) -> Result<(), Error> {
     let mut server = Server::new(
@@ -23,11 +29,14 @@ pub async fn start_server(
         port,
         main_service_path,
         event_worker_path,
+        event_sinks,
+        events_channel_opts,
         user_worker_policy,
         import_map_path,
         no_module_cache,
         callback_tx,
         entrypoints,
+        main_fs_access_rules,
         termination_token,
     )
     .await?;
@@ -1,3 +1,4 @@
+use crate::rt_worker::cpu_profiler::CpuProfiler;
 use crate::rt_worker::supervisor::{CPUUsage, CPUUsageMetrics};
 use crate::rt_worker::worker::UnixStreamEntry;
 use crate::utils::units::mib_to_bytes;
@@ -11,6 +12,7 @@ use deno_core::{
     located_script_name, serde_v8, JsRuntime, ModuleCodeString, ModuleId, PollEventLoopOptions,
     RuntimeOptions,
 };
+use deno_fetch::CreateHttpClientOptions;
 use deno_http::DefaultHttpPropertyExtractor;
 use deno_tls::deno_native_certs::load_native_certs;
 use deno_tls::rustls;
@@ -27,21 +29,26 @@ use std::fmt;
 use std::os::fd::RawFd;
 use std::sync::Arc;
 use std::task::Poll;
-use tokio::sync::{mpsc, watch};
+use tokio::sync::{mpsc, oneshot, watch};
 
 use crate::snapshot;
-use event_worker::events::{EventMetadata, WorkerEventWithMetadata};
-use event_worker::js_interceptors::sb_events_js_interceptors;
+use event_worker::channel::{EventSender, SharedEventReceiver};
+use event_worker::events::EventMetadata;
+use event_worker::js_interceptors::{sb_events_js_interceptors, LogRedactions, MinLogLevel};
 use event_worker::sb_user_event_worker;
 use sb_ai::sb_ai;
 use sb_core::cache::CacheSetting;
 use sb_core::cert::ValueRootCertStoreProvider;
 use sb_core::external_memory::custom_allocator;
+use sb_core::http_client::{create_http_client, default_http_client_options};
 use sb_core::http_start::sb_core_http;
 use sb_core::net::sb_core_net;
-use sb_core::permissions::{sb_core_permissions, Permissions};
+use sb_core::permissions::{
+    sb_core_permissions, EnvAccessRules, FsAccessRules, NetAccessRules, Permissions,
+};
 use sb_core::runtime::sb_core_runtime;
 use sb_core::sb_core_main_js;
+use sb_core::ws::WsHttpClient;
 use sb_env::sb_env as sb_env_op;
 use sb_graph::emitter::EmitterFactory;
 use sb_graph::import_map::load_import_map;
@@ -49,7 +56,10 @@ use sb_graph::{generate_binary_eszip, EszipPayloadKind};
 use sb_module_loader::standalone::create_module_loader_for_standalone_from_eszip_kind;
 use sb_module_loader::RuntimeProviders;
 use sb_node::deno_node;
-use sb_workers::context::{UserWorkerMsgs, WorkerContextInitOpts, WorkerRuntimeOpts};
+use sb_websocket::WsHandshakeHook;
+use sb_workers::context::{
+    BeforeUnloadSignal, CpuProfileRequest, UserWorkerMsgs, WorkerContextInitOpts, WorkerRuntimeOpts,
+};
 use sb_workers::sb_user_workers;
 
 #[ctor]
@@ -96,6 +106,8 @@ pub struct DenoRuntime {
     pub conf: WorkerRuntimeOpts,
     pub is_termination_requested: Arc<AtomicFlag>,
     pub is_terminated: Arc<AtomicFlag>,
+    pub beforeunload_tx: Option<oneshot::Sender<BeforeUnloadSignal>>,
+    pub cpu_profiler: Option<CpuProfiler>,
 
     main_module_id: ModuleId,
 }
@@ -130,11 +142,21 @@ impl DenoRuntime {
         }
 
         let mut net_access_disabled = false;
+        let mut net_access_rules = NetAccessRules::default();
+        let mut fs_access_rules = FsAccessRules::default();
+        let mut env_access_rules = EnvAccessRules::default();
         let mut allow_remote_modules = true;
         if is_user_worker {
             let user_conf = conf.as_user_worker().unwrap();
             net_access_disabled = user_conf.net_access_disabled;
+            net_access_rules = user_conf.net_access_rules.clone();
+            fs_access_rules = user_conf.fs_access_rules.clone().unwrap_or_else(|| {
+                FsAccessRules::new(Some(vec![base_dir_path.clone()]), Some(vec![]))
+            });
+            env_access_rules = user_conf.env_access_rules.clone();
             allow_remote_modules = user_conf.allow_remote_modules;
+        } else if let Some(main_conf) = conf.as_main_worker() {
+            fs_access_rules = main_conf.fs_access_rules.clone();
         }
 
         let mut maybe_arc_import_map = None;
@@ -247,7 +269,12 @@ impl DenoRuntime {
         let mod_code = module_code;
 
         let extensions = vec![
-            sb_core_permissions::init_ops(net_access_disabled),
+            sb_core_permissions::init_ops(
+                net_access_disabled,
+                net_access_rules,
+                fs_access_rules,
+                env_access_rules.clone(),
+            ),
             deno_webidl::deno_webidl::init_ops(),
             deno_console::deno_console::init_ops(),
             deno_url::deno_url::init_ops(),
@@ -262,7 +289,7 @@ impl DenoRuntime {
                 root_cert_store_provider: Some(root_cert_store_provider.clone()),
                 ..Default::default()
             }),
-            deno_websocket::deno_websocket::init_ops::<Permissions>(
+            sb_websocket::deno_websocket::init_ops::<Permissions>(
                 SUPABASE_UA.clone(),
                 Some(root_cert_store_provider.clone()),
                 None,
@@ -309,17 +336,38 @@ impl DenoRuntime {
             compiled_wasm_module_store: Default::default(),
             startup_snapshot: Some(snapshot::snapshot()),
             module_loader: Some(module_loader),
+            // NOTE: The CPU profiler is driven through the inspector, so it's
+            // only created for the workers that may be profiled.
+            inspector: conf
+                .as_user_worker()
+                .map(|it| it.cpu_profile_dir.is_some())
+                .unwrap_or_default(),
             ..Default::default()
         };
 
         let mut js_runtime = JsRuntime::new(runtime_options);
         let version: Option<&str> = option_env!("GIT_V_TAG");
 
+        // NOTE: The receiver must be in place before bootstrapping since the
+        // bootstrap script starts waiting for the signal right away.
+        let beforeunload_tx = if conf.is_user_worker() {
+            let (tx, rx) = oneshot::channel::<BeforeUnloadSignal>();
+
+            js_runtime.op_state().borrow_mut().put(rx);
+            Some(tx)
+        } else {
+            None
+        };
+
         // Bootstrapping stage
         let script = format!(
             // opts, isUserWorker, isEventsWorker, edgeRuntimeVersion, denoVersion
             "globalThis.bootstrapSBEdge({}, {}, {}, '{}', '{}')",
-            deno_core::serde_json::json!({ "target": env!("TARGET") }),
+            deno_core::serde_json::json!({
+                "target": env!("TARGET"),
+                "structuredLogs": conf.as_user_worker().map_or(false, |it| it.structured_logs),
+                "tracing": crate::otel::is_tracing_enabled(),
+            }),
             conf.is_user_worker(),
             conf.is_events_worker(),
             version.unwrap_or("0.1.0"),
@@ -342,8 +390,11 @@ impl DenoRuntime {
 
             if conf.is_events_worker() {
                 // if worker is an events worker, assert events_rx is to be available
-                op_state
-                    .put::<mpsc::UnboundedReceiver<WorkerEventWithMetadata>>(events_rx.unwrap());
+                op_state.put::<SharedEventReceiver>(events_rx.unwrap());
+
+                if let Some(token) = conf.as_events_worker().and_then(|it| it.exit_token.clone()) {
+                    op_state.put(token.drop_guard());
+                }
             }
 
             if conf.is_main_worker() || conf.is_user_worker() {
@@ -359,11 +410,41 @@ impl DenoRuntime {
                     conf.key.map_or("".to_string(), |k| k.to_string()),
                 );
 
+                op_state.put::<MinLogLevel>(MinLogLevel(conf.log_level));
+                op_state.put::<LogRedactions>(LogRedactions::new(
+                    env_access_rules.secret_values(&env_vars),
+                ));
+
+                // resolve the hosts of `fetch` requests and WebSocket
+                // connections with a resolver that drops the private
+                // addresses, and send them through the proxy of the worker
+                if conf.net_access_rules.checks_resolved_addrs() || conf.proxy.is_some() {
+                    let options = default_http_client_options(&op_state)?;
+
+                    op_state.put::<deno_fetch::reqwest::Client>(create_http_client(
+                        &SUPABASE_UA,
+                        options.clone(),
+                        conf.net_access_rules.clone(),
+                        conf.proxy.clone(),
+                    )?);
+                    op_state.put::<WsHttpClient>(WsHttpClient(create_http_client(
+                        &SUPABASE_UA,
+                        CreateHttpClientOptions {
+                            http2: false,
+                            ..options
+                        },
+                        conf.net_access_rules.clone(),
+                        conf.proxy.clone(),
+                    )?));
+                    op_state.put::<WsHandshakeHook>(WsHandshakeHook(sb_core::ws::handshake));
+                }
+
                 if let Some(events_msg_tx) = conf.events_msg_tx.clone() {
-                    op_state.put::<mpsc::UnboundedSender<WorkerEventWithMetadata>>(events_msg_tx);
+                    op_state.put::<EventSender>(events_msg_tx);
                     op_state.put::<EventMetadata>(EventMetadata {
                         service_path: conf.service_path.clone(),
                         execution_id: conf.key,
+                        version: conf.version.clone(),
                     });
                 }
             }
@@ -386,9 +467,34 @@ impl DenoRuntime {
             conf,
             is_termination_requested: Arc::default(),
             is_terminated: Arc::default(),
+            beforeunload_tx,
+            cpu_profiler: None,
         })
     }
 
+    /// Starts the CPU profiler of the user worker if it may be profiled, taking
+    /// the profile requests from `requests_rx`.
+    pub fn start_cpu_profiler(
+        &mut self,
+        requests_rx: Option<mpsc::UnboundedReceiver<CpuProfileRequest>>,
+    ) {
+        let Some((conf, dir)) = self
+            .conf
+            .as_user_worker()
+            .and_then(|conf| Some((conf, conf.cpu_profile_dir.clone()?)))
+        else {
+            return;
+        };
+
+        self.cpu_profiler = Some(CpuProfiler::new(
+            &self.js_runtime.inspector().borrow(),
+            conf.key.unwrap_or_default(),
+            dir,
+            conf.cpu_profile_duration_ms,
+            requests_rx.unwrap_or_else(|| mpsc::unbounded_channel().1),
+        ));
+    }
+
     pub async fn run(
         &mut self,
         unix_stream_rx: mpsc::UnboundedReceiver<UnixStreamEntry>,
@@ -407,6 +513,8 @@ impl DenoRuntime {
             }
         }
 
+        let cpu_profiler = self.cpu_profiler.take();
+
         let mut js_runtime = &mut self.js_runtime;
 
         let mod_result_rx = {
@@ -509,6 +617,10 @@ impl DenoRuntime {
             },
         };
 
+        if let Some(cpu_profiler) = cpu_profiler {
+            cpu_profiler.shutdown(&mut self.js_runtime).await;
+        }
+
         self.is_terminated.raise();
 
         (result, accumulated_cpu_time_ns)
@@ -552,6 +664,8 @@ mod test {
     use crate::deno_runtime::DenoRuntime;
     use crate::rt_worker::worker::UnixStreamEntry;
     use deno_core::{FastString, ModuleCodeString, PollEventLoopOptions};
+    use event_worker::events::LogLevel;
+    use sb_core::permissions::{EnvAccessRules, NetAccessRules};
     use sb_graph::emitter::EmitterFactory;
     use sb_graph::{generate_binary_eszip, EszipPayloadKind};
     use sb_workers::context::{
@@ -588,6 +702,7 @@ mod test {
                     worker_pool_tx,
                     shared_metric_src: None,
                     event_worker_metric_src: None,
+                    fs_access_rules: Default::default(),
                 })
             },
         })
@@ -633,6 +748,7 @@ mod test {
                     worker_pool_tx,
                     shared_metric_src: None,
                     event_worker_metric_src: None,
+                    fs_access_rules: Default::default(),
                 })
             },
         })
@@ -700,6 +816,7 @@ mod test {
                     worker_pool_tx,
                     shared_metric_src: None,
                     event_worker_metric_src: None,
+                    fs_access_rules: Default::default(),
                 })
             },
         })
@@ -763,6 +880,7 @@ mod test {
                         worker_pool_tx,
                         shared_metric_src: None,
                         event_worker_metric_src: None,
+                        fs_access_rules: Default::default(),
                     })
                 }
             },
@@ -1075,12 +1193,22 @@ mod test {
             None,
             Some(WorkerRuntimeOpts::UserWorker(UserWorkerRuntimeOpts {
                 memory_limit_mb: memory_limit,
+                memory_sample_interval_ms: 0,
+                resource_sample_interval_ms: 0,
                 worker_timeout_ms,
+                request_timeout_ms: 0,
+                cpu_time_header: None,
                 cpu_time_soft_limit_ms: 100,
                 cpu_time_hard_limit_ms: 200,
+                shutdown_grace_period_ms: 0,
+                shutdown_grace_cpu_time_ms: 0,
                 low_memory_multiplier: 5,
                 force_create: true,
                 net_access_disabled: false,
+                net_access_rules: NetAccessRules::default(),
+                fs_access_rules: None,
+                env_access_rules: EnvAccessRules::default(),
+                proxy: None,
                 allow_remote_modules: true,
                 custom_module_root: None,
                 key: None,
@@ -1088,6 +1216,13 @@ mod test {
                 events_msg_tx: None,
                 cancel: None,
                 service_path: None,
+                version: None,
+                heap_snapshot: None,
+                cpu_profile_dir: None,
+                cpu_profile_duration_ms: None,
+                log_level: LogLevel::Debug,
+                structured_logs: false,
+                traceparent: None,
             })),
         )
         .await
@@ -1099,15 +1234,9 @@ mod test {
         let mut user_rt = create_basic_user_runtime("./test_cases/readFile", 20, 1000).await;
         let (_tx, unix_stream_rx) = mpsc::unbounded_channel::<UnixStreamEntry>();
 
+        // user workers can read their own service directory
         let (result, _) = user_rt.run(unix_stream_rx, None, None).await;
-        match result {
-            Err(err) => {
-                assert!(err
-                    .to_string()
-                    .contains("TypeError: Deno.readFileSync is not a function"));
-            }
-            _ => panic!("Invalid Result"),
-        };
+        assert!(result.is_ok(), "expected no errors");
     }
 
     #[tokio::test]
@@ -0,0 +1,123 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use deno_core::serde_json;
+use event_worker::events::{EventMetadata, WorkerEvents};
+use log::error;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use super::{EventRecord, FileSinkConfig};
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: u64, // unix ms
+    event_type: &'static str,
+    event: &'a WorkerEvents,
+    metadata: &'a EventMetadata,
+}
+
+pub enum Target {
+    Stdout,
+    File(RotatingFile),
+}
+
+impl Target {
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Stdout => io::stdout().lock().write_all(line),
+            Self::File(file) => file.write_line(line),
+        }
+    }
+}
+
+pub struct RotatingFile {
+    config: FileSinkConfig,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(config: FileSinkConfig) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+
+        let size = file.metadata()?.len();
+
+        Ok(Self { config, file, size })
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let max_size_bytes = self.config.max_size_bytes;
+
+        if max_size_bytes > 0 && self.size > 0 && self.size + line.len() as u64 > max_size_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    fn rotated_path(&self, idx: usize) -> PathBuf {
+        let mut path = OsString::from(self.config.path.as_os_str());
+
+        path.push(format!(".{}", idx));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for idx in (1..self.config.max_files).rev() {
+            match std::fs::rename(self.rotated_path(idx), self.rotated_path(idx + 1)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+
+        if self.config.max_files > 0 {
+            std::fs::rename(&self.config.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.config.path)?;
+
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+/// Writes the events on a blocking thread, one JSON object per line.
+pub fn spawn(mut target: Target, mut rx: mpsc::UnboundedReceiver<Arc<EventRecord>>) {
+    drop(tokio::task::spawn_blocking(move || {
+        while let Some(record) = rx.blocking_recv() {
+            let mut line = match serde_json::to_vec(&JsonLine {
+                timestamp: (record.timestamp_ns / 1_000_000) as u64,
+                event_type: record.event.event.event_type(),
+                event: &record.event.event,
+                metadata: &record.event.metadata,
+            }) {
+                Ok(line) => line,
+                Err(err) => {
+                    error!("failed to serialize the event: {}", err);
+                    continue;
+                }
+            };
+
+            line.push(b'\n');
+
+            if let Err(err) = target.write_line(&line) {
+                error!("failed to write the event: {}", err);
+            }
+        }
+    }));
+}
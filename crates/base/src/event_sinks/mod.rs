@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Error;
+use event_worker::channel::{
+    event_channel, event_channel_with_counters, EventChannelOpts, EventSender,
+};
+use event_worker::events::WorkerEventWithMetadata;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use url::Url;
+
+pub mod json_lines;
+pub mod otlp;
+
+#[derive(Debug, Clone)]
+pub enum EventSinkConfig {
+    /// Writes every event as a JSON line to stdout.
+    Stdout,
+    /// Writes every event as a JSON line to a file that is rotated by size.
+    File(FileSinkConfig),
+    /// Exports the events as OTLP log records over HTTP (JSON encoding).
+    Otlp(OtlpSinkConfig),
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSinkConfig {
+    pub path: PathBuf,
+    pub max_size_bytes: u64, // 0 = never rotated
+    /// Number of rotated files kept next to the active one.
+    pub max_files: usize,
+}
+
+/// Exporter of OTLP over HTTP (JSON encoding), shared by the events sink and
+/// the tracing (see `otel::TracingConfig`).
+#[derive(Debug, Clone)]
+pub struct OtlpSinkConfig {
+    /// Endpoint of the collector for the exported signal (e.g.
+    /// `http://localhost:4318/v1/logs` for the events).
+    pub endpoint: Url,
+    pub headers: Vec<(String, String)>,
+    pub batch_size: usize,
+    pub flush_interval_ms: u64,
+}
+
+impl OtlpSinkConfig {
+    pub fn new(endpoint: Url) -> Self {
+        Self {
+            endpoint,
+            headers: vec![],
+            batch_size: 512,
+            flush_interval_ms: 1000,
+        }
+    }
+}
+
+/// An event along with the time it was received by the sinks.
+#[derive(Debug)]
+pub struct EventRecord {
+    pub timestamp_ns: u128,
+    pub event: WorkerEventWithMetadata,
+}
+
+impl EventRecord {
+    fn new(event: WorkerEventWithMetadata) -> Self {
+        Self {
+            timestamp_ns: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|it| it.as_nanos())
+                .unwrap_or_default(),
+            event,
+        }
+    }
+}
+
+/// Spawns the given sinks and returns the sender the worker events should be
+/// sent to. Every event is delivered to all sinks and, if present, forwarded
+/// to the events worker.
+pub fn spawn_event_sinks(
+    sinks: Vec<EventSinkConfig>,
+    events_worker_tx: Option<EventSender>,
+    channel_opts: EventChannelOpts,
+) -> Result<EventSender, Error> {
+    let sink_txs = sinks
+        .into_iter()
+        .map(spawn_event_sink)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (events_tx, mut events_rx) = match events_worker_tx.as_ref() {
+        Some(tx) => event_channel_with_counters(channel_opts, tx.counters()),
+        None => event_channel(channel_opts),
+    };
+    let handle = Handle::current();
+
+    // NOTE: Forwarding to the events worker may block with
+    // `EventDropPolicy::BlockLogs`, hence the blocking thread.
+    drop(tokio::task::spawn_blocking(move || {
+        while let Some(event) = handle.block_on(events_rx.recv()) {
+            let record = Arc::new(EventRecord::new(match events_worker_tx.as_ref() {
+                Some(tx) => {
+                    let record_event = event.clone();
+                    let _ = tx.send(event);
+                    record_event
+                }
+
+                None => event,
+            }));
+
+            for tx in &sink_txs {
+                let _ = tx.send(record.clone());
+            }
+        }
+    }));
+
+    Ok(events_tx)
+}
+
+fn spawn_event_sink(
+    config: EventSinkConfig,
+) -> Result<mpsc::UnboundedSender<Arc<EventRecord>>, Error> {
+    let (tx, rx) = mpsc::unbounded_channel::<Arc<EventRecord>>();
+
+    match config {
+        EventSinkConfig::Stdout => json_lines::spawn(json_lines::Target::Stdout, rx),
+        EventSinkConfig::File(config) => json_lines::spawn(
+            json_lines::Target::File(json_lines::RotatingFile::open(config)?),
+            rx,
+        ),
+        EventSinkConfig::Otlp(config) => drop(tokio::spawn(otlp::run(config, rx))),
+    }
+
+    Ok(tx)
+}
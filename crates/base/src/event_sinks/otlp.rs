@@ -0,0 +1,152 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Error};
+use deno_core::serde_json::{self, json, Value};
+use event_worker::events::{LogEvent, LogLevel, WorkerEvents};
+use log::error;
+use tokio::sync::mpsc;
+
+use super::{EventRecord, OtlpSinkConfig};
+
+// https://opentelemetry.io/docs/specs/otel/logs/data-model/#field-severitynumber
+fn severity(event: &WorkerEvents) -> (u8, &'static str) {
+    match event {
+        WorkerEvents::Log(LogEvent { level, .. }) => match level {
+            LogLevel::Debug => (5, "DEBUG"),
+            LogLevel::Info => (9, "INFO"),
+            LogLevel::Warning => (13, "WARN"),
+            LogLevel::Error => (17, "ERROR"),
+        },
+
+        WorkerEvents::BootFailure(_) | WorkerEvents::UncaughtException(_) => (17, "ERROR"),
+        WorkerEvents::EventsDropped(_) => (13, "WARN"),
+        _ => (9, "INFO"),
+    }
+}
+
+fn string_attr(key: &str, value: impl Into<String>) -> Value {
+    json!({ "key": key, "value": { "stringValue": value.into() } })
+}
+
+fn to_log_record(record: &EventRecord) -> Value {
+    let event = &record.event.event;
+    let metadata = &record.event.metadata;
+    let (severity_number, severity_text) = severity(event);
+
+    let body = match event {
+        WorkerEvents::Log(LogEvent { msg, .. }) => msg.clone(),
+        event => serde_json::to_string(event).unwrap_or_default(),
+    };
+
+    let mut attributes = vec![string_attr("event.type", event.event_type())];
+
+    if let Some(service_path) = metadata.service_path.as_deref() {
+        attributes.push(string_attr("service.path", service_path));
+    }
+    if let Some(execution_id) = metadata.execution_id {
+        attributes.push(string_attr("execution.id", execution_id.to_string()));
+    }
+    if let Some(version) = metadata.version.as_deref() {
+        attributes.push(string_attr("service.version", version));
+    }
+
+    json!({
+        "timeUnixNano": record.timestamp_ns.to_string(),
+        "observedTimeUnixNano": record.timestamp_ns.to_string(),
+        "severityNumber": severity_number,
+        "severityText": severity_text,
+        "body": { "stringValue": body },
+        "attributes": attributes,
+    })
+}
+
+fn encode(batch: &[Arc<EventRecord>]) -> Value {
+    json!({
+        "resourceLogs": [{
+            "resource": {
+                "attributes": [string_attr("service.name", "edge-runtime")],
+            },
+            "scopeLogs": [{
+                "scope": { "name": "edge-runtime" },
+                "logRecords": batch.iter().map(|it| to_log_record(it)).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+async fn export(
+    client: &reqwest::Client,
+    config: &OtlpSinkConfig,
+    payload: &Value,
+) -> Result<(), Error> {
+    let mut req = client.post(config.endpoint.clone()).json(payload);
+
+    for (key, value) in &config.headers {
+        req = req.header(key, value);
+    }
+
+    let res = req.send().await?;
+
+    if !res.status().is_success() {
+        bail!("otlp collector responded with {}", res.status());
+    }
+
+    Ok(())
+}
+
+/// Batches the items and exports them as the payload built by `encode` once
+/// the batch is full or the flush interval has elapsed.
+pub async fn run_exporter<T, F>(
+    config: OtlpSinkConfig,
+    mut rx: mpsc::UnboundedReceiver<T>,
+    encode: F,
+) where
+    F: Fn(&[T]) -> Value,
+{
+    let client = reqwest::Client::new();
+    let batch_size = config.batch_size.max(1);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut interval =
+        tokio::time::interval(Duration::from_millis(config.flush_interval_ms.max(1)));
+
+    loop {
+        let is_closed = tokio::select! {
+            item = rx.recv() => match item {
+                Some(item) => {
+                    batch.push(item);
+
+                    if batch.len() < batch_size {
+                        continue;
+                    }
+
+                    false
+                }
+
+                None => true,
+            },
+
+            _ = interval.tick() => false,
+        };
+
+        if !batch.is_empty() {
+            if let Err(err) = export(&client, &config, &encode(&batch)).await {
+                error!(
+                    "failed to export to the otlp collector ({}): {}",
+                    config.endpoint, err
+                );
+            }
+
+            batch.clear();
+        }
+
+        if is_closed {
+            break;
+        }
+    }
+}
+
+/// Exports the events as OTLP log records.
+pub async fn run(config: OtlpSinkConfig, rx: mpsc::UnboundedReceiver<Arc<EventRecord>>) {
+    run_exporter(config, rx, encode).await
+}
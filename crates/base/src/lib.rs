@@ -2,7 +2,9 @@ extern crate core;
 
 pub mod commands;
 pub mod deno_runtime;
+pub mod event_sinks;
 pub mod macros;
+pub mod otel;
 pub mod rt_worker;
 pub mod server;
 pub mod snapshot;
@@ -24,6 +24,8 @@ macro_rules! integration_test {
                 $port,
                 String::from($main_file),
                 None,
+                vec![],
+                Default::default(),
                 $shot_policy,
                 $import_map,
                 false,
@@ -32,6 +34,7 @@ macro_rules! integration_test {
                     main: None,
                     events: None,
                 },
+                Default::default(),
                 integration_test!(@term $(, $termination_token)?)
             ) => {
                 panic!("This one should not end first");
@@ -0,0 +1,68 @@
+use deno_core::serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use crate::event_sinks::otlp::run_exporter;
+
+use super::{encode_hex, AttributeValue, SpanData, SpanKind, TracingConfig};
+
+fn to_attribute(key: &str, value: &AttributeValue) -> Value {
+    let value = match value {
+        AttributeValue::String(it) => json!({ "stringValue": it }),
+        // NOTE: 64-bit integers are encoded as strings in OTLP/JSON.
+        AttributeValue::Int(it) => json!({ "intValue": it.to_string() }),
+        AttributeValue::Bool(it) => json!({ "boolValue": it }),
+    };
+
+    json!({ "key": key, "value": value })
+}
+
+// https://opentelemetry.io/docs/specs/otel/trace/api/#spankind
+fn kind(kind: SpanKind) -> u8 {
+    match kind {
+        SpanKind::Internal => 1,
+        SpanKind::Server => 2,
+        SpanKind::Client => 3,
+    }
+}
+
+fn to_span(span: &SpanData) -> Value {
+    let status = match span.error.as_deref() {
+        Some(message) => json!({ "code": 2, "message": message }),
+        None => json!({ "code": 0 }),
+    };
+
+    json!({
+        "traceId": encode_hex(&span.context.trace_id),
+        "spanId": encode_hex(&span.context.span_id),
+        "parentSpanId": span.parent_span_id.as_ref().map(|it| encode_hex(it)).unwrap_or_default(),
+        "name": span.name,
+        "kind": kind(span.kind),
+        "startTimeUnixNano": span.start_time_ns.to_string(),
+        "endTimeUnixNano": span.end_time_ns.to_string(),
+        "attributes": span
+            .attributes
+            .iter()
+            .map(|(key, value)| to_attribute(key, value))
+            .collect::<Vec<_>>(),
+        "status": status,
+    })
+}
+
+fn encode(batch: &[SpanData]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [to_attribute("service.name", &"edge-runtime".into())],
+            },
+            "scopeSpans": [{
+                "scope": { "name": "edge-runtime" },
+                "spans": batch.iter().map(to_span).collect::<Vec<_>>(),
+            }],
+        }],
+    })
+}
+
+/// Exports the ended spans as OTLP spans.
+pub async fn run(config: TracingConfig, rx: mpsc::UnboundedReceiver<SpanData>) {
+    run_exporter(config, rx, encode).await
+}
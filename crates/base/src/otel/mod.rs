@@ -0,0 +1,258 @@
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Error};
+use hyper::header::{HeaderMap, HeaderValue};
+use once_cell::sync::OnceCell;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::event_sinks::OtlpSinkConfig;
+
+pub mod exporter;
+
+/// W3C trace context header (https://www.w3.org/TR/trace-context/).
+pub const TRACEPARENT: &str = "traceparent";
+
+static SPAN_EXPORTER_TX: OnceCell<mpsc::UnboundedSender<SpanData>> = OnceCell::new();
+
+/// Exporter of the spans. The endpoint is the traces one of the collector
+/// (e.g. `http://localhost:4318/v1/traces`).
+pub type TracingConfig = OtlpSinkConfig;
+
+/// Starts exporting the spans to the collector. Until this is called, spans
+/// are no-ops and no trace context is propagated.
+pub fn init_tracing(config: TracingConfig) -> Result<(), Error> {
+    let (tx, rx) = mpsc::unbounded_channel::<SpanData>();
+
+    if SPAN_EXPORTER_TX.set(tx).is_err() {
+        bail!("tracing is already initialized");
+    }
+
+    drop(tokio::spawn(exporter::run(config, rx)));
+    Ok(())
+}
+
+pub fn is_tracing_enabled() -> bool {
+    SPAN_EXPORTER_TX.get().is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpanContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub is_sampled: bool,
+}
+
+impl SpanContext {
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let version = parts.next()?;
+        let trace_id = decode_hex::<16>(parts.next()?)?;
+        let span_id = decode_hex::<8>(parts.next()?)?;
+        let [flags] = decode_hex::<1>(parts.next()?)?;
+
+        // NOTE: Later versions may append fields, version 00 must not.
+        if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+            return None;
+        }
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+
+        Some(Self {
+            trace_id,
+            span_id,
+            is_sampled: flags & 1 == 1,
+        })
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get(TRACEPARENT)
+            .and_then(|it| it.to_str().ok())
+            .and_then(Self::from_traceparent)
+    }
+
+    pub fn to_traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            encode_hex(&self.trace_id),
+            encode_hex(&self.span_id),
+            self.is_sampled as u8
+        )
+    }
+
+    /// Sets the `traceparent` header, replacing the one of the caller.
+    pub fn inject(&self, headers: &mut HeaderMap) {
+        if let Ok(value) = HeaderValue::from_str(&self.to_traceparent()) {
+            headers.insert(TRACEPARENT, value);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SpanKind {
+    Internal,
+    Server,
+    Client,
+}
+
+#[derive(Debug, Clone)]
+pub enum AttributeValue {
+    String(String),
+    Int(i64),
+    Bool(bool),
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<u16> for AttributeValue {
+    fn from(value: u16) -> Self {
+        Self::Int(value as i64)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+#[derive(Debug)]
+pub struct SpanData {
+    pub context: SpanContext,
+    pub parent_span_id: Option<[u8; 8]>,
+    pub name: String,
+    pub kind: SpanKind,
+    pub start_time_ns: u128,
+    pub end_time_ns: u128,
+    pub attributes: Vec<(&'static str, AttributeValue)>,
+    pub error: Option<String>,
+}
+
+/// A span that is exported once it is dropped (or explicitly ended). It is a
+/// no-op if tracing is not enabled.
+#[derive(Debug)]
+pub struct Span {
+    data: Option<SpanData>,
+}
+
+impl Span {
+    pub fn start(name: impl Into<String>, kind: SpanKind, parent: Option<SpanContext>) -> Self {
+        if !is_tracing_enabled() {
+            return Self { data: None };
+        }
+
+        let context = SpanContext {
+            trace_id: parent
+                .map(|it| it.trace_id)
+                .unwrap_or_else(|| *Uuid::new_v4().as_bytes()),
+            span_id: new_span_id(),
+            is_sampled: parent.map_or(true, |it| it.is_sampled),
+        };
+
+        Self {
+            data: Some(SpanData {
+                context,
+                parent_span_id: parent.map(|it| it.span_id),
+                name: name.into(),
+                kind,
+                start_time_ns: now_ns(),
+                end_time_ns: 0,
+                attributes: vec![],
+                error: None,
+            }),
+        }
+    }
+
+    /// Context to be propagated to the children of this span.
+    pub fn context(&self) -> Option<SpanContext> {
+        self.data.as_ref().map(|it| it.context)
+    }
+
+    pub fn set_attribute(&mut self, key: &'static str, value: impl Into<AttributeValue>) {
+        if let Some(data) = self.data.as_mut() {
+            data.attributes.push((key, value.into()));
+        }
+    }
+
+    pub fn set_error(&mut self, message: impl ToString) {
+        if let Some(data) = self.data.as_mut() {
+            data.error = Some(message.to_string());
+        }
+    }
+
+    pub fn end(self) {}
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(mut data) = self.data.take() else {
+            return;
+        };
+
+        if !data.context.is_sampled {
+            return;
+        }
+
+        data.end_time_ns = now_ns();
+
+        if let Some(tx) = SPAN_EXPORTER_TX.get() {
+            let _ = tx.send(data);
+        }
+    }
+}
+
+fn new_span_id() -> [u8; 8] {
+    let mut span_id = [0; 8];
+
+    span_id.copy_from_slice(&Uuid::new_v4().as_bytes()[..8]);
+    span_id
+}
+
+fn now_ns() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.as_nanos())
+        .unwrap_or_default()
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut acc, it| {
+            let _ = write!(acc, "{:02x}", it);
+            acc
+        })
+}
+
+fn decode_hex<const N: usize>(value: &str) -> Option<[u8; N]> {
+    if value.len() != N * 2 || !value.is_ascii() {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(bytes)
+}
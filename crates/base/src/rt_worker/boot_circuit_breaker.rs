@@ -0,0 +1,169 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use sb_workers::errors::{WorkerBootError, WorkerError};
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BootCircuitBreakerPolicy {
+    /// Count of boot failures within `window_ms` that trips the breaker.
+    pub failure_threshold: usize,
+    pub window_ms: u64,
+    /// How long the service is quarantined before a single probe is let
+    /// through.
+    pub cooldown_ms: u64,
+}
+
+impl Default for BootCircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            window_ms: 30 * 1000,
+            cooldown_ms: 60 * 1000,
+        }
+    }
+}
+
+enum BreakerState {
+    Closed {
+        failures: VecDeque<Instant>,
+    },
+    Open {
+        until: Instant,
+        error: WorkerBootError,
+    },
+    HalfOpen {
+        probe_started_at: Instant,
+        error: WorkerBootError,
+    },
+}
+
+pub enum BreakerTransition {
+    Tripped {
+        failures: usize,
+        error: WorkerBootError,
+    },
+    Reset,
+}
+
+/// Circuit breakers for the user worker boot, keyed by service path.
+pub struct BootCircuitBreakers {
+    policy: BootCircuitBreakerPolicy,
+    breakers: HashMap<String, BreakerState>,
+}
+
+impl BootCircuitBreakers {
+    pub fn new(policy: BootCircuitBreakerPolicy) -> Self {
+        Self {
+            policy,
+            breakers: HashMap::new(),
+        }
+    }
+
+    /// Checks whether a worker for the service can be created. In the
+    /// half-open state, only one caller is allowed to probe the service at a
+    /// time. Returns `true` if the caller became that probe, in which case it
+    /// must either report the boot or release the probe.
+    pub fn try_acquire(&mut self, service_path: &str) -> Result<bool, WorkerError> {
+        let Some(state) = self.breakers.get_mut(service_path) else {
+            return Ok(false);
+        };
+
+        let now = Instant::now();
+        let cooldown = Duration::from_millis(self.policy.cooldown_ms);
+
+        match state {
+            BreakerState::Closed { .. } => Ok(false),
+            BreakerState::Open { until, error } if now < *until => {
+                Err(WorkerError::ServiceQuarantined(error.clone()))
+            }
+
+            // NOTE: The probe might never report back (e.g. the request
+            // waiting for a permit timed out before the worker was created),
+            // so another probe is allowed once the cooldown has passed again.
+            BreakerState::HalfOpen {
+                probe_started_at,
+                error,
+            } if now.duration_since(*probe_started_at) < cooldown => {
+                Err(WorkerError::ServiceQuarantined(error.clone()))
+            }
+
+            BreakerState::Open { error, .. } | BreakerState::HalfOpen { error, .. } => {
+                *state = BreakerState::HalfOpen {
+                    probe_started_at: now,
+                    error: error.clone(),
+                };
+
+                Ok(true)
+            }
+        }
+    }
+
+    /// Lets the next caller probe the service right away, as the current
+    /// probe ended without booting a worker.
+    pub fn release_probe(&mut self, service_path: &str) {
+        if let Some(state) = self.breakers.get_mut(service_path) {
+            if let BreakerState::HalfOpen { error, .. } = state {
+                *state = BreakerState::Open {
+                    until: Instant::now(),
+                    error: error.clone(),
+                };
+            }
+        }
+    }
+
+    pub fn record_success(&mut self, service_path: &str) -> Option<BreakerTransition> {
+        match self.breakers.remove(service_path)? {
+            BreakerState::Closed { .. } => None,
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => {
+                Some(BreakerTransition::Reset)
+            }
+        }
+    }
+
+    pub fn record_failure(
+        &mut self,
+        service_path: &str,
+        error: WorkerBootError,
+    ) -> Option<BreakerTransition> {
+        let now = Instant::now();
+        let window = Duration::from_millis(self.policy.window_ms);
+        let state = self
+            .breakers
+            .entry(service_path.to_string())
+            .or_insert_with(|| BreakerState::Closed {
+                failures: VecDeque::new(),
+            });
+
+        let failures = match state {
+            BreakerState::Closed { failures } => {
+                while failures
+                    .front()
+                    .map_or(false, |it| now.duration_since(*it) > window)
+                {
+                    failures.pop_front();
+                }
+
+                failures.push_back(now);
+
+                if failures.len() < self.policy.failure_threshold {
+                    return None;
+                }
+
+                failures.len()
+            }
+
+            // NOTE: Workers that were being created before the breaker tripped
+            // can still fail afterwards. They must not extend the cooldown.
+            BreakerState::Open { .. } => return None,
+            BreakerState::HalfOpen { .. } => 1,
+        };
+
+        *state = BreakerState::Open {
+            until: now + Duration::from_millis(self.policy.cooldown_ms),
+            error: error.clone(),
+        };
+
+        Some(BreakerTransition::Tripped { failures, error })
+    }
+}
@@ -0,0 +1,278 @@
+use std::future::{pending, poll_fn};
+use std::path::{Path, PathBuf};
+use std::task::Poll;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Error};
+use deno_core::futures::channel::mpsc as futures_mpsc;
+use deno_core::futures::StreamExt;
+use deno_core::serde_json::{self, json, Value};
+use deno_core::{
+    InspectorMsg, InspectorMsgKind, InspectorSessionProxy, JsRuntime, JsRuntimeInspector,
+};
+use futures_util::FutureExt;
+use log::{error, info};
+use sb_workers::context::CpuProfileRequest;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::rt;
+
+// NOTE: Bounds the time the termination of a worker is held back to write its
+// last profile.
+static STOP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Drives the V8 CPU profiler of a worker through an inspector session.
+///
+/// The isolate serves the session while its event loop is polled, or through
+/// an interrupt while it runs, so the profiler is driven from the supervisor
+/// runtime. This lets the supervisor stop the profile in progress right before
+/// it terminates the worker (see [`CpuProfilerStop`]).
+pub struct CpuProfiler {
+    stop: CpuProfilerStop,
+}
+
+impl CpuProfiler {
+    pub fn new(
+        inspector: &JsRuntimeInspector,
+        key: Uuid,
+        dir: PathBuf,
+        initial_duration_ms: Option<u64>,
+        requests_rx: mpsc::UnboundedReceiver<CpuProfileRequest>,
+    ) -> Self {
+        let session = InspectorSession::connect(inspector);
+        let stop = CpuProfilerStop {
+            key,
+            requested: CancellationToken::new(),
+            done: CancellationToken::new(),
+        };
+
+        let initial_req = initial_duration_ms.map(|duration_ms| CpuProfileRequest {
+            duration_ms,
+            result_tx: None,
+        });
+
+        drop(rt::SUPERVISOR_RT.spawn(drive(
+            key,
+            dir,
+            session,
+            initial_req,
+            requests_rx,
+            stop.clone(),
+        )));
+
+        Self { stop }
+    }
+
+    pub fn stopper(&self) -> CpuProfilerStop {
+        self.stop.clone()
+    }
+
+    /// Stops the profile in progress, if any, and waits until it is written.
+    ///
+    /// The event loop of the runtime is not polled anymore at this point, so
+    /// the inspector session is served here in the meantime.
+    pub async fn shutdown(self, js_runtime: &mut JsRuntime) {
+        let inspector = js_runtime.inspector();
+        let stop_fut = self.stop.stop();
+
+        tokio::pin!(stop_fut);
+
+        poll_fn(|cx| {
+            if stop_fut.poll_unpin(cx).is_ready() {
+                return Poll::Ready(());
+            }
+
+            unsafe { js_runtime.v8_isolate().enter() };
+            let _ = inspector.borrow().poll_sessions(Some(cx));
+            unsafe { js_runtime.v8_isolate().exit() };
+
+            Poll::Pending
+        })
+        .await;
+    }
+}
+
+/// Stops the profile in progress of a worker, if any, and waits until it is
+/// written.
+#[derive(Debug, Clone)]
+pub struct CpuProfilerStop {
+    key: Uuid,
+    requested: CancellationToken,
+    done: CancellationToken,
+}
+
+impl CpuProfilerStop {
+    pub async fn stop(&self) {
+        self.requested.cancel();
+
+        if tokio::time::timeout(STOP_TIMEOUT, self.done.cancelled())
+            .await
+            .is_err()
+        {
+            error!(
+                "timed out while writing the cpu profile. isolate: {:?}",
+                self.key
+            );
+        }
+    }
+}
+
+/// A raw inspector session. Unlike `LocalInspectorSession`, it can be driven
+/// from any thread.
+struct InspectorSession {
+    tx: futures_mpsc::UnboundedSender<String>,
+    rx: futures_mpsc::UnboundedReceiver<InspectorMsg>,
+    next_message_id: i32,
+}
+
+impl InspectorSession {
+    fn connect(inspector: &JsRuntimeInspector) -> Self {
+        let (outbound_tx, outbound_rx) = futures_mpsc::unbounded();
+        let (inbound_tx, inbound_rx) = futures_mpsc::unbounded();
+
+        // NOTE: The session is accepted the next time the isolate serves the
+        // inspector, and the messages posted meanwhile are queued.
+        let _ = inspector
+            .get_session_sender()
+            .unbounded_send(InspectorSessionProxy {
+                tx: inbound_tx,
+                rx: outbound_rx,
+            });
+
+        Self {
+            tx: outbound_tx,
+            rx: inbound_rx,
+            next_message_id: 0,
+        }
+    }
+
+    async fn post_message(&mut self, method: &str) -> Result<Value, Error> {
+        let id = self.next_message_id;
+
+        self.next_message_id += 1;
+        self.tx
+            .unbounded_send(json!({ "id": id, "method": method }).to_string())
+            .context("inspector session closed")?;
+
+        loop {
+            let msg = self.rx.next().await.context("inspector session closed")?;
+
+            if !matches!(msg.kind, InspectorMsgKind::Message(msg_id) if msg_id == id) {
+                continue;
+            }
+
+            let mut msg: Value = serde_json::from_str(&msg.content)?;
+
+            if let Some(error) = msg.get("error") {
+                bail!("{}", error);
+            }
+
+            return Ok(msg.get_mut("result").map(Value::take).unwrap_or_default());
+        }
+    }
+}
+
+async fn drive(
+    key: Uuid,
+    dir: PathBuf,
+    mut session: InspectorSession,
+    mut next_req: Option<CpuProfileRequest>,
+    mut requests_rx: mpsc::UnboundedReceiver<CpuProfileRequest>,
+    stop: CpuProfilerStop,
+) {
+    let _done = stop.done.clone().drop_guard();
+
+    loop {
+        let req = match next_req.take() {
+            Some(req) => req,
+            None => tokio::select! {
+                Some(req) = requests_rx.recv() => req,
+                _ = stop.requested.cancelled() => return,
+            },
+        };
+
+        let CpuProfileRequest {
+            duration_ms,
+            result_tx,
+        } = req;
+
+        if let Err(err) = start_profile(&mut session).await {
+            error!("failed to start the cpu profiler: {}", err);
+
+            if let Some(tx) = result_tx {
+                let _ = tx.send(Err(err));
+            }
+
+            continue;
+        }
+
+        let is_stopping = loop {
+            tokio::select! {
+                _ = async {
+                    match duration_ms {
+                        0 => pending().await,
+                        ms => tokio::time::sleep(Duration::from_millis(ms)).await,
+                    }
+                } => break false,
+
+                _ = stop.requested.cancelled() => break true,
+
+                Some(req) = requests_rx.recv() => {
+                    if let Some(tx) = req.result_tx {
+                        let _ = tx.send(Err(anyhow!("cpu profiler is already running")));
+                    }
+                }
+            }
+        };
+
+        let result = stop_profile(&mut session, key, &dir).await;
+
+        match result.as_ref() {
+            Ok(path) => info!("cpu profile written: {}", path.display()),
+            Err(err) => error!("failed to write the cpu profile: {}", err),
+        }
+
+        if let Some(tx) = result_tx {
+            let _ = tx.send(result);
+        }
+
+        if is_stopping {
+            return;
+        }
+    }
+}
+
+async fn start_profile(session: &mut InspectorSession) -> Result<(), Error> {
+    session.post_message("Profiler.enable").await?;
+    session.post_message("Profiler.start").await?;
+
+    Ok(())
+}
+
+async fn stop_profile(
+    session: &mut InspectorSession,
+    key: Uuid,
+    dir: &Path,
+) -> Result<PathBuf, Error> {
+    let mut result = session.post_message("Profiler.stop").await?;
+    let _ = session.post_message("Profiler.disable").await;
+
+    let profile = result
+        .get_mut("profile")
+        .map(Value::take)
+        .context("profiler returned no profile")?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.as_millis())
+        .unwrap_or_default();
+
+    let path = dir.join(format!("{}-{}.cpuprofile", key, timestamp));
+
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(&path, serde_json::to_vec(&profile)?).await?;
+
+    Ok(path)
+}
@@ -10,10 +10,10 @@ use tokio::sync::oneshot::Receiver;
 
 impl WorkerHandler for Worker {
     fn handle_error(&self, error: Error) -> Result<WorkerEvents, Error> {
-        log::error!("{}", error);
-        Ok(WorkerEvents::BootFailure(BootFailureEvent {
-            msg: error.to_string(),
-        }))
+        let msg = self.log_redactions.redact(&error.to_string());
+
+        log::error!("{}", msg);
+        Ok(WorkerEvents::BootFailure(BootFailureEvent { msg }))
     }
 
     fn handle_creation(
@@ -24,6 +24,7 @@ impl WorkerHandler for Worker {
         maybe_cpu_usage_metrics_tx: Option<UnboundedSender<CPUUsageMetrics>>,
         name: Option<String>,
     ) -> HandleCreationType {
+        let log_redactions = self.log_redactions.clone();
         let run_worker_rt = async move {
             let result = match created_rt
                 .run(unix_stream_rx, maybe_cpu_usage_metrics_tx, name)
@@ -31,7 +32,7 @@ impl WorkerHandler for Worker {
             {
                 // if the error is execution terminated, check termination event reason
                 (Err(err), cpu_usage_ns) => {
-                    let err_string = err.to_string();
+                    let err_string = log_redactions.redact(&err.to_string());
 
                     if err_string.ends_with("execution terminated") {
                         Ok(termination_event_rx.await.unwrap())
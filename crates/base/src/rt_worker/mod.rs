@@ -1,3 +1,5 @@
+pub mod boot_circuit_breaker;
+pub mod cpu_profiler;
 pub mod implementation;
 pub mod rt;
 pub mod supervisor;
@@ -5,3 +7,4 @@ pub mod utils;
 pub mod worker;
 pub mod worker_ctx;
 pub mod worker_pool;
+pub mod worker_pool_config;
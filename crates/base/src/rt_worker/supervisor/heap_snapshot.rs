@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Error};
+use deno_core::v8::IsolateHandle;
+use futures_util::task::AtomicWaker;
+use log::{error, info};
+use sb_workers::context::HeapSnapshotOpts;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+// NOTE: Snapshots are rate limited process-wide, since writing them is
+// expensive and a misbehaving service could otherwise fill up the disk.
+static LAST_HEAP_SNAPSHOT: Mutex<Option<Instant>> = Mutex::new(None);
+
+fn try_acquire(opts: &HeapSnapshotOpts) -> bool {
+    let mut last = LAST_HEAP_SNAPSHOT.lock().unwrap();
+    let now = Instant::now();
+
+    if let Some(last) = *last {
+        if now.duration_since(last) < Duration::from_millis(opts.min_interval_ms) {
+            return false;
+        }
+    }
+
+    *last = Some(now);
+    true
+}
+
+struct HeapSnapshotInterruptData {
+    path: PathBuf,
+    max_size_bytes: usize,
+    result_tx: oneshot::Sender<Option<PathBuf>>,
+}
+
+fn write_heap_snapshot(
+    isolate: &mut deno_core::v8::Isolate,
+    path: &Path,
+    max_size_bytes: usize,
+) -> Result<(), Error> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut written = 0usize;
+    let mut result = Ok(());
+
+    isolate.take_heap_snapshot(|chunk| {
+        written += chunk.len();
+
+        if written > max_size_bytes {
+            result = Err(anyhow::anyhow!(
+                "heap snapshot exceeds the size limit of {} bytes",
+                max_size_bytes
+            ));
+
+            return false;
+        }
+
+        if let Err(err) = file.write_all(chunk) {
+            result = Err(err.into());
+            return false;
+        }
+
+        true
+    });
+
+    result?;
+    file.flush()?;
+
+    Ok(())
+}
+
+extern "C" fn handle_heap_snapshot_interrupt(
+    isolate: &mut deno_core::v8::Isolate,
+    data: *mut std::ffi::c_void,
+) {
+    let data = unsafe { Box::from_raw(data as *mut HeapSnapshotInterruptData) };
+    let HeapSnapshotInterruptData {
+        path,
+        max_size_bytes,
+        result_tx,
+    } = *data;
+
+    let result = match write_heap_snapshot(isolate, &path, max_size_bytes) {
+        Ok(_) => Some(path),
+        Err(err) => {
+            error!("failed to write heap snapshot: {}", err);
+            let _ = std::fs::remove_file(&path);
+            None
+        }
+    };
+
+    let _ = result_tx.send(result);
+}
+
+/// Writes a heap snapshot of the isolate into the configured directory.
+/// Returns the path of the snapshot, or `None` if it has been skipped or
+/// could not be written.
+pub async fn take_heap_snapshot(
+    key: Uuid,
+    opts: &HeapSnapshotOpts,
+    thread_safe_handle: &IsolateHandle,
+    waker: &AtomicWaker,
+) -> Option<PathBuf> {
+    if !try_acquire(opts) {
+        error!(
+            "heap snapshot skipped due to the rate limit. isolate: {:?}",
+            key
+        );
+        return None;
+    }
+
+    if let Err(err) = std::fs::create_dir_all(&opts.dir) {
+        error!("failed to create heap snapshot directory: {}", err);
+        return None;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|it| it.as_millis())
+        .unwrap_or_default();
+
+    let (result_tx, result_rx) = oneshot::channel::<Option<PathBuf>>();
+    let data_ptr_mut = Box::into_raw(Box::new(HeapSnapshotInterruptData {
+        path: opts.dir.join(format!("{}-{}.heapsnapshot", key, timestamp)),
+        max_size_bytes: opts.max_size_bytes,
+        result_tx,
+    }));
+
+    if !thread_safe_handle.request_interrupt(
+        handle_heap_snapshot_interrupt,
+        data_ptr_mut as *mut std::ffi::c_void,
+    ) {
+        drop(unsafe { Box::from_raw(data_ptr_mut) });
+        return None;
+    }
+
+    waker.wake();
+
+    let path = result_rx.await.ok().flatten()?;
+
+    info!("heap snapshot written: {}", path.display());
+    Some(path)
+}
+
+/// Forwards the memory limit notifications to the supervisor strategy,
+/// taking a heap snapshot before the first one is forwarded.
+#[allow(clippy::too_many_arguments)]
+pub async fn relay_memory_limit(
+    key: Uuid,
+    opts: HeapSnapshotOpts,
+    thread_safe_handle: IsolateHandle,
+    waker: Arc<AtomicWaker>,
+    mut memory_limit_rx: mpsc::UnboundedReceiver<()>,
+    memory_limit_tx: mpsc::UnboundedSender<()>,
+    heap_snapshot_path: Arc<Mutex<Option<PathBuf>>>,
+) -> Result<(), Error> {
+    if memory_limit_rx.recv().await.is_none() {
+        return Ok(());
+    }
+
+    let path = tokio::select! {
+        _ = memory_limit_tx.closed() => return Ok(()),
+        path = take_heap_snapshot(key, &opts, &thread_safe_handle, &waker) => path,
+    };
+
+    *heap_snapshot_path.lock().unwrap() = path;
+
+    loop {
+        if memory_limit_tx.send(()).is_err() {
+            bail!("memory limit receiver dropped");
+        }
+
+        if memory_limit_rx.recv().await.is_none() {
+            return Ok(());
+        }
+    }
+}
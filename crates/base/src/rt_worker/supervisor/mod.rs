@@ -1,21 +1,37 @@
+pub mod heap_snapshot;
 pub mod strategy_per_request;
 pub mod strategy_per_worker;
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use cpu_timer::{CPUAlarmVal, CPUTimer};
 use deno_core::v8::IsolateHandle;
 use enum_as_inner::EnumAsInner;
+use event_worker::channel::EventSender;
+use event_worker::events::{
+    EventMetadata, ResourceSampleEvent, ShutdownReason, WorkerEventWithMetadata, WorkerEvents,
+    WorkerMemoryUsed,
+};
+use futures_util::future::BoxFuture;
 use futures_util::task::AtomicWaker;
 use log::error;
-use sb_workers::context::{Timing, UserWorkerMsgs, UserWorkerRuntimeOpts};
+use sb_workers::context::{
+    BeforeUnloadSignal, RequestStart, Timing, TimingStatus, UserWorkerMsgs, UserWorkerRuntimeOpts,
+};
 use tokio::sync::{
-    mpsc::{self, UnboundedReceiver},
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
     oneshot,
 };
+use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use super::{worker_ctx::TerminationToken, worker_pool::SupervisorPolicy};
+use super::{
+    cpu_profiler::CpuProfilerStop, worker_ctx::TerminationToken, worker_pool::SupervisorPolicy,
+};
 
 #[repr(C)]
 pub struct IsolateInterruptData {
@@ -60,6 +76,165 @@ pub struct IsolateMemoryStats {
     pub external_memory: usize,
 }
 
+/// Tracks the peak memory usage of an isolate across samples.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryHighWaterMark {
+    heap: Arc<AtomicUsize>,
+    external: Arc<AtomicUsize>,
+    total: Arc<AtomicUsize>,
+}
+
+impl MemoryHighWaterMark {
+    pub fn update(&self, stats: &IsolateMemoryStats) {
+        let total = stats.used_heap_size + stats.external_memory;
+
+        self.heap.fetch_max(stats.used_heap_size, Ordering::AcqRel);
+        self.external
+            .fetch_max(stats.external_memory, Ordering::AcqRel);
+        self.total.fetch_max(total, Ordering::AcqRel);
+    }
+
+    pub fn get(&self) -> WorkerMemoryUsed {
+        WorkerMemoryUsed {
+            total: self.total.load(Ordering::Acquire),
+            heap: self.heap.load(Ordering::Acquire),
+            external: self.external.load(Ordering::Acquire),
+        }
+    }
+}
+
+fn request_memory_stats(
+    thread_safe_handle: &IsolateHandle,
+    waker: &AtomicWaker,
+) -> Option<oneshot::Receiver<IsolateMemoryStats>> {
+    let (tx, rx) = oneshot::channel::<IsolateMemoryStats>();
+    let data_ptr_mut = Box::into_raw(Box::new(IsolateInterruptData {
+        should_terminate: false,
+        isolate_memory_usage_tx: Some(tx),
+    }));
+
+    if !thread_safe_handle
+        .request_interrupt(handle_interrupt, data_ptr_mut as *mut std::ffi::c_void)
+    {
+        drop(unsafe { Box::from_raw(data_ptr_mut) });
+        return None;
+    }
+
+    waker.wake();
+    Some(rx)
+}
+
+/// Periodically samples the heap and external memory of the isolate and
+/// notifies the supervisor through `memory_limit_tx` once their sum exceeds
+/// `memory_limit`.
+///
+/// The sampler stops as soon as the supervisor drops the receiving half of
+/// `memory_limit_tx`.
+pub async fn sample_memory_usage(
+    key: Uuid,
+    thread_safe_handle: IsolateHandle,
+    waker: Arc<AtomicWaker>,
+    memory_limit: usize,
+    interval: Duration,
+    high_water_mark: MemoryHighWaterMark,
+    memory_limit_tx: mpsc::UnboundedSender<()>,
+) {
+    let mut interval = tokio::time::interval(interval);
+
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = memory_limit_tx.closed() => break,
+            _ = interval.tick() => {}
+        }
+
+        let Some(rx) = request_memory_stats(&thread_safe_handle, &waker) else {
+            break;
+        };
+
+        let stats = tokio::select! {
+            _ = memory_limit_tx.closed() => break,
+            result = rx => match result {
+                Ok(stats) => stats,
+                Err(_) => break,
+            }
+        };
+
+        high_water_mark.update(&stats);
+
+        if stats.used_heap_size + stats.external_memory >= memory_limit {
+            error!(
+                "memory limit reached (heap: {}, external: {}). isolate: {:?}",
+                stats.used_heap_size, stats.external_memory, key
+            );
+
+            let _ = memory_limit_tx.send(());
+            break;
+        }
+    }
+}
+
+/// Periodically reports the resource usage of the worker as a
+/// `ResourceSample` event until `token` is cancelled.
+#[allow(clippy::too_many_arguments)]
+pub async fn sample_resource_usage(
+    thread_safe_handle: IsolateHandle,
+    waker: Arc<AtomicWaker>,
+    interval: Duration,
+    status: TimingStatus,
+    high_water_mark: MemoryHighWaterMark,
+    events_msg_tx: EventSender,
+    event_metadata: EventMetadata,
+    token: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(interval);
+
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+
+        let Some(rx) = request_memory_stats(&thread_safe_handle, &waker) else {
+            break;
+        };
+
+        let stats = tokio::select! {
+            _ = token.cancelled() => break,
+            result = rx => match result {
+                Ok(stats) => stats,
+                Err(_) => break,
+            }
+        };
+
+        high_water_mark.update(&stats);
+
+        let event = WorkerEvents::ResourceSample(ResourceSampleEvent {
+            cpu_time_used: status.cpu_time_used_ms.load(Ordering::Acquire),
+            memory_used: WorkerMemoryUsed {
+                total: stats.used_heap_size + stats.external_memory,
+                heap: stats.used_heap_size,
+                external: stats.external_memory,
+            },
+            inflight_requests: status.cpu_clock.active(),
+            served_requests: status.served_requests.load(Ordering::Acquire),
+        });
+
+        if events_msg_tx
+            .send(WorkerEventWithMetadata {
+                event,
+                metadata: event_metadata.clone(),
+            })
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct CPUTimerParam {
     soft_limit_ms: u64,
@@ -112,6 +287,18 @@ impl CPUTimerParam {
     }
 }
 
+/// Decides when a user worker must be terminated, based on the signals it
+/// receives through [`Arguments`] (CPU usage, wall clock, memory, requests).
+///
+/// The `SupervisorPolicy` of the pool still decides how requests are routed
+/// to the workers; the strategy only supervises each worker. Implementations
+/// must stop the CPU profiler (if any) and then terminate the isolate (see
+/// [`handle_interrupt`]) before resolving, and resolve with the shutdown
+/// reason and the CPU time used in milliseconds.
+pub trait SupervisorStrategy: Send + Sync + 'static {
+    fn supervise(&self, args: Arguments) -> BoxFuture<'static, (ShutdownReason, i64)>;
+}
+
 pub struct Arguments {
     pub key: Uuid,
     pub runtime_opts: UserWorkerRuntimeOpts,
@@ -126,6 +313,8 @@ pub struct Arguments {
     pub thread_safe_handle: IsolateHandle,
     pub waker: Arc<AtomicWaker>,
     pub termination_token: Option<TerminationToken>,
+    pub beforeunload_tx: Option<oneshot::Sender<BeforeUnloadSignal>>,
+    pub cpu_profiler: Option<CpuProfilerStop>,
 }
 
 pub struct CPUUsage {
@@ -139,9 +328,161 @@ pub enum CPUUsageMetrics {
     Leave(CPUUsage),
 }
 
-async fn wait_cpu_alarm(maybe_alarm: Option<&mut UnboundedReceiver<()>>) -> Option<()> {
+pub async fn wait_cpu_alarm(maybe_alarm: Option<&mut UnboundedReceiver<()>>) -> Option<()> {
     match maybe_alarm {
         Some(alarm) => Some(alarm.recv().await?),
         None => None,
     }
 }
+
+/// Sits on the request timing path between the pool and the strategy, and
+/// cancels `RequestStart::timeout` of every request that has not ended within
+/// `timeout`. The other requests running on the same worker are not affected.
+pub async fn enforce_request_timeout(
+    key: Uuid,
+    (mut req_start_rx, mut req_end_rx): (UnboundedReceiver<RequestStart>, UnboundedReceiver<Uuid>),
+    (req_start_tx, req_end_tx): (UnboundedSender<RequestStart>, UnboundedSender<Uuid>),
+    timeout: Duration,
+) {
+    let mut deadlines = HashMap::<Uuid, AbortHandle>::new();
+
+    loop {
+        tokio::select! {
+            Some(start) = req_start_rx.recv() => {
+                let token = start.timeout.clone();
+                let deadline = tokio::spawn(async move {
+                    tokio::time::sleep(timeout).await;
+                    error!("request timeout reached. isolate: {:?}", key);
+                    token.cancel();
+                });
+
+                deadlines.insert(start.id, deadline.abort_handle());
+
+                // NOTE: The strategy may not be interested in the start signal
+                // (e.g. the per worker strategy).
+                let _ = req_start_tx.send(start);
+            }
+
+            Some(id) = req_end_rx.recv() => {
+                if let Some(deadline) = deadlines.remove(&id) {
+                    deadline.abort();
+                }
+
+                let _ = req_end_tx.send(id);
+            }
+
+            else => break,
+        }
+    }
+
+    for deadline in deadlines.into_values() {
+        deadline.abort();
+    }
+}
+
+/// What came out of dispatching the `beforeunload` event.
+#[derive(Debug, Default)]
+pub struct BeforeUnloadOutcome {
+    /// Accumulated CPU time of the isolate (in nanoseconds), if it has been
+    /// observed while waiting.
+    pub cpu_accumulated_ns: Option<i64>,
+    /// Whether the handlers exhausted their CPU budget, in which case the
+    /// isolate must be terminated.
+    pub cpu_budget_exhausted: bool,
+}
+
+/// Dispatches the `beforeunload` event into the isolate and waits until its
+/// handlers settle, the grace period elapses or they exhaust their own CPU
+/// budget, whichever comes first.
+///
+/// The CPU budget is enforced by re-arming the CPU timer of the worker (if it
+/// has one), so that handlers that never yield are caught as well. Otherwise,
+/// it's only checked whenever the isolate leaves the event loop.
+pub async fn dispatch_beforeunload(
+    key: Uuid,
+    reason: ShutdownReason,
+    beforeunload_tx: Option<oneshot::Sender<BeforeUnloadSignal>>,
+    runtime_opts: &UserWorkerRuntimeOpts,
+    cpu_usage_metrics_rx: &mut UnboundedReceiver<CPUUsageMetrics>,
+    cpu_timer: Option<(&CPUTimer, &mut UnboundedReceiver<()>)>,
+    waker: &AtomicWaker,
+) -> BeforeUnloadOutcome {
+    let mut outcome = BeforeUnloadOutcome::default();
+    let Some(beforeunload_tx) = beforeunload_tx else {
+        return outcome;
+    };
+
+    let (ack_tx, mut ack_rx) = oneshot::channel::<()>();
+
+    // NOTE: Workers that exceeded their memory limit are never given a grace
+    // period. They are terminated right away, as running the handlers would
+    // only grow the heap further.
+    if reason == ShutdownReason::Memory
+        || runtime_opts.shutdown_grace_period_ms == 0
+        || beforeunload_tx.send((reason, ack_tx)).is_err()
+    {
+        return outcome;
+    }
+
+    let cpu_budget_ms = runtime_opts.shutdown_grace_cpu_time_ms;
+    let (cpu_timer, mut cpu_alarms_rx) = match cpu_timer.filter(|_| cpu_budget_ms > 0) {
+        Some((cpu_timer, cpu_alarms_rx)) => {
+            // alarms of the CPU time limits that haven't been handled yet are
+            // not relevant anymore
+            while cpu_alarms_rx.try_recv().is_ok() {}
+
+            match cpu_timer.reset_once(cpu_budget_ms) {
+                Ok(()) => (Some(cpu_timer), Some(cpu_alarms_rx)),
+                Err(err) => {
+                    error!("can't reset cpu timer: {}", err);
+                    (None, None)
+                }
+            }
+        }
+
+        None => (None, None),
+    };
+
+    waker.wake();
+
+    let cpu_budget_ns = cpu_budget_ms as i64 * 1_000_000;
+    let mut cpu_used_ns = 0i64;
+    let grace_period =
+        tokio::time::sleep(Duration::from_millis(runtime_opts.shutdown_grace_period_ms));
+
+    tokio::pin!(grace_period);
+
+    loop {
+        tokio::select! {
+            _ = &mut ack_rx => break,
+
+            _ = &mut grace_period => {
+                error!("beforeunload grace period reached. isolate: {:?}", key);
+                break;
+            }
+
+            Some(_) = wait_cpu_alarm(cpu_alarms_rx.as_deref_mut()) => {
+                error!("beforeunload CPU time limit reached. isolate: {:?}", key);
+                outcome.cpu_budget_exhausted = true;
+                break;
+            }
+
+            Some(metrics) = cpu_usage_metrics_rx.recv() => {
+                let CPUUsageMetrics::Leave(CPUUsage { accumulated, diff }) = metrics else {
+                    continue;
+                };
+
+                cpu_used_ns += diff;
+                outcome.cpu_accumulated_ns = Some(accumulated);
+
+                if cpu_timer.is_none() && cpu_budget_ns > 0 && cpu_used_ns >= cpu_budget_ns {
+                    error!("beforeunload CPU time limit reached. isolate: {:?}", key);
+                    outcome.cpu_budget_exhausted = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    outcome
+}
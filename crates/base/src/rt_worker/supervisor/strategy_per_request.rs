@@ -4,15 +4,31 @@ use std::{future::pending, sync::atomic::Ordering, time::Duration};
 use std::thread::ThreadId;
 
 use event_worker::events::ShutdownReason;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
 use log::error;
 use sb_workers::context::{Timing, TimingStatus, UserWorkerMsgs};
 use tokio::time::Instant;
 
 use crate::rt_worker::supervisor::{
-    handle_interrupt, wait_cpu_alarm, CPUUsage, CPUUsageMetrics, IsolateInterruptData,
+    dispatch_beforeunload, handle_interrupt, wait_cpu_alarm, CPUUsage, CPUUsageMetrics,
+    IsolateInterruptData,
 };
 
-use super::Arguments;
+use super::{Arguments, SupervisorStrategy};
+
+/// Applies the CPU time limit to each request. With `oneshot`, the worker is
+/// terminated as soon as its first request is completed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PerRequestStrategy {
+    pub oneshot: bool,
+}
+
+impl SupervisorStrategy for PerRequestStrategy {
+    fn supervise(&self, args: Arguments) -> BoxFuture<'static, (ShutdownReason, i64)> {
+        supervise(args, self.oneshot).boxed()
+    }
+}
 
 pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64) {
     let Arguments {
@@ -26,12 +42,23 @@ pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64)
         pool_msg_tx,
         isolate_memory_usage_tx,
         thread_safe_handle,
+        waker,
         termination_token,
+        beforeunload_tx,
+        cpu_profiler,
         ..
     } = args;
 
     let Timing {
-        status: TimingStatus { demand, is_retired },
+        status:
+            TimingStatus {
+                demand,
+                is_retired,
+                drain,
+                cpu_time_used_ms,
+                cpu_clock,
+                ..
+            },
         req: (mut req_start_rx, mut req_end_rx),
         ..
     } = timing.unwrap_or_default();
@@ -50,6 +77,8 @@ pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64)
     let mut complete_reason = None::<ShutdownReason>;
     let mut req_ack_count = 0usize;
     let mut req_start_ack = false;
+    let mut is_draining = false;
+    let mut beforeunload_tx = beforeunload_tx;
 
     // reduce 100ms from wall clock duration, so the interrupt can be handled before
     // isolate is dropped
@@ -96,8 +125,10 @@ pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64)
                         assert!(is_worker_entered);
 
                         is_worker_entered = false;
+                        cpu_clock.advance(diff as u64);
                         cpu_usage_ms += diff / 1_000_000;
                         cpu_usage_accumulated_ms = accumulated / 1_000_000;
+                        cpu_time_used_ms.store(cpu_usage_accumulated_ms as usize, Ordering::Release);
 
                         if !cpu_timer_param.is_disabled() {
                             if cpu_usage_ms >= hard_limit_ms as i64 {
@@ -120,12 +151,20 @@ pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64)
                 }
             }
 
-            Some(notify) = req_start_rx.recv() => {
+            _ = drain.notified(), if !is_draining => {
+                is_draining = true;
+
+                if !req_start_ack && req_ack_count == demand.load(Ordering::Acquire) {
+                    complete_reason = Some(ShutdownReason::EarlyDrop);
+                }
+            }
+
+            Some(req_start) = req_start_rx.recv() => {
                 // INVARIANT: This branch MUST not be satisfied more than once
                 // during the same request cycle.
                 assert!(!req_start_ack, "supervisor has seen request start signal twice");
 
-                notify.notify_one();
+                req_start.fence.notify_one();
 
                 if let Some(cpu_timer) = cpu_timer.as_ref() {
                     if let Err(ex) = cpu_timer.reset() {
@@ -168,7 +207,16 @@ pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64)
         }
 
         match complete_reason.take() {
-            Some(ShutdownReason::EarlyDrop) if !oneshot => {
+            // NOTE: Requests that were already routed to the worker before it
+            // was drained must still be served.
+            Some(ShutdownReason::EarlyDrop)
+                if is_draining && req_ack_count != demand.load(Ordering::Acquire) =>
+            {
+                req_start_ack = false;
+                continue;
+            }
+
+            Some(ShutdownReason::EarlyDrop) if !oneshot && !is_draining => {
                 req_start_ack = false;
                 wall_clock_duration_alert
                     .as_mut()
@@ -186,6 +234,25 @@ pub async fn supervise(args: Arguments, oneshot: bool) -> (ShutdownReason, i64)
             Some(reason) => {
                 is_retired.raise();
 
+                if let Some(accumulated) = dispatch_beforeunload(
+                    key,
+                    reason,
+                    beforeunload_tx.take(),
+                    &runtime_opts,
+                    &mut cpu_usage_metrics_rx,
+                    cpu_timer.as_ref().zip(cpu_alarms_rx.as_mut()),
+                    &waker,
+                )
+                .await
+                .cpu_accumulated_ns
+                {
+                    cpu_usage_accumulated_ms = accumulated / 1_000_000;
+                }
+
+                if let Some(cpu_profiler) = cpu_profiler {
+                    cpu_profiler.stop().await;
+                }
+
                 let data_ptr_mut = Box::into_raw(Box::new(IsolateInterruptData {
                     should_terminate: true,
                     isolate_memory_usage_tx: Some(isolate_memory_usage_tx),
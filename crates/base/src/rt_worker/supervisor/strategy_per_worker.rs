@@ -4,12 +4,28 @@ use std::{future::pending, sync::atomic::Ordering, time::Duration};
 use std::thread::ThreadId;
 
 use event_worker::events::ShutdownReason;
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
 use log::error;
 use sb_workers::context::{Timing, TimingStatus, UserWorkerMsgs};
 
 use crate::rt_worker::supervisor::{wait_cpu_alarm, CPUUsage};
 
-use super::{handle_interrupt, Arguments, CPUUsageMetrics, IsolateInterruptData};
+use super::{
+    dispatch_beforeunload, handle_interrupt, Arguments, CPUUsageMetrics, IsolateInterruptData,
+    SupervisorStrategy,
+};
+
+/// Keeps the worker alive across requests until it reaches its soft CPU time
+/// limit or wall clock limit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PerWorkerStrategy;
+
+impl SupervisorStrategy for PerWorkerStrategy {
+    fn supervise(&self, args: Arguments) -> BoxFuture<'static, (ShutdownReason, i64)> {
+        supervise(args).boxed()
+    }
+}
 
 pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
     let Arguments {
@@ -23,13 +39,25 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
         pool_msg_tx,
         isolate_memory_usage_tx,
         thread_safe_handle,
+        waker,
         termination_token,
+        beforeunload_tx,
+        cpu_profiler,
         ..
     } = args;
 
     let Timing {
-        status: TimingStatus { demand, is_retired },
+        status:
+            TimingStatus {
+                demand,
+                is_retired,
+                drain,
+                cpu_time_used_ms,
+                cpu_clock,
+                ..
+            },
         req: (_, mut req_end_rx),
+        ..
     } = timing.unwrap_or_default();
 
     let (cpu_timer, mut cpu_alarms_rx) = cpu_timer.unzip();
@@ -43,6 +71,7 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
     let mut cpu_usage_ms = 0i64;
 
     let mut cpu_time_soft_limit_reached = false;
+    let mut is_draining = false;
     let mut wall_clock_alerts = 0;
     let mut req_ack_count = 0usize;
 
@@ -77,7 +106,7 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
 
     tokio::pin!(wall_clock_duration_alert);
 
-    loop {
+    let (reason, should_terminate) = loop {
         tokio::select! {
             _ = async {
                 match termination_token.as_ref() {
@@ -85,8 +114,7 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                     None => pending().await,
                 }
             } => {
-                interrupt_fn(true);
-                return (ShutdownReason::TerminationRequested, cpu_usage_ms);
+                break (ShutdownReason::TerminationRequested, true);
             }
 
             Some(metrics) = cpu_usage_metrics_rx.recv() => {
@@ -110,18 +138,19 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                         }
                     }
 
-                    CPUUsageMetrics::Leave(CPUUsage { accumulated, .. }) => {
+                    CPUUsageMetrics::Leave(CPUUsage { accumulated, diff }) => {
                         assert!(is_worker_entered);
 
                         is_worker_entered = false;
+                        cpu_clock.advance(diff as u64);
                         cpu_usage_ms = accumulated / 1_000_000;
+                        cpu_time_used_ms.store(cpu_usage_ms as usize, Ordering::Release);
 
                         if !cpu_timer_param.is_disabled() {
                             if cpu_usage_ms >= hard_limit_ms as i64 {
                                 // shutdown worker
-                                interrupt_fn(true);
                                 error!("CPU time hard limit reached. isolate: {:?}", key);
-                                return (ShutdownReason::CPUTime, cpu_usage_ms);
+                                break (ShutdownReason::CPUTime, true);
                             } else if cpu_usage_ms >= soft_limit_ms as i64 && !cpu_time_soft_limit_reached {
                                     // retire worker
                                     is_retired.raise();
@@ -129,9 +158,8 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                                     cpu_time_soft_limit_reached = true;
 
                                     if req_ack_count == demand.load(Ordering::Acquire) {
-                                        interrupt_fn(true);
                                         error!("early termination due to the last request being completed. isolate: {:?}", key);
-                                        return (ShutdownReason::EarlyDrop, cpu_usage_ms);
+                                        break (ShutdownReason::EarlyDrop, true);
                                     }
                             }
                         }
@@ -139,6 +167,16 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                 }
             }
 
+            _ = drain.notified(), if !is_draining => {
+                is_retired.raise();
+                is_draining = true;
+
+                if req_ack_count == demand.load(Ordering::Acquire) {
+                    error!("early termination due to the worker being drained. isolate: {:?}", key);
+                    break (ShutdownReason::EarlyDrop, true);
+                }
+            }
+
             Some(_) = wait_cpu_alarm(cpu_alarms_rx.as_mut()) => {
                 if is_worker_entered {
                     if !cpu_time_soft_limit_reached {
@@ -148,15 +186,13 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                         cpu_time_soft_limit_reached = true;
 
                         if req_ack_count == demand.load(Ordering::Acquire) {
-                            interrupt_fn(true);
                             error!("early termination due to the last request being completed. isolate: {:?}", key);
-                            return (ShutdownReason::EarlyDrop, cpu_usage_ms);
+                            break (ShutdownReason::EarlyDrop, true);
                         }
                     } else {
                         // shutdown worker
-                        interrupt_fn(true);
                         error!("CPU time hard limit reached. isolate: {:?}", key);
-                        return (ShutdownReason::CPUTime, cpu_usage_ms);
+                        break (ShutdownReason::CPUTime, true);
                     }
                 }
             }
@@ -164,7 +200,9 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
             Some(_) = req_end_rx.recv() => {
                 req_ack_count += 1;
 
-                if !cpu_time_soft_limit_reached {
+                let should_drop = cpu_time_soft_limit_reached || is_draining;
+
+                if !should_drop {
                     if let Some(tx) = pool_msg_tx.clone() {
                         if tx.send(UserWorkerMsgs::Idle(key)).is_err() {
                             error!("failed to send idle msg to pool: {:?}", key);
@@ -172,13 +210,12 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                     }
                 }
 
-                if !cpu_time_soft_limit_reached || req_ack_count != demand.load(Ordering::Acquire) {
+                if !should_drop || req_ack_count != demand.load(Ordering::Acquire) {
                     continue;
                 }
 
-                interrupt_fn(true);
                 error!("early termination due to the last request being completed. isolate: {:?}", key);
-                return (ShutdownReason::EarlyDrop, cpu_usage_ms);
+                break (ShutdownReason::EarlyDrop, true);
             }
 
             // wall clock warning
@@ -193,27 +230,50 @@ pub async fn supervise(args: Arguments) -> (ShutdownReason, i64) {
                     wall_clock_alerts += 1;
                 } else {
                     // wall-clock limit reached
-                    // Don't terminate isolate from supervisor when wall-clock
-                    // duration reached. It's dropped in deno_runtime.rs
-                    interrupt_fn(
+                    // The worker is retired, so it's left to finish its
+                    // pending requests (if any) before it exits.
+                    error!("wall clock duration reached. isolate: {:?}", key);
+                    break (
+                        ShutdownReason::WallClockTime,
                         // NOTE: Wall clock is also triggered when no more
                         // pending requests, so we must compare the request
                         // count here to judge whether we need to terminate the
                         // isolate.
                         req_ack_count == demand.load(Ordering::Acquire),
                     );
-
-                    error!("wall clock duration reached. isolate: {:?}", key);
-                    return (ShutdownReason::WallClockTime, cpu_usage_ms);
                 }
             }
 
             // memory usage
             Some(_) = memory_limit_rx.recv() => {
-                interrupt_fn(true);
                 error!("memory limit reached for the worker. isolate: {:?}", key);
-                return (ShutdownReason::Memory, cpu_usage_ms);
+                break (ShutdownReason::Memory, true);
             }
         }
+    };
+
+    is_retired.raise();
+
+    let beforeunload = dispatch_beforeunload(
+        key,
+        reason,
+        beforeunload_tx,
+        &runtime_opts,
+        &mut cpu_usage_metrics_rx,
+        cpu_timer.as_ref().zip(cpu_alarms_rx.as_mut()),
+        &waker,
+    )
+    .await;
+
+    if let Some(accumulated) = beforeunload.cpu_accumulated_ns {
+        cpu_usage_ms = accumulated / 1_000_000;
+    }
+
+    if let Some(cpu_profiler) = cpu_profiler {
+        cpu_profiler.stop().await;
     }
+
+    interrupt_fn(should_terminate || beforeunload.cpu_budget_exhausted);
+
+    (reason, cpu_usage_ms)
 }
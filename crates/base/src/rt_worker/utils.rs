@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
-use event_worker::events::{EventMetadata, WorkerEventWithMetadata};
+use event_worker::channel::EventSender;
+use event_worker::events::EventMetadata;
 use sb_workers::context::{UserWorkerMsgs, WorkerRuntimeOpts};
 use tokio::sync::{mpsc::UnboundedSender, Notify};
 use uuid::Uuid;
@@ -8,7 +9,7 @@ use uuid::Uuid;
 type WorkerCoreConfig = (
     Option<Uuid>,
     Option<UnboundedSender<UserWorkerMsgs>>,
-    Option<UnboundedSender<WorkerEventWithMetadata>>,
+    Option<EventSender>,
     Option<Arc<Notify>>,
     String,
 );
@@ -36,12 +37,14 @@ pub fn get_event_metadata(conf: &WorkerRuntimeOpts) -> EventMetadata {
     let mut event_metadata = EventMetadata {
         service_path: None,
         execution_id: None,
+        version: None,
     };
     if conf.is_user_worker() {
         let conf = conf.as_user_worker().unwrap();
         event_metadata = EventMetadata {
             service_path: conf.service_path.clone(),
             execution_id: conf.key,
+            version: conf.version.clone(),
         };
     }
 
@@ -3,16 +3,19 @@ use crate::rt_worker::supervisor;
 use crate::rt_worker::utils::{get_event_metadata, parse_worker_conf};
 use crate::rt_worker::worker_ctx::create_supervisor;
 use crate::utils::send_event_if_event_worker_available;
-use anyhow::{anyhow, Error};
+use anyhow::Error;
+use event_worker::channel::EventSender;
 use event_worker::events::{
-    EventMetadata, ShutdownEvent, ShutdownReason, UncaughtExceptionEvent, WorkerEventWithMetadata,
-    WorkerEvents, WorkerMemoryUsed,
+    EventMetadata, ShutdownEvent, ShutdownReason, UncaughtExceptionEvent, WorkerEvents,
+    WorkerMemoryUsed,
 };
+use event_worker::js_interceptors::LogRedactions;
 use futures_util::FutureExt;
 use log::{debug, error};
 use sb_core::conn_sync::ConnSync;
 use sb_core::{MetricSource, RuntimeMetricSource, WorkerMetricSource};
 use sb_workers::context::{UserWorkerMsgs, WorkerContextInitOpts};
+use sb_workers::errors::WorkerBootError;
 use std::any::Any;
 use std::future::{pending, Future};
 use std::pin::Pin;
@@ -25,20 +28,23 @@ use tokio::time::Instant;
 use uuid::Uuid;
 
 use super::rt;
-use super::supervisor::CPUUsageMetrics;
+use super::supervisor::{CPUUsageMetrics, SupervisorStrategy};
 use super::worker_ctx::TerminationToken;
 use super::worker_pool::SupervisorPolicy;
 
 #[derive(Clone)]
 pub struct Worker {
     pub worker_boot_start_time: Instant,
-    pub events_msg_tx: Option<UnboundedSender<WorkerEventWithMetadata>>,
+    pub events_msg_tx: Option<EventSender>,
     pub pool_msg_tx: Option<UnboundedSender<UserWorkerMsgs>>,
     pub cancel: Option<Arc<Notify>>,
     pub event_metadata: EventMetadata,
     pub worker_key: Option<Uuid>,
     pub supervisor_policy: Option<SupervisorPolicy>,
+    pub supervisor_strategy: Option<Arc<dyn SupervisorStrategy>>,
     pub worker_name: String,
+    /// Redacts the secrets from the errors the worker fails with.
+    pub log_redactions: LogRedactions,
 }
 
 pub type HandleCreationType = Pin<Box<dyn Future<Output = Result<WorkerEvents, Error>>>>;
@@ -64,9 +70,15 @@ impl Worker {
 
         let event_metadata = get_event_metadata(&init_opts.conf);
         let worker_boot_start_time = Instant::now();
+        let log_redactions = init_opts
+            .conf
+            .as_user_worker()
+            .map(|it| LogRedactions::new(it.env_access_rules.secret_values(&init_opts.env_vars)))
+            .unwrap_or_default();
 
         Ok(Self {
             supervisor_policy: None,
+            supervisor_strategy: None,
             worker_boot_start_time,
             events_msg_tx,
             pool_msg_tx,
@@ -74,6 +86,7 @@ impl Worker {
             event_metadata,
             worker_key,
             worker_name,
+            log_redactions,
         })
     }
 
@@ -81,6 +94,13 @@ impl Worker {
         self.supervisor_policy = supervisor_policy;
     }
 
+    pub fn set_supervisor_strategy(
+        &mut self,
+        supervisor_strategy: Option<Arc<dyn SupervisorStrategy>>,
+    ) {
+        self.supervisor_strategy = supervisor_strategy;
+    }
+
     pub fn start(
         &self,
         mut opts: WorkerContextInitOpts,
@@ -95,13 +115,18 @@ impl Worker {
         let worker_key = self.worker_key;
         let event_metadata = self.event_metadata.clone();
         let supervisor_policy = self.supervisor_policy.unwrap_or_default();
+        let supervisor_strategy = self
+            .supervisor_strategy
+            .clone()
+            .unwrap_or_else(|| supervisor_policy.strategy());
 
         let (unix_stream_tx, unix_stream_rx) = unix_stream_pair;
         let events_msg_tx = self.events_msg_tx.clone();
         let pool_msg_tx = self.pool_msg_tx.clone();
 
         let method_cloner = self.clone();
-        let timing = opts.timing.take();
+        let mut timing = opts.timing.take();
+        let cpu_profile_rx = timing.as_mut().and_then(|it| it.cpu_profile_rx.take());
         let worker_kind = opts.conf.to_worker_kind();
         let maybe_main_worker_opts = opts.conf.as_main_worker().cloned();
 
@@ -143,6 +168,8 @@ impl Worker {
                             }
                         };
 
+                        new_runtime.start_cpu_profiler(cpu_profile_rx);
+
                         let _ = booter_signal.send(Ok(metric_src));
 
                         // CPU TIMER
@@ -151,13 +178,13 @@ impl Worker {
 
                         let _cpu_timer;
 
-                        // TODO: Allow customization of supervisor
                         let termination_fut = if worker_kind.is_user_worker() {
                             // cputimer is returned from supervisor and assigned here to keep it in scope.
                             let Ok(maybe_timer) = create_supervisor(
                                 worker_key.unwrap_or(Uuid::nil()),
                                 &mut new_runtime,
                                 supervisor_policy,
+                                supervisor_strategy,
                                 termination_event_tx,
                                 pool_msg_tx.clone(),
                                 maybe_cpu_usage_metrics_rx,
@@ -215,6 +242,8 @@ impl Worker {
                                                 heap: 0,
                                                 external: 0,
                                             },
+                                            memory_high_water_mark: WorkerMemoryUsed::default(),
+                                            heap_snapshot: None,
                                         },
                                     ));
                                 })
@@ -245,7 +274,14 @@ impl Worker {
                     }
 
                     Err(err) => {
-                        let _ = booter_signal.send(Err(anyhow!("worker boot error")));
+                        let boot_error = WorkerBootError::from(&err);
+                        let boot_error = WorkerBootError {
+                            msg: method_cloner.log_redactions.redact(&boot_error.msg),
+                            ..boot_error
+                        };
+
+                        let _ = booter_signal
+                            .send(Err(Error::new(boot_error).context("worker boot error")));
                         method_cloner.handle_error(err)
                     }
                 };
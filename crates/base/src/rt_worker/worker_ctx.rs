@@ -1,16 +1,19 @@
 use crate::deno_runtime::DenoRuntime;
 use crate::utils::send_event_if_event_worker_available;
-use crate::utils::units::bytes_to_display;
+use crate::utils::units::{bytes_to_display, mib_to_bytes};
 
+use crate::rt_worker::cpu_profiler::CpuProfiler;
+use crate::rt_worker::utils::get_event_metadata;
 use crate::rt_worker::worker::{Worker, WorkerHandler};
 use crate::rt_worker::worker_pool::WorkerPool;
 use anyhow::{anyhow, bail, Error};
 use cpu_timer::CPUTimer;
-use event_worker::events::{
-    BootEvent, ShutdownEvent, WorkerEventWithMetadata, WorkerEvents, WorkerMemoryUsed,
+use event_worker::channel::{
+    event_channel, EventChannelCounters, EventChannelOpts, EventSender, SharedEventReceiver,
 };
+use event_worker::events::{BootEvent, ShutdownEvent, WorkerEvents, WorkerMemoryUsed};
 use hyper::{Body, Request, Response};
-use log::{debug, error};
+use log::{debug, error, info};
 use sb_core::conn_sync::ConnSync;
 use sb_core::{MetricSource, SharedMetricSource};
 use sb_graph::EszipPayloadKind;
@@ -21,7 +24,9 @@ use sb_workers::context::{
 use sb_workers::errors::WorkerError;
 use std::future::pending;
 use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::UnixStream;
 use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
 use tokio::sync::{mpsc, oneshot, watch, Notify};
@@ -29,9 +34,10 @@ use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use super::rt;
-use super::supervisor::{self, CPUTimerParam, CPUUsageMetrics};
+use super::supervisor::{self, CPUTimerParam, CPUUsageMetrics, SupervisorStrategy};
 use super::worker::UnixStreamEntry;
 use super::worker_pool::{SupervisorPolicy, WorkerPoolPolicy};
+use super::worker_pool_config::WorkerPoolConfigWatcher;
 
 #[derive(Clone)]
 pub struct TerminationToken {
@@ -84,7 +90,7 @@ async fn handle_request(
     let (sender_stream, recv_stream) = UnixStream::pair()?;
     let WorkerRequestMsg {
         req,
-        res_tx,
+        mut res_tx,
         conn_watch,
     } = msg;
 
@@ -94,7 +100,7 @@ async fn handle_request(
     let (mut request_sender, connection) = hyper::client::conn::handshake(sender_stream).await?;
 
     // spawn a task to poll the connection and drive the HTTP state
-    tokio::task::spawn(async move {
+    let connection_handle = tokio::task::spawn(async move {
         match connection.without_shutdown().await {
             Err(e) => {
                 error!("Error in worker connection: {}", e.message(),);
@@ -114,7 +120,18 @@ async fn handle_request(
 
     tokio::task::yield_now().await;
 
-    let result = request_sender.send_request(req).await;
+    let result = tokio::select! {
+        result = request_sender.send_request(req) => result,
+
+        // NOTE: The caller has given up on the request (e.g. it has timed
+        // out). Dropping the connection lets the worker see the request as
+        // aborted, so that `Request.signal` of the request fires.
+        () = res_tx.closed() => {
+            connection_handle.abort();
+            return Ok(());
+        }
+    };
+
     let _ = res_tx.send(result);
 
     Ok(())
@@ -125,11 +142,12 @@ pub fn create_supervisor(
     key: Uuid,
     worker_runtime: &mut DenoRuntime,
     supervisor_policy: SupervisorPolicy,
+    supervisor_strategy: Arc<dyn SupervisorStrategy>,
     termination_event_tx: oneshot::Sender<WorkerEvents>,
     pool_msg_tx: Option<UnboundedSender<UserWorkerMsgs>>,
     cpu_usage_metrics_rx: Option<UnboundedReceiver<CPUUsageMetrics>>,
     cancel: Option<Arc<Notify>>,
-    timing: Option<Timing>,
+    mut timing: Option<Timing>,
     termination_token: Option<TerminationToken>,
 ) -> Result<Option<CPUTimer>, Error> {
     let (memory_limit_tx, memory_limit_rx) = mpsc::unbounded_channel::<()>();
@@ -145,6 +163,29 @@ pub fn create_supervisor(
     let conf = worker_runtime.conf.as_user_worker().unwrap().clone();
     let is_termination_requested = worker_runtime.is_termination_requested.clone();
     let cancel = cancel.clone();
+    let memory_sample_tx = memory_limit_tx.clone();
+    let heap_snapshot_path = Arc::<std::sync::Mutex<Option<PathBuf>>>::default();
+    let memory_limit_rx = match conf.heap_snapshot.clone() {
+        Some(opts) => {
+            let (relay_tx, relay_rx) = mpsc::unbounded_channel::<()>();
+
+            drop(
+                rt::SUPERVISOR_RT.spawn(supervisor::heap_snapshot::relay_memory_limit(
+                    key,
+                    opts,
+                    thread_safe_handle.clone(),
+                    waker.clone(),
+                    memory_limit_rx,
+                    relay_tx,
+                    heap_snapshot_path.clone(),
+                )),
+            );
+
+            relay_rx
+        }
+
+        None => memory_limit_rx,
+    };
 
     worker_runtime.js_runtime.add_near_heap_limit_callback(move |cur, _| {
         debug!(
@@ -161,6 +202,49 @@ pub fn create_supervisor(
         cur * (conf.low_memory_multiplier as usize)
     });
 
+    let memory_high_water_mark = supervisor::MemoryHighWaterMark::default();
+    let beforeunload_tx = worker_runtime.beforeunload_tx.take();
+    let cpu_profiler = worker_runtime
+        .cpu_profiler
+        .as_ref()
+        .map(CpuProfiler::stopper);
+
+    if conf.memory_sample_interval_ms > 0 {
+        drop(rt::SUPERVISOR_RT.spawn(supervisor::sample_memory_usage(
+            key,
+            thread_safe_handle.clone(),
+            waker.clone(),
+            mib_to_bytes(conf.memory_limit_mb) as usize,
+            Duration::from_millis(conf.memory_sample_interval_ms),
+            memory_high_water_mark.clone(),
+            memory_sample_tx,
+        )));
+    }
+
+    let resource_sample_token = CancellationToken::new();
+
+    if let Some(events_msg_tx) = conf
+        .events_msg_tx
+        .clone()
+        .filter(|_| conf.resource_sample_interval_ms > 0)
+    {
+        drop(
+            rt::SUPERVISOR_RT.spawn(supervisor::sample_resource_usage(
+                thread_safe_handle.clone(),
+                waker.clone(),
+                Duration::from_millis(conf.resource_sample_interval_ms),
+                timing
+                    .as_ref()
+                    .map(|it| it.status.clone())
+                    .unwrap_or_default(),
+                memory_high_water_mark.clone(),
+                events_msg_tx,
+                get_event_metadata(&worker_runtime.conf),
+                resource_sample_token.clone(),
+            )),
+        );
+    }
+
     // Note: CPU timer must be started in the same thread as the worker runtime
 
     let cpu_timer_param =
@@ -169,6 +253,19 @@ pub fn create_supervisor(
     let (maybe_cpu_timer, maybe_cpu_alarms_rx) =
         cpu_timer_param.get_cpu_timer(supervisor_policy).unzip();
 
+    if let Some(timing) = timing.as_mut().filter(|_| conf.request_timeout_ms > 0) {
+        let (req_start_tx, req_start_rx) = mpsc::unbounded_channel();
+        let (req_end_tx, req_end_rx) = mpsc::unbounded_channel();
+        let req = std::mem::replace(&mut timing.req, (req_start_rx, req_end_rx));
+
+        drop(rt::SUPERVISOR_RT.spawn(supervisor::enforce_request_timeout(
+            key,
+            req,
+            (req_start_tx, req_end_tx),
+            Duration::from_millis(conf.request_timeout_ms),
+        )));
+    }
+
     drop({
         let _rt_guard = rt::SUPERVISOR_RT.enter();
         let maybe_cpu_timer_inner = maybe_cpu_timer.clone();
@@ -191,17 +288,13 @@ pub fn create_supervisor(
                 thread_safe_handle,
                 waker: waker.clone(),
                 termination_token,
+                beforeunload_tx,
+                cpu_profiler,
             };
 
-            let (reason, cpu_usage_ms) = {
-                use supervisor::*;
-                match supervisor_policy {
-                    SupervisorPolicy::PerWorker => strategy_per_worker::supervise(args).await,
-                    SupervisorPolicy::PerRequest { oneshot } => {
-                        strategy_per_request::supervise(args, oneshot).await
-                    }
-                }
-            };
+            let (reason, cpu_usage_ms) = supervisor_strategy.supervise(args).await;
+
+            resource_sample_token.cancel();
 
             // NOTE: Sending a signal to the pooler that it is the user worker going
             // disposed down and will not accept awaiting subsequent requests, so
@@ -225,11 +318,14 @@ pub fn create_supervisor(
             waker.wake();
 
             let memory_used = match isolate_memory_usage_rx.await {
-                Ok(v) => WorkerMemoryUsed {
-                    total: v.used_heap_size + v.external_memory,
-                    heap: v.used_heap_size,
-                    external: v.external_memory,
-                },
+                Ok(v) => {
+                    memory_high_water_mark.update(&v);
+                    WorkerMemoryUsed {
+                        total: v.used_heap_size + v.external_memory,
+                        heap: v.used_heap_size,
+                        external: v.external_memory,
+                    }
+                }
                 Err(_) => {
                     error!("isolate memory usage sender dropped");
                     WorkerMemoryUsed {
@@ -244,6 +340,8 @@ pub fn create_supervisor(
             let termination_event = WorkerEvents::Shutdown(ShutdownEvent {
                 reason,
                 memory_used,
+                memory_high_water_mark: memory_high_water_mark.get(),
+                heap_snapshot: heap_snapshot_path.lock().unwrap().take(),
                 cpu_time_used: cpu_usage_ms as usize,
             });
 
@@ -258,23 +356,24 @@ pub struct CreateWorkerArgs(
     WorkerContextInitOpts,
     Option<SupervisorPolicy>,
     Option<TerminationToken>,
+    Option<Arc<dyn SupervisorStrategy>>,
 );
 
 impl From<WorkerContextInitOpts> for CreateWorkerArgs {
     fn from(val: WorkerContextInitOpts) -> Self {
-        CreateWorkerArgs(val, None, None)
+        CreateWorkerArgs(val, None, None, None)
     }
 }
 
 impl From<(WorkerContextInitOpts, SupervisorPolicy)> for CreateWorkerArgs {
     fn from(val: (WorkerContextInitOpts, SupervisorPolicy)) -> Self {
-        CreateWorkerArgs(val.0, Some(val.1), None)
+        CreateWorkerArgs(val.0, Some(val.1), None, None)
     }
 }
 
 impl<T: Into<Option<TerminationToken>>> From<(WorkerContextInitOpts, T)> for CreateWorkerArgs {
     fn from(val: (WorkerContextInitOpts, T)) -> Self {
-        CreateWorkerArgs(val.0, None, val.1.into())
+        CreateWorkerArgs(val.0, None, val.1.into(), None)
     }
 }
 
@@ -292,7 +391,7 @@ impl
             Option<TerminationToken>,
         ),
     ) -> Self {
-        CreateWorkerArgs(val.0, Some(val.1), val.2)
+        CreateWorkerArgs(val.0, Some(val.1), val.2, None)
     }
 }
 
@@ -306,6 +405,14 @@ impl CreateWorkerArgs {
         self.2 = Some(token);
         self
     }
+
+    pub fn with_supervisor_strategy(
+        mut self,
+        strategy: impl Into<Option<Arc<dyn SupervisorStrategy>>>,
+    ) -> Self {
+        self.3 = strategy.into();
+        self
+    }
 }
 
 pub async fn create_worker<Opt: Into<CreateWorkerArgs>>(
@@ -315,13 +422,18 @@ pub async fn create_worker<Opt: Into<CreateWorkerArgs>>(
     let (worker_boot_result_tx, worker_boot_result_rx) =
         oneshot::channel::<Result<MetricSource, Error>>();
 
-    let CreateWorkerArgs(init_opts, maybe_supervisor_policy, maybe_termination_token) =
-        init_opts.into();
+    let CreateWorkerArgs(
+        init_opts,
+        maybe_supervisor_policy,
+        maybe_termination_token,
+        maybe_supervisor_strategy,
+    ) = init_opts.into();
 
     let mut worker_init = Worker::new(&init_opts)?;
 
     if init_opts.conf.is_user_worker() {
         worker_init.set_supervisor_policy(maybe_supervisor_policy);
+        worker_init.set_supervisor_strategy(maybe_supervisor_strategy);
     }
 
     let worker: Box<dyn WorkerHandler> = Box::new(worker_init);
@@ -458,53 +570,179 @@ pub async fn create_main_worker(
     Ok(sender)
 }
 
-pub async fn create_events_worker(
+// Delay before restarting a crashed events worker. It doubles after each
+// restart and is reset once an instance stayed up for a while.
+const EVENTS_WORKER_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const EVENTS_WORKER_MAX_BACKOFF: Duration = Duration::from_secs(30);
+const EVENTS_WORKER_STABLE_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Clone)]
+struct EventsWorkerBootOpts {
     events_worker_path: PathBuf,
     import_map_path: Option<String>,
     no_module_cache: bool,
     maybe_entrypoint: Option<String>,
-    termination_token: Option<TerminationToken>,
-) -> Result<(MetricSource, mpsc::UnboundedSender<WorkerEventWithMetadata>), Error> {
-    let (events_tx, events_rx) = mpsc::unbounded_channel::<WorkerEventWithMetadata>();
+    events_rx: SharedEventReceiver,
+}
 
-    let mut service_path = events_worker_path.clone();
-    let mut maybe_eszip = None;
-    if let Some(ext) = events_worker_path.extension() {
-        if ext == "eszip" {
-            service_path = events_worker_path.parent().unwrap().to_path_buf();
-            maybe_eszip = Some(EszipPayloadKind::VecKind(std::fs::read(
-                events_worker_path,
-            )?));
+struct EventsWorkerInstance {
+    exit_token: CancellationToken,
+    termination_token: TerminationToken,
+}
+
+impl EventsWorkerBootOpts {
+    async fn boot(
+        &self,
+        parent_token: &TerminationToken,
+    ) -> Result<(MetricSource, EventsWorkerInstance), Error> {
+        let mut service_path = self.events_worker_path.clone();
+        let mut maybe_eszip = None;
+        if let Some(ext) = self.events_worker_path.extension() {
+            if ext == "eszip" {
+                service_path = self.events_worker_path.parent().unwrap().to_path_buf();
+                maybe_eszip = Some(EszipPayloadKind::VecKind(std::fs::read(
+                    &self.events_worker_path,
+                )?));
+            }
         }
+
+        let instance = EventsWorkerInstance {
+            exit_token: CancellationToken::new(),
+            termination_token: TerminationToken {
+                inbound: parent_token.inbound.child_token(),
+                outbound: CancellationToken::new(),
+            },
+        };
+
+        let (metric, _) = create_worker((
+            WorkerContextInitOpts {
+                service_path,
+                no_module_cache: self.no_module_cache,
+                import_map_path: self.import_map_path.clone(),
+                env_vars: std::env::vars().collect(),
+                events_rx: Some(self.events_rx.clone()),
+                timing: None,
+                maybe_eszip,
+                maybe_entrypoint: self.maybe_entrypoint.clone(),
+                maybe_module_code: None,
+                conf: WorkerRuntimeOpts::EventsWorker(EventWorkerRuntimeOpts {
+                    exit_token: Some(instance.exit_token.clone()),
+                }),
+            },
+            instance.termination_token.clone(),
+        ))
+        .await
+        .map_err(|err| anyhow!("events worker boot error: {}", err))?;
+
+        Ok((metric, instance))
     }
+}
 
-    let (metric, _) = create_worker((
-        WorkerContextInitOpts {
-            service_path,
-            no_module_cache,
-            import_map_path,
-            env_vars: std::env::vars().collect(),
-            events_rx: Some(events_rx),
-            timing: None,
-            maybe_eszip,
-            maybe_entrypoint,
-            maybe_module_code: None,
-            conf: WorkerRuntimeOpts::EventsWorker(EventWorkerRuntimeOpts {}),
-        },
+/// Restarts the events worker with a backoff whenever it exits without being
+/// asked to. Events are buffered by the channel in the meantime.
+async fn supervise_events_worker(
+    boot_opts: EventsWorkerBootOpts,
+    mut instance: EventsWorkerInstance,
+    termination_token: TerminationToken,
+    counters: EventChannelCounters,
+) {
+    let mut backoff = EVENTS_WORKER_MIN_BACKOFF;
+
+    'supervise: loop {
+        let started_at = Instant::now();
+
+        instance.exit_token.cancelled().await;
+
+        if termination_token.inbound.is_cancelled() {
+            instance.termination_token.outbound.cancelled().await;
+            break;
+        }
+
+        if started_at.elapsed() >= EVENTS_WORKER_STABLE_AFTER {
+            backoff = EVENTS_WORKER_MIN_BACKOFF;
+        }
+
+        loop {
+            error!(
+                "events worker exited unexpectedly, restarting in {}ms",
+                backoff.as_millis()
+            );
+
+            tokio::select! {
+                _ = termination_token.inbound.cancelled() => break 'supervise,
+                _ = tokio::time::sleep(backoff) => {}
+            }
+
+            backoff = (backoff * 2).min(EVENTS_WORKER_MAX_BACKOFF);
+
+            match boot_opts.boot(&termination_token).await {
+                Ok((_, new_instance)) => {
+                    let restarts = counters.consumer_restarts.fetch_add(1, Ordering::Relaxed) + 1;
+
+                    info!("events worker restarted (restarts: {})", restarts);
+                    instance = new_instance;
+                    break;
+                }
+
+                Err(err) => error!("failed to restart the events worker: {}", err),
+            }
+        }
+    }
+
+    termination_token.outbound.cancel();
+}
+
+pub async fn create_events_worker(
+    events_worker_path: PathBuf,
+    import_map_path: Option<String>,
+    no_module_cache: bool,
+    maybe_entrypoint: Option<String>,
+    channel_opts: EventChannelOpts,
+    termination_token: Option<TerminationToken>,
+) -> Result<(MetricSource, EventSender), Error> {
+    let (events_tx, events_rx) = event_channel(channel_opts);
+    let counters = events_rx.counters();
+    let termination_token = termination_token.unwrap_or_default();
+    let boot_opts = EventsWorkerBootOpts {
+        events_worker_path,
+        import_map_path,
+        no_module_cache,
+        maybe_entrypoint,
+        events_rx: Arc::new(tokio::sync::Mutex::new(events_rx)),
+    };
+
+    let (metric, instance) = boot_opts.boot(&termination_token).await?;
+
+    drop(tokio::spawn(supervise_events_worker(
+        boot_opts,
+        instance,
         termination_token,
-    ))
-    .await
-    .map_err(|err| anyhow!("events worker boot error: {}", err))?;
+        counters,
+    )));
 
     Ok((metric, events_tx))
 }
 
 pub async fn create_user_worker_pool(
     policy: WorkerPoolPolicy,
-    worker_event_sender: Option<mpsc::UnboundedSender<WorkerEventWithMetadata>>,
+    worker_event_sender: Option<EventSender>,
     termination_token: Option<TerminationToken>,
 ) -> Result<(SharedMetricSource, mpsc::UnboundedSender<UserWorkerMsgs>), Error> {
-    let metric_src = SharedMetricSource::default();
+    let metric_src = match worker_event_sender.as_ref().map(EventSender::counters) {
+        Some(counters) => SharedMetricSource::default().with_event_channel_counters(
+            counters.dropped_events,
+            counters.dropped_events_by_service,
+            counters.consumer_restarts,
+        ),
+        None => SharedMetricSource::default(),
+    };
+
+    let config = policy
+        .config_path()
+        .cloned()
+        .map(WorkerPoolConfigWatcher::new)
+        .transpose()?;
+
     let (user_worker_msgs_tx, mut user_worker_msgs_rx) =
         mpsc::unbounded_channel::<UserWorkerMsgs>();
 
@@ -520,6 +758,7 @@ pub async fn create_user_worker_pool(
                 metric_src_inner,
                 worker_event_sender,
                 user_worker_msgs_tx_clone,
+                config,
             );
 
             // Note: Keep this loop non-blocking. Spawn a task to run blocking calls.
@@ -544,6 +783,15 @@ pub async fn create_user_worker_pool(
                         }
                     }
 
+                    Ok(()) = async {
+                        match worker_pool.config.as_mut() {
+                            Some(config) => config.changed().await,
+                            None => pending().await,
+                        }
+                    } => {
+                        worker_pool.reload_config();
+                    }
+
                     msg = user_worker_msgs_rx.recv() => {
                         match msg {
                             None => break,
@@ -559,6 +807,33 @@ pub async fn create_user_worker_pool(
                             Some(UserWorkerMsgs::Idle(key)) => {
                                 worker_pool.idle(&key);
                             }
+                            Some(UserWorkerMsgs::BootFailed(service_path, error)) => {
+                                worker_pool.boot_failed(&service_path, error);
+                            }
+                            Some(UserWorkerMsgs::BootProbeReleased(service_path)) => {
+                                worker_pool.boot_probe_released(&service_path);
+                            }
+                            Some(UserWorkerMsgs::List(tx)) => {
+                                if tx.send(worker_pool.list_user_workers()).is_err() {
+                                    error!("main worker receiver dropped");
+                                }
+                            }
+                            Some(UserWorkerMsgs::Stats(key, tx)) => {
+                                worker_pool.user_worker_stats(&key, tx);
+                            }
+                            Some(UserWorkerMsgs::Terminate(key, tx)) => {
+                                if tx.send(worker_pool.terminate_user_worker(&key)).is_err() {
+                                    error!("main worker receiver dropped");
+                                }
+                            }
+                            Some(UserWorkerMsgs::CpuProfile(key, duration_ms, tx)) => {
+                                worker_pool.user_worker_cpu_profile(&key, duration_ms, tx);
+                            }
+                            Some(UserWorkerMsgs::Retire(key, tx)) => {
+                                if tx.send(worker_pool.retire_user_worker(&key)).is_err() {
+                                    error!("main worker receiver dropped");
+                                }
+                            }
                             Some(UserWorkerMsgs::Shutdown(key)) => {
                                 worker_pool.shutdown(&key);
 
@@ -1,31 +1,50 @@
+use crate::otel::{Span, SpanContext, SpanKind};
 use crate::rt_worker::worker_ctx::{create_worker, send_user_worker_request};
-use anyhow::{anyhow, Context, Error};
+use crate::utils::send_event_if_event_worker_available;
+use anyhow::{anyhow, bail, Context, Error};
 use enum_as_inner::EnumAsInner;
-use event_worker::events::WorkerEventWithMetadata;
+use event_worker::channel::EventSender;
+use event_worker::events::{
+    BootCircuitBreakerEvent, BootCircuitBreakerState, EventMetadata, RequestCompletedEvent,
+    RequestStartedEvent, WorkerEvents,
+};
+use futures_util::TryStreamExt;
+use http::header::{HeaderName, HeaderValue};
 use http::Request;
-use hyper::Body;
+use hyper::body::HttpBody;
+use hyper::{Body, Response, StatusCode};
 use log::error;
 use sb_core::conn_sync::ConnSync;
 use sb_core::util::sync::AtomicFlag;
 use sb_core::SharedMetricSource;
 use sb_workers::context::{
-    CreateUserWorkerResult, SendRequestResult, Timing, TimingStatus, UserWorkerMsgs,
-    UserWorkerProfile, WorkerContextInitOpts, WorkerRuntimeOpts,
+    CpuProfileRequest, CreateUserWorkerResult, HeapSnapshotOpts, RequestCpuClock, RequestStart,
+    ResponseEnd, SendRequestResult, Timing, TimingStatus, UserWorkerInfo, UserWorkerMsgs,
+    UserWorkerProfile, UserWorkerStats, WorkerContextInitOpts, WorkerRuntimeOpts,
 };
+use sb_workers::errors::WorkerBootError;
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::mpsc::UnboundedSender;
+use std::time::{Duration, Instant};
 use tokio::sync::oneshot::Sender;
 use tokio::sync::{mpsc, watch, Notify, OwnedSemaphorePermit, Semaphore, TryAcquireError};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
-use super::worker_ctx::TerminationToken;
+use super::boot_circuit_breaker::{
+    BootCircuitBreakerPolicy, BootCircuitBreakers, BreakerTransition,
+};
+use super::supervisor::strategy_per_request::PerRequestStrategy;
+use super::supervisor::strategy_per_worker::PerWorkerStrategy;
+use super::supervisor::SupervisorStrategy;
+use super::worker_ctx::{CreateWorkerArgs, TerminationToken};
+use super::worker_pool_config::WorkerPoolConfigWatcher;
 
-#[derive(Debug, Clone, Copy, EnumAsInner)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumAsInner)]
 pub enum SupervisorPolicy {
     PerWorker,
     PerRequest { oneshot: bool },
@@ -58,6 +77,14 @@ impl SupervisorPolicy {
     pub fn is_oneshot(&self) -> bool {
         matches!(self, Self::PerRequest { oneshot: true })
     }
+
+    /// Returns the built-in supervisor strategy for this policy.
+    pub fn strategy(&self) -> Arc<dyn SupervisorStrategy> {
+        match *self {
+            Self::PerWorker => Arc::new(PerWorkerStrategy),
+            Self::PerRequest { oneshot } => Arc::new(PerRequestStrategy { oneshot }),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -65,6 +92,11 @@ pub struct WorkerPoolPolicy {
     supervisor_policy: SupervisorPolicy,
     max_parallelism: usize,
     request_wait_timeout_ms: u64,
+    config_path: Option<PathBuf>,
+    boot_circuit_breaker: Option<BootCircuitBreakerPolicy>,
+    supervisor_strategy: Option<Arc<dyn SupervisorStrategy>>,
+    heap_snapshot: Option<HeapSnapshotOpts>,
+    cpu_profile_dir: Option<PathBuf>,
 }
 
 impl Default for WorkerPoolPolicy {
@@ -78,6 +110,11 @@ impl Default for WorkerPoolPolicy {
             supervisor_policy: SupervisorPolicy::default(),
             max_parallelism: available_parallelism,
             request_wait_timeout_ms: 10000,
+            config_path: None,
+            boot_circuit_breaker: None,
+            supervisor_strategy: None,
+            heap_snapshot: None,
+            cpu_profile_dir: None,
         }
     }
 }
@@ -96,8 +133,59 @@ impl WorkerPoolPolicy {
             request_wait_timeout_ms: request_wait_timeout_ms
                 .into()
                 .unwrap_or(default.request_wait_timeout_ms),
+            config_path: None,
+            boot_circuit_breaker: None,
+            supervisor_strategy: None,
+            heap_snapshot: None,
+            cpu_profile_dir: None,
         }
     }
+
+    /// Loads per-service limits from the given TOML or JSON file. The file is
+    /// watched and reloaded whenever it changes.
+    pub fn with_config_path(mut self, config_path: impl Into<Option<PathBuf>>) -> Self {
+        self.config_path = config_path.into();
+        self
+    }
+
+    pub fn config_path(&self) -> Option<&PathBuf> {
+        self.config_path.as_ref()
+    }
+
+    /// Quarantines services that repeatedly fail to boot. While quarantined,
+    /// worker creation fails fast with the last boot error instead of paying
+    /// the full boot cost again.
+    pub fn with_boot_circuit_breaker(
+        mut self,
+        policy: impl Into<Option<BootCircuitBreakerPolicy>>,
+    ) -> Self {
+        self.boot_circuit_breaker = policy.into();
+        self
+    }
+
+    /// Supervises the user workers with the given strategy instead of the
+    /// built-in one of the supervisor policy.
+    pub fn with_supervisor_strategy(
+        mut self,
+        strategy: impl Into<Option<Arc<dyn SupervisorStrategy>>>,
+    ) -> Self {
+        self.supervisor_strategy = strategy.into();
+        self
+    }
+
+    /// Writes a heap snapshot into the given directory before a user worker
+    /// is terminated due to its memory limit.
+    pub fn with_heap_snapshot(mut self, opts: impl Into<Option<HeapSnapshotOpts>>) -> Self {
+        self.heap_snapshot = opts.into();
+        self
+    }
+
+    /// Allows the user workers to be CPU profiled. Profiles are written into
+    /// the given directory.
+    pub fn with_cpu_profile_dir(mut self, dir: impl Into<Option<PathBuf>>) -> Self {
+        self.cpu_profile_dir = dir.into();
+        self
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -129,19 +217,68 @@ pub struct ActiveWorkerRegistry {
     next: Option<usize>,
     notify_pair: (flume::Sender<Option<Uuid>>, flume::Receiver<Option<Uuid>>),
     sem: Arc<Semaphore>,
+    max_parallelism: usize,
+    version: Option<String>,
+    policy: SupervisorPolicy,
 }
 
 impl ActiveWorkerRegistry {
-    fn new(max_parallelism: usize) -> Self {
+    fn new(max_parallelism: usize, policy: SupervisorPolicy) -> Self {
         Self {
             workers: HashSet::default(),
             next: Option::default(),
             notify_pair: flume::unbounded(),
             sem: Arc::new(Semaphore::const_new(max_parallelism)),
+            max_parallelism,
+            version: None,
+            policy,
         }
     }
 
-    fn mark_used_and_try_advance(&mut self, policy: SupervisorPolicy) -> Option<&Uuid> {
+    /// Changes the number of workers the service can have at once. Shrinking
+    /// takes effect as the current workers release their permits.
+    fn resize(&mut self, max_parallelism: usize) {
+        match max_parallelism.cmp(&self.max_parallelism) {
+            std::cmp::Ordering::Greater => {
+                let added = max_parallelism - self.max_parallelism;
+                let (notify_tx, _) = self.notify_pair.clone();
+
+                self.sem.add_permits(added);
+
+                // wake up the create requests that are waiting for a permit
+                for _ in 0..added {
+                    let _ = notify_tx.send(None);
+                }
+            }
+
+            std::cmp::Ordering::Less => {
+                let removed = self.max_parallelism - max_parallelism;
+                let sem = self.sem.clone();
+
+                drop(tokio::spawn(async move {
+                    for _ in 0..removed {
+                        match sem.clone().acquire_owned().await {
+                            Ok(permit) => permit.forget(),
+                            Err(_) => return,
+                        }
+                    }
+                }));
+            }
+
+            std::cmp::Ordering::Equal => {}
+        }
+
+        self.max_parallelism = max_parallelism;
+    }
+
+    fn is_outdated(&self, version: Option<&str>) -> bool {
+        matches!(
+            (self.version.as_deref(), version),
+            (Some(latest), Some(version)) if latest != version
+        )
+    }
+
+    fn mark_used_and_try_advance(&mut self) -> Option<&Uuid> {
         if self.workers.is_empty() {
             let _ = self.next.take();
             return None;
@@ -154,7 +291,7 @@ impl ActiveWorkerRegistry {
             .unwrap_or(0);
 
         match self.workers.iter().nth(idx).cloned() {
-            Some(WorkerId(key, true)) => match policy {
+            Some(WorkerId(key, true)) => match self.policy {
                 SupervisorPolicy::PerWorker => {
                     self.next = Some(idx + 1);
                     self.workers.get(&key).map(|it| &it.0)
@@ -178,9 +315,9 @@ impl ActiveWorkerRegistry {
         }
     }
 
-    fn mark_idle(&mut self, key: &Uuid, policy: SupervisorPolicy) {
+    fn mark_idle(&mut self, key: &Uuid) {
         if let Some(WorkerId(key, mark)) = self.workers.get(key).cloned() {
-            if policy.is_per_request() {
+            if self.policy.is_per_request() {
                 if mark {
                     return;
                 }
@@ -208,18 +345,23 @@ pub struct WorkerPool {
     pub user_workers: HashMap<Uuid, UserWorkerProfile>,
     pub active_workers: HashMap<String, ActiveWorkerRegistry>,
     pub worker_pool_msgs_tx: mpsc::UnboundedSender<UserWorkerMsgs>,
+    pub config: Option<WorkerPoolConfigWatcher>,
+    pub boot_circuit_breakers: Option<BootCircuitBreakers>,
 
     // TODO: refactor this out of worker pool
-    pub worker_event_sender: Option<mpsc::UnboundedSender<WorkerEventWithMetadata>>,
+    pub worker_event_sender: Option<EventSender>,
 }
 
 impl WorkerPool {
     pub(crate) fn new(
         policy: WorkerPoolPolicy,
         metric_src: SharedMetricSource,
-        worker_event_sender: Option<UnboundedSender<WorkerEventWithMetadata>>,
+        worker_event_sender: Option<EventSender>,
         worker_pool_msgs_tx: mpsc::UnboundedSender<UserWorkerMsgs>,
+        config: Option<WorkerPoolConfigWatcher>,
     ) -> Self {
+        let boot_circuit_breakers = policy.boot_circuit_breaker.map(BootCircuitBreakers::new);
+
         Self {
             policy,
             metric_src,
@@ -227,6 +369,59 @@ impl WorkerPool {
             user_workers: HashMap::new(),
             active_workers: HashMap::new(),
             worker_pool_msgs_tx,
+            config,
+            boot_circuit_breakers,
+        }
+    }
+
+    /// Applies the `max_parallelism` and the supervisor policy of a reloaded
+    /// config to the services which already have workers.
+    pub fn reload_config(&mut self) {
+        let Some(config) = self.config.as_ref().map(|it| it.current()) else {
+            return;
+        };
+
+        let mut policies = vec![];
+
+        for (service_path, registry) in self.active_workers.iter_mut() {
+            let service_config = config.find(service_path);
+            let max_parallelism = service_config
+                .and_then(|it| it.max_parallelism)
+                .unwrap_or(self.policy.max_parallelism);
+
+            registry.resize(max_parallelism);
+            policies.push((
+                service_path.clone(),
+                service_config
+                    .and_then(|it| it.policy)
+                    .unwrap_or(self.policy.supervisor_policy),
+            ));
+        }
+
+        for (service_path, policy) in policies {
+            self.set_supervisor_policy(&service_path, policy);
+        }
+    }
+
+    /// Switches the service to another supervisor policy.
+    ///
+    /// NOTE: The workers of the service are drained, as they are supervised
+    /// under the previous policy.
+    fn set_supervisor_policy(&mut self, service_path: &str, policy: SupervisorPolicy) {
+        let Some(registry) = self
+            .active_workers
+            .get_mut(service_path)
+            .filter(|it| it.policy != policy)
+        else {
+            return;
+        };
+
+        let outdated = registry.workers.iter().map(|it| it.0).collect::<Vec<_>>();
+
+        registry.policy = policy;
+
+        for key in outdated {
+            self.drain(&key);
         }
     }
 
@@ -242,7 +437,69 @@ impl WorkerPool {
             .unwrap_or("")
             .to_string();
 
-        let is_oneshot_policy = self.policy.supervisor_policy.is_oneshot();
+        let mut span = Span::start(
+            "user_worker.create",
+            SpanKind::Internal,
+            worker_options
+                .conf
+                .as_user_worker()
+                .and_then(|it| it.traceparent.as_deref())
+                .and_then(SpanContext::from_traceparent),
+        );
+
+        span.set_attribute("service.path", service_path.as_str());
+
+        let service_config = self
+            .config
+            .as_ref()
+            .and_then(|it| it.current().find(&service_path).cloned());
+
+        if let Some(config) = service_config.as_ref() {
+            config.apply(&mut worker_options);
+        }
+
+        let max_parallelism = service_config
+            .as_ref()
+            .and_then(|it| it.max_parallelism)
+            .unwrap_or(self.policy.max_parallelism);
+
+        let request_wait_timeout_ms = service_config
+            .as_ref()
+            .and_then(|it| it.request_wait_timeout_ms)
+            .unwrap_or(self.policy.request_wait_timeout_ms);
+
+        let supervisor_policy = service_config
+            .as_ref()
+            .and_then(|it| it.policy)
+            .unwrap_or(self.policy.supervisor_policy);
+
+        self.set_supervisor_policy(&service_path, supervisor_policy);
+
+        if let Some(version) = worker_options
+            .conf
+            .as_user_worker()
+            .and_then(|it| it.version.clone())
+        {
+            let registry = self
+                .active_workers
+                .entry(service_path.clone())
+                .or_insert_with(|| ActiveWorkerRegistry::new(max_parallelism, supervisor_policy));
+
+            // NOTE: The version of the latest create request always wins, so
+            // that rolling back to a previous deployment drains the workers
+            // of the current one as well.
+            if registry.version.as_ref() != Some(&version) {
+                let outdated = registry.workers.iter().map(|it| it.0).collect::<Vec<_>>();
+
+                registry.version = Some(version);
+
+                for key in outdated {
+                    self.drain(&key);
+                }
+            }
+        }
+
+        let is_oneshot_policy = supervisor_policy.is_oneshot();
         let force_create = worker_options
             .conf
             .as_user_worker()
@@ -250,6 +507,8 @@ impl WorkerPool {
 
         if let Some(ref active_worker_uuid) = self.maybe_active_worker(&service_path, force_create)
         {
+            span.set_attribute("worker.cold_start", false);
+
             if tx
                 .send(Ok(CreateUserWorkerResult {
                     key: *active_worker_uuid,
@@ -261,6 +520,24 @@ impl WorkerPool {
             return;
         }
 
+        let is_boot_probe = match self
+            .boot_circuit_breakers
+            .as_mut()
+            .map(|it| it.try_acquire(&service_path))
+        {
+            Some(Err(err)) => {
+                span.set_error(&err);
+
+                if tx.send(Err(err.into())).is_err() {
+                    error!("main worker receiver dropped")
+                }
+                return;
+            }
+
+            Some(Ok(is_probe)) => is_probe,
+            None => false,
+        };
+
         enum FlowAfterFence {
             Stop,
             Resend(Sender<Result<CreateUserWorkerResult, Error>>),
@@ -274,12 +551,11 @@ impl WorkerPool {
             let registry = self
                 .active_workers
                 .entry(service_path.clone())
-                .or_insert_with(|| ActiveWorkerRegistry::new(self.policy.max_parallelism));
+                .or_insert_with(|| ActiveWorkerRegistry::new(max_parallelism, supervisor_policy));
 
             let sem = registry.sem.clone();
             let (_, notify_rx) = registry.notify_pair.clone();
-            let wait_timeout =
-                tokio::time::sleep(Duration::from_millis(self.policy.request_wait_timeout_ms));
+            let wait_timeout = tokio::time::sleep(Duration::from_millis(request_wait_timeout_ms));
 
             async move {
                 use FlowAfterFence::*;
@@ -330,11 +606,29 @@ impl WorkerPool {
 
         let worker_pool_msgs_tx = self.worker_pool_msgs_tx.clone();
         let events_msg_tx = self.worker_event_sender.clone();
-        let supervisor_policy = self.policy.supervisor_policy;
+        let supervisor_strategy = self.policy.supervisor_strategy.clone();
+        let heap_snapshot = self.policy.heap_snapshot.clone();
+        let cpu_profile_dir = self.policy.cpu_profile_dir.clone();
 
         drop(tokio::spawn(async move {
-            let (permit, tx) = match wait_fence_fut.await {
-                FlowAfterFence::Stop => return,
+            let flow = wait_fence_fut.await;
+
+            // NOTE: Only a create request that boots a worker reports back to
+            // the circuit breaker, so the probe must be released otherwise.
+            if is_boot_probe
+                && !matches!(flow, FlowAfterFence::Create(..))
+                && worker_pool_msgs_tx
+                    .send(UserWorkerMsgs::BootProbeReleased(service_path.clone()))
+                    .is_err()
+            {
+                error!("user worker msgs receiver dropped")
+            }
+
+            let (permit, tx) = match flow {
+                FlowAfterFence::Stop => {
+                    span.set_error("no worker became available");
+                    return;
+                }
                 FlowAfterFence::Resend(tx) => {
                     let WorkerContextInitOpts {
                         service_path,
@@ -375,6 +669,8 @@ impl WorkerPool {
                 FlowAfterFence::Create(permit, tx) => (permit, tx),
             };
 
+            span.set_attribute("worker.cold_start", true);
+
             let Ok(mut user_worker_rt_opts) = worker_options.conf.into_user_worker() else {
                 return;
             };
@@ -382,41 +678,83 @@ impl WorkerPool {
             let uuid = uuid::Uuid::new_v4();
             let cancel = Arc::<Notify>::default();
             let (req_start_timing_tx, req_start_timing_rx) =
-                mpsc::unbounded_channel::<Arc<Notify>>();
+                mpsc::unbounded_channel::<RequestStart>();
 
             let status = TimingStatus {
                 demand: Arc::new(AtomicUsize::new(0)),
                 is_retired: Arc::new(AtomicFlag::default()),
+                drain: Arc::default(),
+                cpu_time_used_ms: Arc::default(),
+                cpu_clock: RequestCpuClock::default(),
+                served_requests: Arc::default(),
             };
 
-            let (req_end_timing_tx, req_end_timing_rx) = mpsc::unbounded_channel::<()>();
+            let (req_end_timing_tx, req_end_timing_rx) = mpsc::unbounded_channel::<Uuid>();
+
+            let version = user_worker_rt_opts.version.clone();
+            let cpu_time_header = user_worker_rt_opts
+                .cpu_time_header
+                .as_deref()
+                .and_then(|it| HeaderName::from_str(it).ok());
+            let (cpu_profile_tx, cpu_profile_rx) = cpu_profile_dir
+                .is_some()
+                .then(mpsc::unbounded_channel::<CpuProfileRequest>)
+                .unzip();
+
+            // NOTE: Every user worker has its own termination token so that it
+            // can be terminated individually through the pool.
+            let termination_token = termination_token.unwrap_or_default();
 
             user_worker_rt_opts.service_path = Some(service_path.clone());
             user_worker_rt_opts.key = Some(uuid);
 
             user_worker_rt_opts.pool_msg_tx = Some(worker_pool_msgs_tx.clone());
             user_worker_rt_opts.events_msg_tx = events_msg_tx;
+            user_worker_rt_opts.heap_snapshot = heap_snapshot;
+            user_worker_rt_opts.cpu_profile_dir = cpu_profile_dir;
             user_worker_rt_opts.cancel = Some(cancel.clone());
 
             worker_options.timing = Some(Timing {
                 status: status.clone(),
                 req: (req_start_timing_rx, req_end_timing_rx),
+                cpu_profile_rx,
             });
 
             worker_options.conf = WorkerRuntimeOpts::UserWorker(user_worker_rt_opts);
 
-            match create_worker((worker_options, supervisor_policy, termination_token.clone()))
-                .await
+            match create_worker(
+                CreateWorkerArgs::from((
+                    worker_options,
+                    supervisor_policy,
+                    Some(termination_token.clone()),
+                ))
+                .with_supervisor_strategy(supervisor_strategy),
+            )
+            .await
             {
-                Ok((_, worker_request_msg_tx)) => {
+                Ok((metric_src, worker_request_msg_tx)) => {
+                    span.set_attribute("execution.id", uuid.to_string());
+
                     let profile = UserWorkerProfile {
                         worker_request_msg_tx,
                         timing_tx_pair: (req_start_timing_tx, req_end_timing_tx),
                         service_path,
+                        version,
+                        cpu_time_header,
+                        cpu_profile_tx,
                         permit: permit.map(Arc::new),
                         status: status.clone(),
                         cancel,
+                        metric_src: metric_src.into_worker().ok(),
+                        termination_token: termination_token.inbound.clone(),
+                        created_at: Instant::now(),
                     };
+
+                    // NOTE: Demand must be counted before the pool sees the
+                    // worker. Otherwise, the worker could be drained before the
+                    // request that created it is sent.
+                    status.demand.fetch_add(1, Ordering::Release);
+
                     if worker_pool_msgs_tx
                         .send(UserWorkerMsgs::Created(uuid, profile))
                         .is_err()
@@ -426,10 +764,19 @@ impl WorkerPool {
                     if tx.send(Ok(CreateUserWorkerResult { key: uuid })).is_err() {
                         error!("main worker receiver dropped")
                     };
-
-                    status.demand.fetch_add(1, Ordering::Release);
                 }
                 Err(e) => {
+                    span.set_error(&e);
+
+                    if worker_pool_msgs_tx
+                        .send(UserWorkerMsgs::BootFailed(
+                            service_path,
+                            WorkerBootError::from(&e),
+                        ))
+                        .is_err()
+                    {
+                        error!("user worker msgs receiver dropped")
+                    }
                     if tx.send(Err(e)).is_err() {
                         error!("main worker receiver dropped")
                     } else {
@@ -441,17 +788,101 @@ impl WorkerPool {
     }
 
     pub fn add_user_worker(&mut self, key: Uuid, profile: UserWorkerProfile) {
+        let service_config = self
+            .config
+            .as_ref()
+            .and_then(|it| it.current().find(&profile.service_path).cloned());
+        let max_parallelism = service_config
+            .as_ref()
+            .and_then(|it| it.max_parallelism)
+            .unwrap_or(self.policy.max_parallelism);
+        let supervisor_policy = service_config
+            .as_ref()
+            .and_then(|it| it.policy)
+            .unwrap_or(self.policy.supervisor_policy);
+
         let registry = self
             .active_workers
             .entry(profile.service_path.clone())
-            .or_insert_with(|| ActiveWorkerRegistry::new(self.policy.max_parallelism));
+            .or_insert_with(|| ActiveWorkerRegistry::new(max_parallelism, supervisor_policy));
+
+        // NOTE: A worker of the previous version can finish booting after a
+        // newer version was requested. It must only serve the request that
+        // created it.
+        let is_outdated = registry.is_outdated(profile.version.as_deref());
 
-        registry
-            .workers
-            .insert(WorkerId(key, self.policy.supervisor_policy.is_per_worker()));
+        if !is_outdated {
+            registry
+                .workers
+                .insert(WorkerId(key, registry.policy.is_per_worker()));
+        }
+
+        if let Some(transition) = self
+            .boot_circuit_breakers
+            .as_mut()
+            .and_then(|it| it.record_success(&profile.service_path))
+        {
+            self.send_boot_circuit_breaker_event(&profile.service_path, transition);
+        }
+
+        if let Some(version) = profile.version.as_deref() {
+            self.metric_src.incl_active_user_worker_version(version);
+        }
 
         self.user_workers.insert(key, profile);
         self.metric_src.incl_active_user_workers();
+
+        if is_outdated {
+            self.drain(&key);
+        }
+    }
+
+    pub fn boot_probe_released(&mut self, service_path: &str) {
+        if let Some(breakers) = self.boot_circuit_breakers.as_mut() {
+            breakers.release_probe(service_path);
+        }
+    }
+
+    pub fn boot_failed(&mut self, service_path: &str, error: WorkerBootError) {
+        if let Some(transition) = self
+            .boot_circuit_breakers
+            .as_mut()
+            .and_then(|it| it.record_failure(service_path, error))
+        {
+            self.send_boot_circuit_breaker_event(service_path, transition);
+        }
+    }
+
+    fn send_boot_circuit_breaker_event(&self, service_path: &str, transition: BreakerTransition) {
+        let event = match transition {
+            BreakerTransition::Tripped { failures, error } => {
+                error!(
+                    "service has been quarantined after {} boot failures: {}",
+                    failures, service_path
+                );
+
+                BootCircuitBreakerEvent {
+                    state: BootCircuitBreakerState::Open,
+                    failures,
+                    error: Some(error.to_string()),
+                }
+            }
+
+            BreakerTransition::Reset => BootCircuitBreakerEvent {
+                state: BootCircuitBreakerState::Closed,
+                failures: 0,
+                error: None,
+            },
+        };
+
+        send_event_if_event_worker_available(
+            self.worker_event_sender.clone(),
+            WorkerEvents::BootCircuitBreaker(event),
+            EventMetadata {
+                service_path: Some(service_path.to_string()),
+                ..Default::default()
+            },
+        );
     }
 
     pub fn send_request(
@@ -463,17 +894,68 @@ impl WorkerPool {
     ) {
         let _: Result<(), Error> = match self.user_workers.get(key) {
             Some(worker) => {
-                let policy = self.policy.supervisor_policy;
+                let policy = self
+                    .active_workers
+                    .get(&worker.service_path)
+                    .map_or(self.policy.supervisor_policy, |it| it.policy);
                 let profile = worker.clone();
                 let cancel = worker.cancel.clone();
                 let (req_start_tx, req_end_tx) = profile.timing_tx_pair.clone();
+                let events_msg_tx = self.worker_event_sender.clone();
+                let event_metadata = EventMetadata {
+                    service_path: Some(profile.service_path.clone()),
+                    execution_id: Some(*key),
+                    version: profile.version.clone(),
+                };
 
                 // Create a closure to handle the request and send the response
                 let request_handler = async move {
-                    if !policy.is_per_worker() {
-                        let fence = Arc::new(Notify::const_new());
+                    let started_at = Instant::now();
+                    let mut req = req;
+                    let method = req.method().to_string();
+                    let path = req.uri().path().to_string();
+                    let mut span = Span::start(
+                        "user_worker.request",
+                        SpanKind::Internal,
+                        SpanContext::from_headers(req.headers()),
+                    );
+
+                    span.set_attribute("http.method", method.as_str());
+                    span.set_attribute("http.target", path.as_str());
+                    span.set_attribute("service.path", profile.service_path.as_str());
+
+                    // NOTE: The user worker sees this span as the parent, and
+                    // so do its outbound requests.
+                    if let Some(ctx) = span.context() {
+                        ctx.inject(req.headers_mut());
+                    }
 
-                        if let Err(ex) = req_start_tx.send(fence.clone()) {
+                    let request_bytes = Arc::<AtomicUsize>::default();
+                    let req = if HttpBody::size_hint(req.body()).exact() == Some(0) {
+                        req
+                    } else {
+                        let request_bytes = request_bytes.clone();
+
+                        req.map(|body| {
+                            Body::wrap_stream(body.inspect_ok(move |chunk| {
+                                request_bytes.fetch_add(chunk.len(), Ordering::Relaxed);
+                            }))
+                        })
+                    };
+
+                    let request_id = Uuid::new_v4();
+                    let fence = Arc::new(Notify::const_new());
+                    let timeout = CancellationToken::new();
+                    let req_start = RequestStart {
+                        id: request_id,
+                        fence: fence.clone(),
+                        timeout: timeout.clone(),
+                    };
+
+                    // NOTE: The start signal is sent under every policy, so that
+                    // the supervisor can track the request timeout.
+                    if let Err(ex) = req_start_tx.send(req_start) {
+                        if !policy.is_per_worker() {
                             // NOTE(Nyannyacha): The only way to be trapped in
                             // this branch is if the supervisor associated with
                             // the isolate has been terminated for some reason,
@@ -489,23 +971,113 @@ impl WorkerPool {
                             return Err(ex)
                                 .with_context(|| "failed to notify the fence to the supervisor");
                         }
+                    }
 
+                    if !policy.is_per_worker() {
                         fence.notified().await;
                     }
 
-                    let result = send_user_worker_request(
-                        profile.worker_request_msg_tx,
-                        cancel,
-                        req,
-                        conn_watch,
-                    )
-                    .await;
+                    send_event_if_event_worker_available(
+                        events_msg_tx.clone(),
+                        WorkerEvents::RequestStarted(RequestStartedEvent {
+                            method: method.clone(),
+                            path: path.clone(),
+                        }),
+                        event_metadata.clone(),
+                    );
+
+                    let cpu_clock = profile.status.cpu_clock.clone();
+                    let served_requests = profile.status.served_requests.clone();
+                    let cpu_clock_start = cpu_clock.enter();
+                    let result = tokio::select! {
+                        result = send_user_worker_request(
+                            profile.worker_request_msg_tx,
+                            cancel,
+                            req,
+                            conn_watch,
+                        ) => result,
+
+                        // NOTE: Dropping the pending request lets the worker
+                        // see it as aborted.
+                        () = timeout.cancelled() => Response::builder()
+                            .status(StatusCode::GATEWAY_TIMEOUT)
+                            .body(Body::empty())
+                            .map_err(Error::from),
+                    };
 
                     match result {
-                        Ok(rep) => Ok((rep, req_end_tx)),
+                        Ok(mut rep) => {
+                            if let Some(name) = profile.cpu_time_header {
+                                let cpu_time_ms = cpu_clock.elapsed(cpu_clock_start) / 1_000_000;
+
+                                rep.headers_mut()
+                                    .insert(name, HeaderValue::from(cpu_time_ms));
+                            }
+
+                            // NOTE: The request is considered finished once the
+                            // caller drops or signals the end of the response,
+                            // so the CPU time spent streaming the body is
+                            // attributed too.
+                            let status = rep.status().as_u16();
+                            span.set_attribute("http.status_code", status);
+
+                            let (res_end_tx, mut res_end_rx) =
+                                mpsc::unbounded_channel::<ResponseEnd>();
+
+                            tokio::task::spawn(async move {
+                                // NOTE: The caller dropping the sender without
+                                // signalling the end is considered an abort.
+                                let res_end = res_end_rx.recv().await.unwrap_or(ResponseEnd {
+                                    response_bytes: 0,
+                                    aborted: true,
+                                });
+
+                                let cpu_time_used = cpu_clock.leave(cpu_clock_start) / 1_000_000;
+                                served_requests.fetch_add(1, Ordering::Release);
+                                let _ = req_end_tx.send(request_id);
+
+                                send_event_if_event_worker_available(
+                                    events_msg_tx,
+                                    WorkerEvents::RequestCompleted(RequestCompletedEvent {
+                                        method,
+                                        path,
+                                        status: Some(status),
+                                        duration_ms: started_at.elapsed().as_millis() as usize,
+                                        request_bytes: request_bytes.load(Ordering::Relaxed),
+                                        response_bytes: res_end.response_bytes,
+                                        cpu_time_used: cpu_time_used as usize,
+                                        aborted: res_end.aborted,
+                                    }),
+                                    event_metadata,
+                                );
+
+                                span.set_attribute("http.aborted", res_end.aborted);
+                            });
+
+                            Ok((rep, res_end_tx))
+                        }
                         Err(err) => {
-                            let _ = req_end_tx.send(());
+                            let cpu_time_used = cpu_clock.leave(cpu_clock_start) / 1_000_000;
+                            served_requests.fetch_add(1, Ordering::Release);
+                            let _ = req_end_tx.send(request_id);
+
+                            send_event_if_event_worker_available(
+                                events_msg_tx,
+                                WorkerEvents::RequestCompleted(RequestCompletedEvent {
+                                    method,
+                                    path,
+                                    status: None,
+                                    duration_ms: started_at.elapsed().as_millis() as usize,
+                                    request_bytes: request_bytes.load(Ordering::Relaxed),
+                                    response_bytes: 0,
+                                    cpu_time_used: cpu_time_used as usize,
+                                    aborted: false,
+                                }),
+                                event_metadata,
+                            );
+
                             error!("failed to send request to user worker: {}", err.to_string());
+                            span.set_error(&err);
                             Err(err)
                         }
                     }
@@ -534,23 +1106,136 @@ impl WorkerPool {
         };
     }
 
+    pub fn list_user_workers(&self) -> Vec<UserWorkerInfo> {
+        self.user_workers
+            .iter()
+            .map(|(key, profile)| UserWorkerInfo {
+                key: *key,
+                service_path: profile.service_path.clone(),
+                version: profile.version.clone(),
+                demand: profile.status.demand.load(Ordering::Acquire),
+                age_ms: profile.created_at.elapsed().as_millis() as u64,
+                is_retired: profile.status.is_retired.is_raised(),
+            })
+            .collect()
+    }
+
+    pub fn user_worker_stats(&self, key: &Uuid, tx: Sender<Result<UserWorkerStats, Error>>) {
+        let Some(profile) = self.user_workers.get(key) else {
+            if tx.send(Err(anyhow!("user worker not available"))).is_err() {
+                error!("main worker receiver dropped")
+            }
+            return;
+        };
+
+        let cpu_time_used_ms = profile.status.cpu_time_used_ms.load(Ordering::Acquire);
+        let metric_src = profile.metric_src.clone();
+
+        drop(tokio::spawn(async move {
+            let heap_stats = match metric_src.as_ref() {
+                Some(src) => src.get_heap_statistics().await,
+                None => None,
+            };
+
+            if tx
+                .send(Ok(UserWorkerStats {
+                    cpu_time_used_ms,
+                    heap_stats,
+                }))
+                .is_err()
+            {
+                error!("main worker receiver dropped")
+            }
+        }));
+    }
+
+    pub fn user_worker_cpu_profile(
+        &self,
+        key: &Uuid,
+        duration_ms: u64,
+        tx: Sender<Result<PathBuf, Error>>,
+    ) {
+        let Some(profile) = self.user_workers.get(key) else {
+            if tx.send(Err(anyhow!("user worker not available"))).is_err() {
+                error!("main worker receiver dropped")
+            }
+            return;
+        };
+
+        let Some(cpu_profile_tx) = profile.cpu_profile_tx.as_ref() else {
+            if tx
+                .send(Err(anyhow!("cpu profiling is not enabled")))
+                .is_err()
+            {
+                error!("main worker receiver dropped")
+            }
+            return;
+        };
+
+        if let Err(err) = cpu_profile_tx.send(CpuProfileRequest {
+            duration_ms,
+            result_tx: Some(tx),
+        }) {
+            if let Some(tx) = err.0.result_tx {
+                if tx.send(Err(anyhow!("user worker not available"))).is_err() {
+                    error!("main worker receiver dropped")
+                }
+            }
+        }
+    }
+
+    /// Stops routing requests to the worker. The worker keeps serving the
+    /// requests it already has until the supervisor terminates it.
+    pub fn retire_user_worker(&mut self, key: &Uuid) -> Result<(), Error> {
+        let Some(profile) = self.user_workers.get(key) else {
+            bail!("user worker not available");
+        };
+
+        profile.status.is_retired.raise();
+        self.retire(key);
+
+        Ok(())
+    }
+
+    pub fn terminate_user_worker(&mut self, key: &Uuid) -> Result<(), Error> {
+        let Some(token) = self
+            .user_workers
+            .get(key)
+            .map(|it| it.termination_token.clone())
+        else {
+            bail!("user worker not available");
+        };
+
+        self.retire_user_worker(key)?;
+        token.cancel();
+
+        Ok(())
+    }
+
     pub fn idle(&mut self, key: &Uuid) {
         if let Some(registry) = self
             .user_workers
             .get_mut(key)
             .and_then(|it| self.active_workers.get_mut(&it.service_path))
         {
-            registry.mark_idle(key, self.policy.supervisor_policy);
+            registry.mark_idle(key);
         }
     }
 
     pub fn shutdown(&mut self, key: &Uuid) {
         self.retire(key);
 
+        let Some(profile) = self.user_workers.remove(key) else {
+            return;
+        };
+
+        if let Some(version) = profile.version.as_deref() {
+            self.metric_src.decl_active_user_worker_version(version);
+        }
+
         let Some((notify_tx, _)) = self
-            .user_workers
-            .remove(key)
-            .and_then(|it| self.active_workers.get(&it.service_path))
+            .active_workers
+            .get(&profile.service_path)
             .map(|it| it.notify_pair.clone())
         else {
             return;
@@ -561,6 +1246,18 @@ impl WorkerPool {
         self.metric_src.decl_active_user_workers();
     }
 
+    /// Retires the worker and lets it terminate as soon as its in-flight
+    /// requests are finished.
+    fn drain(&mut self, key: &Uuid) {
+        self.retire(key);
+
+        if let Some(profile) = self.user_workers.get(key) {
+            profile.status.is_retired.raise();
+            profile.status.drain.notify_one();
+            self.metric_src.incl_drained_user_worker();
+        }
+    }
+
     fn retire(&mut self, key: &Uuid) {
         if let Some(profile) = self.user_workers.get_mut(key) {
             let registry = self
@@ -591,8 +1288,7 @@ impl WorkerPool {
             return None;
         };
 
-        let policy = self.policy.supervisor_policy;
-        let mut advance_fn = move || registry.mark_used_and_try_advance(policy).copied();
+        let mut advance_fn = move || registry.mark_used_and_try_advance().copied();
 
         let Some(worker_uuid) = advance_fn() else {
             return None;
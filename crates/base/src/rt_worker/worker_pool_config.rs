@@ -0,0 +1,273 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Error};
+use log::{error, info};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use sb_workers::context::{UserWorkerRuntimeOpts, WorkerContextInitOpts};
+use serde::{Deserialize, Deserializer};
+use tokio::sync::watch;
+
+use super::worker_pool::SupervisorPolicy;
+
+fn deserialize_glob<'de, D>(deserializer: D) -> Result<glob::Pattern, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+
+    glob::Pattern::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+/// A glob pattern matched against the service path of a user worker.
+#[derive(Debug, Clone)]
+pub struct ServicePattern(glob::Pattern);
+
+impl<'de> Deserialize<'de> for ServicePattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_glob(deserializer).map(Self)
+    }
+}
+
+impl ServicePattern {
+    pub fn matches(&self, service_path: &str) -> bool {
+        self.0.matches(service_path)
+    }
+}
+
+/// A glob pattern matched against the name of an environment variable (e.g.
+/// `SUPABASE_*`).
+#[derive(Debug, Clone)]
+pub struct EnvVarPattern(glob::Pattern);
+
+impl<'de> Deserialize<'de> for EnvVarPattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_glob(deserializer).map(Self)
+    }
+}
+
+impl EnvVarPattern {
+    pub fn matches(&self, name: &str) -> bool {
+        self.0.matches(name)
+    }
+}
+
+fn deserialize_supervisor_policy<'de, D>(
+    deserializer: D,
+) -> Result<Option<SupervisorPolicy>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let policy = String::deserialize(deserializer)?;
+
+    match policy.as_str() {
+        "per_worker" => Ok(Some(SupervisorPolicy::PerWorker)),
+        "per_request" => Ok(Some(SupervisorPolicy::PerRequest { oneshot: false })),
+        "oneshot" => Ok(Some(SupervisorPolicy::oneshot())),
+        _ => Err(serde::de::Error::unknown_variant(
+            &policy,
+            &["per_worker", "per_request", "oneshot"],
+        )),
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceLimits {
+    pub memory_limit_mb: Option<u64>,
+    pub low_memory_multiplier: Option<u64>,
+    pub worker_timeout_ms: Option<u64>,
+    pub cpu_time_soft_limit_ms: Option<u64>,
+    pub cpu_time_hard_limit_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceConfig {
+    pub path: ServicePattern,
+    #[serde(default)]
+    pub limits: ServiceLimits,
+    /// Supervisor policy of the workers of the service, in place of the one
+    /// of the pool. Changing it drains the current workers of the service.
+    #[serde(default, deserialize_with = "deserialize_supervisor_policy")]
+    pub policy: Option<SupervisorPolicy>,
+    pub max_parallelism: Option<usize>,
+    pub request_wait_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub net_access_disabled: bool,
+    pub env_allowlist: Option<Vec<EnvVarPattern>>,
+}
+
+impl ServiceConfig {
+    /// Clamps the options requested by the main worker to the limits of this
+    /// entry. Values requested by the main worker can only be tightened here,
+    /// never loosened.
+    pub fn apply(&self, worker_options: &mut WorkerContextInitOpts) {
+        if let Some(allowlist) = self.env_allowlist.as_ref() {
+            worker_options
+                .env_vars
+                .retain(|key, _| allowlist.iter().any(|it| it.matches(key)));
+        }
+
+        let Some(conf) = worker_options.conf.as_user_worker_mut() else {
+            return;
+        };
+
+        self.apply_to_runtime_opts(conf);
+    }
+
+    pub fn apply_to_runtime_opts(&self, conf: &mut UserWorkerRuntimeOpts) {
+        let ServiceLimits {
+            memory_limit_mb,
+            low_memory_multiplier,
+            worker_timeout_ms,
+            cpu_time_soft_limit_ms,
+            cpu_time_hard_limit_ms,
+        } = self.limits;
+
+        clamp(&mut conf.memory_limit_mb, memory_limit_mb);
+        clamp(&mut conf.low_memory_multiplier, low_memory_multiplier);
+        clamp(&mut conf.worker_timeout_ms, worker_timeout_ms);
+        clamp_cpu_time(&mut conf.cpu_time_soft_limit_ms, cpu_time_soft_limit_ms);
+        clamp_cpu_time(&mut conf.cpu_time_hard_limit_ms, cpu_time_hard_limit_ms);
+
+        if self.net_access_disabled {
+            conf.net_access_disabled = true;
+        }
+    }
+}
+
+fn clamp(requested: &mut u64, cap: Option<u64>) {
+    if let Some(cap) = cap {
+        *requested = (*requested).min(cap);
+    }
+}
+
+// NOTE: Zero disables the CPU timer, which means "no limit", so it must be
+// replaced with the configured cap as well.
+fn clamp_cpu_time(requested: &mut u64, cap: Option<u64>) {
+    if let Some(cap) = cap {
+        if *requested == 0 || *requested > cap {
+            *requested = cap;
+        }
+    }
+}
+
+/// Declarative limits for user workers, keyed by service path.
+///
+/// The first entry whose pattern matches the service path of a worker wins.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkerPoolConfig {
+    #[serde(default, rename = "service")]
+    pub services: Vec<ServiceConfig>,
+}
+
+impl WorkerPoolConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("can't read worker pool config: {}", path.display()))?;
+
+        match path.extension().and_then(|it| it.to_str()) {
+            Some("toml") => Self::from_toml_str(&content),
+            Some("json") => Self::from_json_str(&content),
+            _ => bail!(
+                "unsupported worker pool config format (expected .toml or .json): {}",
+                path.display()
+            ),
+        }
+    }
+
+    pub fn from_toml_str(content: &str) -> Result<Self, Error> {
+        toml::from_str(content).map_err(|err| anyhow!("invalid worker pool config: {}", err))
+    }
+
+    pub fn from_json_str(content: &str) -> Result<Self, Error> {
+        deno_core::serde_json::from_str(content)
+            .map_err(|err| anyhow!("invalid worker pool config: {}", err))
+    }
+
+    pub fn find(&self, service_path: &str) -> Option<&ServiceConfig> {
+        self.services
+            .iter()
+            .find(|it| it.path.matches(service_path))
+    }
+}
+
+/// Keeps the latest successfully parsed [`WorkerPoolConfig`] and reloads it
+/// whenever the underlying file changes.
+pub struct WorkerPoolConfigWatcher {
+    rx: watch::Receiver<Arc<WorkerPoolConfig>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl WorkerPoolConfigWatcher {
+    pub fn new(path: PathBuf) -> Result<Self, Error> {
+        let initial = WorkerPoolConfig::from_file(&path)?;
+        let (tx, rx) = watch::channel(Arc::new(initial));
+
+        let mut watcher = notify::recommended_watcher({
+            let path = path.clone();
+            move |result: notify::Result<notify::Event>| {
+                let Ok(event) = result else {
+                    return;
+                };
+
+                if !(event.kind.is_modify() || event.kind.is_create()) {
+                    return;
+                }
+
+                // NOTE: The events of the other files in the parent directory
+                // are received as well (see below).
+                if !event
+                    .paths
+                    .iter()
+                    .any(|it| it.file_name() == path.file_name())
+                {
+                    return;
+                }
+
+                match WorkerPoolConfig::from_file(&path) {
+                    Ok(config) => {
+                        info!("worker pool config reloaded: {}", path.display());
+                        let _ = tx.send(Arc::new(config));
+                    }
+
+                    // NOTE: Keep the previous config if the new one can't be
+                    // parsed. It's likely that the file is still being written.
+                    Err(err) => error!("failed to reload worker pool config: {:?}", err),
+                }
+            }
+        })?;
+
+        // NOTE: Editors tend to replace the file rather than modifying it in
+        // place, so the parent directory must be watched instead of the file.
+        let watch_target = path
+            .parent()
+            .filter(|it| !it.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+
+        watcher.watch(watch_target, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    pub fn current(&self) -> Arc<WorkerPoolConfig> {
+        self.rx.borrow().clone()
+    }
+
+    /// Resolves once a new config was loaded.
+    pub async fn changed(&mut self) -> Result<(), watch::error::RecvError> {
+        self.rx.changed().await
+    }
+}
@@ -1,13 +1,16 @@
+use crate::event_sinks::{spawn_event_sinks, EventSinkConfig};
+use crate::otel::{Span, SpanContext, SpanKind};
 use crate::rt_worker::worker_ctx::{
     create_events_worker, create_main_worker, create_user_worker_pool, TerminationToken,
 };
 use crate::rt_worker::worker_pool::WorkerPoolPolicy;
 use anyhow::Error;
-use event_worker::events::WorkerEventWithMetadata;
+use event_worker::channel::{EventChannelOpts, EventSender};
 use futures_util::Stream;
 use hyper::{server::conn::Http, service::Service, Body, Request, Response};
 use log::{debug, error, info};
 use sb_core::conn_sync::ConnSync;
+use sb_core::permissions::FsAccessRules;
 use sb_core::SharedMetricSource;
 use sb_workers::context::{MainWorkerRuntimeOpts, WorkerRequestMsg};
 use std::future::Future;
@@ -36,6 +39,8 @@ pub enum ServerHealth {
 struct NotifyOnEos<S> {
     inner: S,
     cancel: Option<CancellationToken>,
+    // ends along with the response body
+    span: Option<Span>,
 }
 
 impl<S> Drop for NotifyOnEos<S> {
@@ -43,6 +48,8 @@ impl<S> Drop for NotifyOnEos<S> {
         if let Some(cancel) = self.cancel.take() {
             cancel.cancel();
         }
+
+        drop(self.span.take());
     }
 }
 
@@ -89,7 +96,7 @@ impl Service<Request<Body>> for WorkerService {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
         // create a response in a future.
         let cancel = self.cancel.child_token();
         let metric_src = self.metric_src.clone();
@@ -99,6 +106,25 @@ impl Service<Request<Body>> for WorkerService {
             let (ob_conn_watch_tx, ob_conn_watch_rx) = watch::channel(ConnSync::Want);
 
             let req_uri = req.uri().clone();
+            let mut server_span = Span::start(
+                format!("HTTP {}", req.method()),
+                SpanKind::Server,
+                SpanContext::from_headers(req.headers()),
+            );
+
+            server_span.set_attribute("http.method", req.method().as_str());
+            server_span.set_attribute("http.target", req_uri.path());
+
+            let mut main_worker_span = Span::start(
+                "main_worker.request",
+                SpanKind::Internal,
+                server_span.context(),
+            );
+
+            if let Some(ctx) = main_worker_span.context() {
+                ctx.inject(req.headers_mut());
+            }
+
             let msg = WorkerRequestMsg {
                 req,
                 res_tx,
@@ -131,6 +157,8 @@ impl Service<Request<Body>> for WorkerService {
                 Ok(res) => res,
                 Err(err) => {
                     metric_src.incl_handled_requests();
+                    main_worker_span.set_error(&err);
+                    server_span.set_error(&err);
                     return Err(err.into());
                 }
             };
@@ -144,23 +172,35 @@ impl Service<Request<Body>> for WorkerService {
                         e
                     );
 
+                    main_worker_span.set_error(&e);
+                    server_span.set_error(&e);
+                    server_span.set_attribute("http.status_code", 500u16);
+
                     // FIXME: add an error body
                     return Ok(Response::builder()
                         .status(500)
                         .body(Body::wrap_stream(NotifyOnEos {
                             inner: Body::empty(),
                             cancel: Some(cancel.clone()),
+                            span: Some(server_span),
                         }))
                         .unwrap());
                 }
             };
 
+            let status = res.status().as_u16();
+
+            main_worker_span.set_attribute("http.status_code", status);
+            main_worker_span.end();
+            server_span.set_attribute("http.status_code", status);
+
             let (parts, body) = res.into_parts();
             let res = Response::from_parts(
                 parts,
                 Body::wrap_stream(NotifyOnEos {
                     inner: body,
                     cancel: Some(cancel.clone()),
+                    span: Some(server_span),
                 }),
             );
 
@@ -193,14 +233,17 @@ impl Server {
         port: u16,
         main_service_path: String,
         maybe_events_service_path: Option<String>,
+        event_sinks: Vec<EventSinkConfig>,
+        events_channel_opts: EventChannelOpts,
         maybe_user_worker_policy: Option<WorkerPoolPolicy>,
         import_map_path: Option<String>,
         no_module_cache: bool,
         callback_tx: Option<Sender<ServerHealth>>,
         entrypoints: WorkerEntrypoints,
+        main_fs_access_rules: FsAccessRules,
         termination_token: Option<TerminationToken>,
     ) -> Result<Self, Error> {
-        let mut worker_events_tx: Option<mpsc::UnboundedSender<WorkerEventWithMetadata>> = None;
+        let mut worker_events_tx: Option<EventSender> = None;
         let maybe_events_entrypoint = entrypoints.events;
         let maybe_main_entrypoint = entrypoints.main;
         let termination_token = termination_token.unwrap_or_default();
@@ -215,6 +258,7 @@ impl Server {
                 import_map_path.clone(),
                 no_module_cache,
                 maybe_events_entrypoint,
+                events_channel_opts.clone(),
                 Some(termination_token.child_token()),
             )
             .await?;
@@ -225,6 +269,14 @@ impl Server {
             None
         };
 
+        if !event_sinks.is_empty() {
+            worker_events_tx = Some(spawn_event_sinks(
+                event_sinks,
+                worker_events_tx,
+                events_channel_opts,
+            )?);
+        }
+
         // Create a user worker pool
         let (shared_metric_src, worker_pool_tx) = create_user_worker_pool(
             maybe_user_worker_policy.unwrap_or_default(),
@@ -243,6 +295,7 @@ impl Server {
                 worker_pool_tx,
                 shared_metric_src: Some(shared_metric_src.clone()),
                 event_worker_metric_src,
+                fs_access_rules: main_fs_access_rules,
             },
             maybe_main_entrypoint,
             Some(termination_token.child_token()),
@@ -1,10 +1,10 @@
+use event_worker::channel::EventSender;
 use event_worker::events::{EventMetadata, WorkerEventWithMetadata, WorkerEvents};
-use tokio::sync::mpsc;
 
 pub mod units;
 
 pub fn send_event_if_event_worker_available(
-    maybe_event_worker: Option<mpsc::UnboundedSender<WorkerEventWithMetadata>>,
+    maybe_event_worker: Option<EventSender>,
     event: WorkerEvents,
     metadata: EventMetadata,
 ) {
@@ -13,17 +13,30 @@ use base::rt_worker::{
     worker_ctx::{create_user_worker_pool, create_worker, CreateWorkerArgs, TerminationToken},
     worker_pool::{SupervisorPolicy, WorkerPoolPolicy},
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
+use deno_core::serde_json::{self, Value};
+use event_worker::{
+    channel::{event_channel, EventChannelOpts, EventReceiver},
+    events::{LogEvent, ShutdownEvent, WorkerEvents},
+};
 use futures_util::{future::BoxFuture, Future, FutureExt};
 use http::{Request, Response};
 use hyper::Body;
 use pin_project::pin_project;
+use ring::digest;
 use sb_core::conn_sync::ConnSync;
 use sb_workers::context::{
-    MainWorkerRuntimeOpts, Timing, UserWorkerRuntimeOpts, WorkerContextInitOpts, WorkerRequestMsg,
-    WorkerRuntimeOpts,
+    MainWorkerRuntimeOpts, RequestStart, Timing, UserWorkerMsgs, UserWorkerRuntimeOpts,
+    WorkerContextInitOpts, WorkerRequestMsg, WorkerRuntimeOpts,
 };
 use scopeguard::ScopeGuard;
-use tokio::sync::{mpsc, oneshot, watch, Notify};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::{mpsc, oneshot, watch, Notify},
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
 pub struct CreateTestUserWorkerArgs(WorkerContextInitOpts, Option<SupervisorPolicy>);
 
@@ -52,8 +65,8 @@ pub fn create_conn_watch() -> (
 #[derive(Debug)]
 pub struct RequestScope {
     policy: SupervisorPolicy,
-    req_start_tx: mpsc::UnboundedSender<Arc<Notify>>,
-    req_end_tx: mpsc::UnboundedSender<()>,
+    req_start_tx: mpsc::UnboundedSender<RequestStart>,
+    req_end_tx: mpsc::UnboundedSender<Uuid>,
     termination_token: TerminationToken,
     conn: (Option<watch::Sender<ConnSync>>, watch::Receiver<ConnSync>),
 }
@@ -64,15 +77,25 @@ impl RequestScope {
     }
 
     pub async fn start_request(mut self) -> RequestScopeGuard {
+        let request_id = Uuid::new_v4();
+
         if self.policy.is_per_request() {
             let fence = Arc::<Notify>::default();
 
-            self.req_start_tx.send(fence.clone()).unwrap();
+            self.req_start_tx
+                .send(RequestStart {
+                    id: request_id,
+                    fence: fence.clone(),
+                    timeout: CancellationToken::new(),
+                })
+                .unwrap();
+
             fence.notified().await;
         }
 
         RequestScopeGuard {
             cancelled: false,
+            request_id,
             req_end_tx: self.req_end_tx.clone(),
             termination_token: Some(self.termination_token.clone()),
             conn_tx: self.conn.0.take().unwrap(),
@@ -85,7 +108,8 @@ impl RequestScope {
 #[pin_project]
 pub struct RequestScopeGuard {
     cancelled: bool,
-    req_end_tx: mpsc::UnboundedSender<()>,
+    request_id: Uuid,
+    req_end_tx: mpsc::UnboundedSender<Uuid>,
     termination_token: Option<TerminationToken>,
     conn_tx: watch::Sender<ConnSync>,
     inner: Option<BoxFuture<'static, ()>>,
@@ -103,7 +127,7 @@ impl Future for RequestScopeGuard {
 
         if !(*this.cancelled) {
             *this.cancelled = true;
-            this.req_end_tx.send(()).unwrap();
+            this.req_end_tx.send(*this.request_id).unwrap();
             this.termination_token.as_ref().unwrap().inbound.cancel();
         }
 
@@ -221,6 +245,7 @@ impl TestBedBuilder {
                 worker_pool_tx,
                 shared_metric_src: None,
                 event_worker_metric_src: None,
+                fs_access_rules: Default::default(),
             }),
         };
 
@@ -315,6 +340,167 @@ pub async fn create_test_user_worker<Opt: Into<CreateTestUserWorkerArgs>>(
     })
 }
 
+/// Options of a user worker running the service, with no env vars.
+pub fn user_worker_opts(service_path: &str, conf: UserWorkerRuntimeOpts) -> WorkerContextInitOpts {
+    WorkerContextInitOpts {
+        service_path: service_path.into(),
+        no_module_cache: false,
+        import_map_path: None,
+        env_vars: HashMap::new(),
+        events_rx: None,
+        timing: None,
+        maybe_eszip: None,
+        maybe_entrypoint: None,
+        maybe_module_code: None,
+        conf: WorkerRuntimeOpts::UserWorker(conf),
+    }
+}
+
+/// Asks the pool for a user worker of the given version of the service and
+/// returns its key.
+pub async fn create_pool_user_worker(
+    tx: &mpsc::UnboundedSender<UserWorkerMsgs>,
+    service_path: &str,
+    version: &str,
+) -> Uuid {
+    let (result_tx, result_rx) = oneshot::channel();
+    let opts = user_worker_opts(
+        service_path,
+        UserWorkerRuntimeOpts {
+            version: Some(version.to_string()),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    tx.send(UserWorkerMsgs::Create(opts, result_tx)).unwrap();
+    result_rx.await.unwrap().unwrap().key
+}
+
+/// Receives the events of a worker until it shuts down, returning the lines it
+/// logged along with its shutdown event.
+pub async fn collect_events_until_shutdown(
+    events_rx: &mut EventReceiver,
+) -> (Vec<LogEvent>, ShutdownEvent) {
+    let mut logs = vec![];
+
+    loop {
+        match events_rx.recv().await.unwrap().event {
+            WorkerEvents::Log(event) => logs.push(event),
+            WorkerEvents::Shutdown(event) => return (logs, event),
+            _ => {}
+        }
+    }
+}
+
+/// Runs the service in a user worker and collects the first `count` lines it
+/// logs.
+pub async fn run_worker_and_collect_logs(
+    service_path: &str,
+    env_vars: HashMap<String, String>,
+    conf: UserWorkerRuntimeOpts,
+    count: usize,
+) -> Vec<LogEvent> {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = WorkerContextInitOpts {
+        env_vars,
+        ..user_worker_opts(
+            service_path,
+            UserWorkerRuntimeOpts {
+                events_msg_tx: Some(events_tx),
+                ..conf
+            },
+        )
+    };
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let mut logs = vec![];
+
+    while logs.len() < count {
+        if let WorkerEvents::Log(event) = events_rx.recv().await.unwrap().event {
+            logs.push(event);
+        }
+    }
+
+    logs
+}
+
+/// Runs the service in a user worker and parses the first line it logs, which
+/// is expected to be its results serialized as JSON.
+pub async fn run_worker_and_collect_json_log(
+    service_path: &str,
+    env_vars: HashMap<String, String>,
+    conf: UserWorkerRuntimeOpts,
+) -> Value {
+    let logs = run_worker_and_collect_logs(service_path, env_vars, conf, 1).await;
+
+    serde_json::from_str(&logs[0].msg).unwrap()
+}
+
+/// Accepts a WebSocket connection and echoes the first message back, unless
+/// the client didn't mask it as it must.
+pub async fn serve_websocket_echo(listener: TcpListener) {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut stream = BufReader::new(stream);
+    let mut key = String::new();
+    let mut line = String::new();
+
+    while stream.read_line(&mut line).await.unwrap() > 2 {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("sec-websocket-key") {
+                key = value.trim().to_string();
+            }
+        }
+
+        line.clear();
+    }
+
+    let accept = {
+        let mut ctx = digest::Context::new(&digest::SHA1_FOR_LEGACY_USE_ONLY);
+
+        ctx.update(key.as_bytes());
+        ctx.update(b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11");
+        STANDARD.encode(ctx.finish())
+    };
+
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\nconnection: Upgrade\r\nsec-websocket-accept: {}\r\n\r\n",
+        accept
+    );
+
+    stream.write_all(response.as_bytes()).await.unwrap();
+
+    // a short text frame
+    let mut head = [0u8; 2];
+    let mut mask = [0u8; 4];
+
+    stream.read_exact(&mut head).await.unwrap();
+
+    let masked = head[1] & 0x80 != 0;
+    let mut payload = vec![0u8; (head[1] & 0x7f) as usize];
+
+    if masked {
+        stream.read_exact(&mut mask).await.unwrap();
+    }
+
+    stream.read_exact(&mut payload).await.unwrap();
+
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    let reply = if masked {
+        payload
+    } else {
+        b"unmasked".to_vec()
+    };
+
+    stream.write_all(&[0x81, reply.len() as u8]).await.unwrap();
+    stream.write_all(&reply).await.unwrap();
+}
+
 pub fn test_user_worker_pool_policy() -> WorkerPoolPolicy {
     WorkerPoolPolicy::new(SupervisorPolicy::oneshot(), 1, 4 * 1000 * 3600)
 }
@@ -0,0 +1,26 @@
+// Runs `fn` and reports whether it was allowed or denied by the permissions
+// of the worker.
+export async function check(name: string, fn: () => unknown) {
+	try {
+		await fn();
+		return [name, 'allowed'];
+	} catch (e) {
+		// hosts rejected after they were resolved fail as network errors
+		const denied = e instanceof Deno.errors.PermissionDenied ||
+			String(e).includes('is not allowed');
+
+		return [name, denied ? 'denied' : 'allowed'];
+	}
+}
+
+export function openWebSocket(url: string) {
+	return new Promise((resolve, reject) => {
+		const ws = new WebSocket(url);
+
+		ws.onopen = () => {
+			ws.close();
+			resolve(null);
+		};
+		ws.onerror = (e) => reject(new Error((e as ErrorEvent).message));
+	});
+}
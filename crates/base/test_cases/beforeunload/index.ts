@@ -0,0 +1,9 @@
+addEventListener('beforeunload', (ev) => {
+	ev.detail.waitUntil(
+		new Promise((resolve) => setTimeout(resolve, 10)).then(() => {
+			console.log(`beforeunload: ${ev.detail.reason}`);
+		}),
+	);
+});
+
+Deno.serve(() => new Response('meow'));
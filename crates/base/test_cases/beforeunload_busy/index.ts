@@ -0,0 +1,8 @@
+addEventListener('beforeunload', (ev) => {
+	console.log(`beforeunload: ${ev.detail.reason}`);
+
+	// never yields back to the event loop
+	while (true) {}
+});
+
+Deno.serve(() => new Response('meow'));
@@ -0,0 +1,11 @@
+addEventListener('beforeunload', (ev) => {
+	console.log(`beforeunload: ${ev.detail.reason}`);
+});
+
+globalThis.buffers = [];
+
+for (let i = 0; i < 15; i++) {
+	globalThis.buffers.push(new ArrayBuffer(1024 * 1024));
+}
+
+Deno.serve(() => new Response('meow'));
@@ -0,0 +1 @@
+throw new Error(`leaked ${Deno.env.get('SUPABASE_SERVICE_ROLE_KEY')}`);
@@ -0,0 +1,7 @@
+console.debug("debug message");
+console.log("log message");
+console.info("info message");
+console.warn("warn message");
+console.error("error message");
+
+Deno.serve(() => new Response("meow"));
@@ -0,0 +1,17 @@
+import { check, openWebSocket } from '../_shared/check.ts';
+
+const results = await Promise.all([
+	check('fetch 127.0.0.1', () => fetch('http://127.0.0.1:1/')),
+	check('fetch 169.254.169.254', () => fetch('http://169.254.169.254/latest/meta-data/')),
+	check('fetch [::ffff:10.0.0.1]', () => fetch('http://[::ffff:10.0.0.1]/')),
+	check('fetch localhost', () => fetch('http://localhost:1/')),
+	check('websocket 192.168.0.1', async () => new WebSocket('ws://192.168.0.1/')),
+	check('websocket localhost', () => openWebSocket('ws://localhost:1/')),
+	check('websocket wss localhost', () => openWebSocket('wss://localhost:1/')),
+	check('connect localhost', () => Deno.connect({ hostname: 'localhost', port: 1 })),
+	check('connectTls localhost', () => Deno.connectTls({ hostname: 'localhost', port: 1 })),
+]);
+
+console.log(JSON.stringify(Object.fromEntries(results)));
+
+Deno.serve(() => new Response('meow'));
@@ -0,0 +1,20 @@
+function getEnv(key: string) {
+	try {
+		return Deno.env.get(key) ?? null;
+	} catch (e) {
+		return e instanceof Deno.errors.PermissionDenied ? 'denied' : String(e);
+	}
+}
+
+const secret = Deno.env.get('SUPABASE_SERVICE_ROLE_KEY');
+
+console.log(JSON.stringify({
+	allowed: getEnv('SUPABASE_URL'),
+	notAllowed: getEnv('DATABASE_PASSWORD'),
+	secretReadable: secret === 'service-role-secret',
+	listed: Object.keys(Deno.env.toObject()).sort(),
+}));
+
+console.log(`using key ${secret}`, { key: secret });
+
+Deno.serve(() => new Response('meow'));
@@ -0,0 +1,7 @@
+const eventManager = new globalThis.EventManager();
+
+for await (const data of eventManager) {
+	if (data.event_type === 'Log' && data.event.msg === 'crash') {
+		throw new Error('crash requested');
+	}
+}
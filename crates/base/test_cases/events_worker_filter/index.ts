@@ -0,0 +1,8 @@
+const eventManager = new globalThis.EventManager({
+	eventTypes: ['Log'],
+	servicePaths: ['crash/*'],
+});
+
+for await (const data of eventManager) {
+	throw new Error(`unexpected ${data.event_type} from ${data.metadata.service_path}`);
+}
@@ -0,0 +1,17 @@
+const logs = new globalThis.EventManager({ eventTypes: ['Log'] });
+const crashes = new globalThis.EventManager({ servicePaths: ['crash/*'] });
+const seen = new Set();
+
+async function consume(eventManager, name) {
+	for await (const data of eventManager) {
+		if (data.event_type === 'Log' && data.event.msg === 'crash') {
+			seen.add(name);
+		}
+
+		if (seen.size === 2) {
+			throw new Error('crash received by both subscriptions');
+		}
+	}
+}
+
+await Promise.all([consume(logs, 'logs'), consume(crashes, 'crashes')]);
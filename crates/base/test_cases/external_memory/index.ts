@@ -0,0 +1,9 @@
+// NOTE: Each buffer stays below the limit on its own, but the heap and the
+// external memory combined exceed it.
+globalThis.buffers = [];
+
+for (let i = 0; i < 15; i++) {
+	globalThis.buffers.push(new ArrayBuffer(1024 * 1024));
+}
+
+Deno.serve(() => new Response(`${globalThis.buffers.length}`));
@@ -0,0 +1,17 @@
+import { check } from '../_shared/check.ts';
+
+const writeDir = Deno.env.get('WRITE_DIR');
+
+const results = await Promise.all([
+	check('read service dir', () => Deno.readTextFileSync('./test_cases/fs_access_rules/index.ts')),
+	check('read outside', () => Deno.readTextFileSync('./test_cases/fs_access_rules/../main/index.ts')),
+	check('read through symlink', () => Deno.readTextFileSync(`${writeDir}/etc/hostname`)),
+	check('write service dir', () => Deno.writeTextFileSync('./test_cases/fs_access_rules/out.txt', 'meow')),
+	check('write allowed dir', () => Deno.writeTextFileSync(`${writeDir}/out.txt`, 'meow')),
+	check('read dir outside', () => Deno.readDirSync('/').next()),
+	check('cwd', () => Deno.cwd()),
+]);
+
+console.log(JSON.stringify({ ...Object.fromEntries(results), chdir: typeof Deno.chdir }));
+
+Deno.serve(() => new Response('meow'));
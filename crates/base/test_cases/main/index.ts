@@ -37,7 +37,8 @@ serve(async (req: Request) => {
         cpuTimeHardLimitMs,
         noModuleCache,
         importMapPath,
-        envVars
+        envVars,
+        traceparent: req.headers.get('traceparent'),
     });
   }
 
@@ -0,0 +1,15 @@
+import { check } from '../_shared/check.ts';
+
+const results = await Promise.all([
+	check('fetch 127.0.0.1:1', () => fetch('http://127.0.0.1:1/')),
+	check('fetch 127.0.0.1:2', () => fetch('http://127.0.0.1:2/')),
+	check('fetch localhost:1', () => fetch('http://localhost:1/')),
+	check('fetch api.example.com:444', () => fetch('https://api.example.com:444/')),
+	check('websocket 127.0.0.1:2', async () => new WebSocket('ws://127.0.0.1:2/')),
+	check('connect 127.0.0.1:1', () => Deno.connect({ hostname: '127.0.0.1', port: 1 })),
+	check('connect 10.0.0.1:80', () => Deno.connect({ hostname: '10.0.0.1', port: 80 })),
+]);
+
+console.log(JSON.stringify(Object.fromEntries(results)));
+
+Deno.serve(() => new Response('meow'));
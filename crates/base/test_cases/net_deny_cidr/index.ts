@@ -0,0 +1,13 @@
+import { check, openWebSocket } from '../_shared/check.ts';
+
+const results = await Promise.all([
+	check('fetch localhost', () => fetch('http://localhost:1/')),
+	check('fetch [::ffff:127.0.0.1]', () => fetch('http://[::ffff:127.0.0.1]:1/')),
+	check('websocket localhost', () => openWebSocket('ws://localhost:1/')),
+	check('connect localhost', () => Deno.connect({ hostname: 'localhost', port: 1 })),
+	check('connectTls localhost', () => Deno.connectTls({ hostname: 'localhost', port: 1 })),
+]);
+
+console.log(JSON.stringify(Object.fromEntries(results)));
+
+Deno.serve(() => new Response('meow'));
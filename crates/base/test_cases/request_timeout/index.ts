@@ -0,0 +1,10 @@
+Deno.serve(async (req) => {
+	if (new URL(req.url).pathname.endsWith("/fast")) {
+		return new Response("fast");
+	}
+
+	await new Promise((resolve) => req.signal.addEventListener("abort", resolve));
+	console.log("request aborted");
+
+	return new Response("meow");
+});
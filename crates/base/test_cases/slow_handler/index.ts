@@ -0,0 +1,4 @@
+Deno.serve(async () => {
+	await new Promise(r => setTimeout(r, 2000));
+	return new Response("meow");
+});
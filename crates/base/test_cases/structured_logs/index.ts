@@ -0,0 +1,7 @@
+const payload: Record<string, unknown> = { user: "meow", count: 3n };
+payload.self = payload;
+
+console.log("request handled", payload, { big: "x".repeat(32 * 1024) });
+console.log("plain message");
+
+Deno.serve(() => new Response("meow"));
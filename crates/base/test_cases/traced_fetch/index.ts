@@ -0,0 +1,6 @@
+Deno.serve(async (req: Request) => {
+	const { searchParams } = new URL(req.url);
+	const res = await fetch(searchParams.get('target')!);
+
+	return new Response(await res.text());
+});
@@ -0,0 +1,24 @@
+let arrived = 0;
+let release: () => void;
+const bothArrived = new Promise<void>((resolve) => (release = resolve));
+
+Deno.serve(async (req: Request) => {
+	if (++arrived === 2) {
+		release();
+	}
+
+	// both requests are in flight from here on
+	await bothArrived;
+
+	const target = new URL(req.url).searchParams.get('target')!;
+	const [explicit, implicit] = await Promise.all([
+		// @ts-ignore: `traceContext` is specific to the edge runtime
+		fetch(target, { traceContext: req }),
+		fetch(target),
+	]);
+
+	return Response.json({
+		explicit: await explicit.text(),
+		implicit: await implicit.text(),
+	});
+});
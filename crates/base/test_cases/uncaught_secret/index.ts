@@ -0,0 +1,5 @@
+setTimeout(() => {
+	throw new Error(`leaked ${Deno.env.get('SUPABASE_SERVICE_ROLE_KEY')}`);
+});
+
+Deno.serve(() => new Response('meow'));
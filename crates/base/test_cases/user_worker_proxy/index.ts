@@ -0,0 +1,16 @@
+const port = Deno.env.get('PROXY_PORT');
+
+async function text(url: string) {
+	try {
+		return await (await fetch(url)).text();
+	} catch (e) {
+		return String(e);
+	}
+}
+
+console.log(JSON.stringify({
+	proxied: await text('http://proxied.invalid/hello'),
+	direct: await text(`http://127.0.0.1:${port}/hello`),
+}));
+
+Deno.serve(() => new Response('meow'));
@@ -0,0 +1,11 @@
+const ws = new WebSocket(Deno.env.get('WS_URL')!);
+const echo = await new Promise((resolve, reject) => {
+	ws.onopen = () => ws.send('meow');
+	ws.onmessage = (e) => resolve(e.data);
+	ws.onerror = (e) => reject(new Error((e as ErrorEvent).message));
+});
+
+ws.close();
+console.log(JSON.stringify({ echo }));
+
+Deno.serve(() => new Response('meow'));
@@ -0,0 +1,106 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::time::Duration;
+
+use base::rt_worker::worker_pool::SupervisorPolicy;
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::ShutdownReason;
+use sb_workers::context::UserWorkerRuntimeOpts;
+use serial_test::serial;
+
+use crate::integration_test_helper::{
+    collect_events_until_shutdown, create_test_user_worker, test_user_runtime_opts,
+    user_worker_opts,
+};
+
+#[tokio::test]
+#[serial]
+async fn test_beforeunload_is_dispatched_before_termination() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/beforeunload",
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            shutdown_grace_period_ms: 1000,
+            shutdown_grace_cpu_time_ms: 100,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let (logs, shutdown) = collect_events_until_shutdown(&mut events_rx).await;
+
+    assert_eq!(shutdown.reason, ShutdownReason::WallClockTime);
+
+    assert!(logs
+        .iter()
+        .any(|it| it.msg.contains("beforeunload: WallClockTime")));
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+#[serial]
+async fn test_beforeunload_cpu_budget_cuts_off_busy_handlers() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/beforeunload_busy",
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            shutdown_grace_period_ms: 60 * 1000,
+            shutdown_grace_cpu_time_ms: 100,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    // the handler never yields, so only the CPU timer can cut it off before
+    // the grace period elapses
+    let (logs, shutdown) = tokio::time::timeout(
+        Duration::from_secs(10),
+        collect_events_until_shutdown(&mut events_rx),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(shutdown.reason, ShutdownReason::WallClockTime);
+
+    assert!(logs
+        .iter()
+        .any(|it| it.msg.contains("beforeunload: WallClockTime")));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_beforeunload_is_not_dispatched_on_memory_limit() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/beforeunload_memory",
+        UserWorkerRuntimeOpts {
+            memory_limit_mb: 16,
+            memory_sample_interval_ms: 50,
+            shutdown_grace_period_ms: 1000,
+            shutdown_grace_cpu_time_ms: 100,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let (logs, shutdown) = collect_events_until_shutdown(&mut events_rx).await;
+
+    assert_eq!(shutdown.reason, ShutdownReason::Memory);
+
+    assert!(!logs.iter().any(|it| it.msg.contains("beforeunload")));
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use base::rt_worker::boot_circuit_breaker::{
+    BootCircuitBreakerPolicy, BootCircuitBreakers, BreakerTransition,
+};
+use sb_workers::errors::{WorkerBootError, WorkerError};
+
+fn boot_error() -> WorkerBootError {
+    WorkerBootError {
+        class: "Error".to_string(),
+        msg: "boom".to_string(),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_boot_circuit_breaker_trips_and_resets() {
+    let service_path = "./test_cases/boot_failure";
+    let mut breakers = BootCircuitBreakers::new(BootCircuitBreakerPolicy {
+        failure_threshold: 2,
+        window_ms: 10 * 1000,
+        cooldown_ms: 100,
+    });
+
+    assert!(breakers.try_acquire(service_path).is_ok());
+    assert!(breakers
+        .record_failure(service_path, boot_error())
+        .is_none());
+    assert!(matches!(
+        breakers.record_failure(service_path, boot_error()),
+        Some(BreakerTransition::Tripped { failures: 2, .. })
+    ));
+
+    match breakers.try_acquire(service_path) {
+        Err(WorkerError::ServiceQuarantined(err)) => assert_eq!(err.to_string(), "Error: boom"),
+        _ => panic!("breaker must be open"),
+    }
+
+    // other services must not be affected
+    assert!(breakers.try_acquire("./test_cases/oak").is_ok());
+
+    tokio::time::advance(Duration::from_millis(100)).await;
+
+    // only a single probe is let through in the half-open state
+    assert!(matches!(breakers.try_acquire(service_path), Ok(true)));
+    assert!(breakers.try_acquire(service_path).is_err());
+
+    assert!(matches!(
+        breakers.record_success(service_path),
+        Some(BreakerTransition::Reset)
+    ));
+    assert!(matches!(breakers.try_acquire(service_path), Ok(false)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_boot_circuit_breaker_failed_probe_trips_again() {
+    let service_path = "./test_cases/boot_failure";
+    let mut breakers = BootCircuitBreakers::new(BootCircuitBreakerPolicy {
+        failure_threshold: 1,
+        window_ms: 10 * 1000,
+        cooldown_ms: 100,
+    });
+
+    assert!(breakers
+        .record_failure(service_path, boot_error())
+        .is_some());
+
+    tokio::time::advance(Duration::from_millis(100)).await;
+
+    assert!(breakers.try_acquire(service_path).is_ok());
+    assert!(matches!(
+        breakers.record_failure(service_path, boot_error()),
+        Some(BreakerTransition::Tripped { failures: 1, .. })
+    ));
+    assert!(breakers.try_acquire(service_path).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_boot_circuit_breaker_released_probe() {
+    let service_path = "./test_cases/boot_failure";
+    let mut breakers = BootCircuitBreakers::new(BootCircuitBreakerPolicy {
+        failure_threshold: 1,
+        window_ms: 10 * 1000,
+        cooldown_ms: 100,
+    });
+
+    assert!(breakers
+        .record_failure(service_path, boot_error())
+        .is_some());
+
+    tokio::time::advance(Duration::from_millis(100)).await;
+
+    assert!(matches!(breakers.try_acquire(service_path), Ok(true)));
+    assert!(breakers.try_acquire(service_path).is_err());
+
+    // a probe that didn't boot a worker lets the next caller probe
+    breakers.release_probe(service_path);
+
+    assert!(matches!(breakers.try_acquire(service_path), Ok(true)));
+}
@@ -0,0 +1,119 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base::rt_worker::worker_pool::SupervisorPolicy;
+use deno_core::serde_json::{self, Value};
+use http::{Request, StatusCode};
+use hyper::Body;
+use sb_workers::context::{UserWorkerRuntimeOpts, WorkerContextInitOpts, WorkerRequestMsg};
+use serial_test::serial;
+use tokio::sync::oneshot;
+
+use crate::integration_test_helper::{
+    create_conn_watch, create_test_user_worker, test_user_runtime_opts, user_worker_opts,
+};
+
+fn create_opts(dir: &Path, cpu_profile_duration_ms: u64) -> WorkerContextInitOpts {
+    user_worker_opts(
+        "./test_cases/std_user_worker",
+        UserWorkerRuntimeOpts {
+            cpu_profile_dir: Some(dir.to_path_buf()),
+            cpu_profile_duration_ms: Some(cpu_profile_duration_ms),
+            ..test_user_runtime_opts()
+        },
+    )
+}
+
+fn find_cpu_profiles(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|it| {
+            it.filter_map(|entry| Some(entry.ok()?.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "cpuprofile"))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn assert_cpu_profile(path: &Path) {
+    let profile: Value = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+
+    assert!(profile["nodes"].as_array().is_some_and(|it| !it.is_empty()));
+    assert!(profile["startTime"].is_number());
+    assert!(profile["endTime"].is_number());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_cpu_profile_is_written_on_shutdown() {
+    let dir = std::env::temp_dir().join(format!("cpu-profile-{}", uuid::Uuid::new_v4()));
+    let (worker_req_tx, scope) =
+        create_test_user_worker((create_opts(&dir, 0), SupervisorPolicy::PerWorker))
+            .await
+            .unwrap();
+
+    let (res_tx, res_rx) = oneshot::channel();
+    let (_conn_tx, conn_rx) = create_conn_watch();
+    let req = Request::builder()
+        .uri("/std_user_worker")
+        .method("OPTIONS")
+        .body(Body::empty())
+        .unwrap();
+
+    worker_req_tx
+        .send(WorkerRequestMsg {
+            req,
+            res_tx,
+            conn_watch: Some(conn_rx),
+        })
+        .unwrap();
+
+    // nothing is written while the worker is serving requests
+    assert_eq!(res_rx.await.unwrap().unwrap().status(), StatusCode::OK);
+    assert!(find_cpu_profiles(&dir).is_empty());
+
+    scope.start_request().await.await;
+
+    let profiles = find_cpu_profiles(&dir);
+
+    assert_eq!(profiles.len(), 1);
+    assert_cpu_profile(&profiles[0]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_cpu_profile_is_written_after_duration() {
+    let dir = std::env::temp_dir().join(format!("cpu-profile-{}", uuid::Uuid::new_v4()));
+    let (_, scope) = create_test_user_worker((create_opts(&dir, 100), SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let profiles = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let profiles = find_cpu_profiles(&dir);
+
+            if !profiles.is_empty() {
+                break profiles;
+            }
+
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(profiles.len(), 1);
+    assert_cpu_profile(&profiles[0]);
+
+    scope.start_request().await.await;
+
+    // NOTE: The profile has already been stopped, so nothing is written on
+    // shutdown.
+    assert_eq!(find_cpu_profiles(&dir).len(), 1);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
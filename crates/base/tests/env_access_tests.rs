@@ -0,0 +1,154 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::collections::HashMap;
+
+use base::rt_worker::worker_pool::SupervisorPolicy;
+use deno_core::serde_json::{self, json, Value};
+use event_worker::channel::{event_channel, EventChannelOpts, EventSender};
+use event_worker::events::WorkerEvents;
+use event_worker::js_interceptors::LogRedactions;
+use sb_core::permissions::EnvAccessRules;
+use sb_workers::context::{UserWorkerRuntimeOpts, WorkerContextInitOpts};
+use serial_test::serial;
+
+use crate::integration_test_helper::{
+    create_test_user_worker, run_worker_and_collect_logs, test_user_runtime_opts, user_worker_opts,
+};
+
+#[test]
+fn test_env_access_rules_matching() {
+    let rules = EnvAccessRules::parse(
+        Some(&["SUPABASE_*".to_string(), "PORT".to_string()]),
+        &["*_KEY".to_string()],
+    )
+    .unwrap();
+
+    assert!(rules.is_allowed("SUPABASE_URL"));
+    assert!(rules.is_allowed("PORT"));
+    assert!(!rules.is_allowed("PORTS"));
+    assert!(!rules.is_allowed("DATABASE_PASSWORD"));
+    assert!(rules.is_secret("SUPABASE_SERVICE_ROLE_KEY"));
+    assert!(!rules.is_secret("SUPABASE_URL"));
+
+    assert!(EnvAccessRules::default().is_allowed("ANYTHING"));
+    assert!(EnvAccessRules::parse(None, &["[".to_string()]).is_err());
+}
+
+#[test]
+fn test_log_redactions() {
+    let redactions =
+        LogRedactions::new(["secret".to_string(), "secret-key".to_string(), "".into()]);
+
+    assert_eq!(
+        redactions.redact("a secret-key and a secret"),
+        "a [REDACTED] and a [REDACTED]"
+    );
+    assert_eq!(redactions.redact("nothing"), "nothing");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_env_access_rules_apply_to_user_workers() {
+    let logs = run_worker_and_collect_logs(
+        "./test_cases/env_access_rules",
+        HashMap::from([
+            ("SUPABASE_URL".to_string(), "http://localhost".to_string()),
+            (
+                "SUPABASE_SERVICE_ROLE_KEY".to_string(),
+                "service-role-secret".to_string(),
+            ),
+            ("DATABASE_PASSWORD".to_string(), "postgres".to_string()),
+        ]),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            structured_logs: true,
+            env_access_rules: EnvAccessRules::parse(
+                Some(&["SUPABASE_*".to_string()]),
+                &["SUPABASE_SERVICE_ROLE_KEY".to_string()],
+            )
+            .unwrap(),
+            ..test_user_runtime_opts()
+        },
+        2,
+    )
+    .await;
+
+    assert_eq!(
+        serde_json::from_str::<Value>(&logs[0].msg).unwrap(),
+        json!({
+            "allowed": "http://localhost",
+            "notAllowed": "denied",
+            "secretReadable": true,
+            "listed": ["SUPABASE_URL"],
+        })
+    );
+
+    assert!(logs[1].msg.starts_with("using key [REDACTED]"));
+    assert!(!logs[1].msg.contains("service-role-secret"));
+    assert_eq!(logs[1].fields.as_ref().unwrap()["key"], "[REDACTED]");
+}
+
+fn secret_env_opts(service_path: &str, events_tx: EventSender) -> WorkerContextInitOpts {
+    WorkerContextInitOpts {
+        env_vars: HashMap::from([(
+            "SUPABASE_SERVICE_ROLE_KEY".to_string(),
+            "service-role-secret".to_string(),
+        )]),
+        ..user_worker_opts(
+            service_path,
+            UserWorkerRuntimeOpts {
+                env_access_rules: EnvAccessRules::parse(
+                    None,
+                    &["SUPABASE_SERVICE_ROLE_KEY".to_string()],
+                )
+                .unwrap(),
+                events_msg_tx: Some(events_tx),
+                ..test_user_runtime_opts()
+            },
+        )
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_secrets_are_redacted_from_uncaught_exceptions() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = secret_env_opts("./test_cases/uncaught_secret", events_tx);
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let exception = loop {
+        if let WorkerEvents::UncaughtException(event) = events_rx.recv().await.unwrap().event {
+            break event.exception;
+        }
+    };
+
+    assert!(exception.contains("leaked [REDACTED]"));
+    assert!(!exception.contains("service-role-secret"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_secrets_are_redacted_from_boot_failures() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = secret_env_opts("./test_cases/boot_failure_secret", events_tx);
+    let Err(err) = create_test_user_worker((opts, SupervisorPolicy::PerWorker)).await else {
+        panic!("the worker should fail to boot");
+    };
+
+    let err = format!("{:#}", err);
+
+    assert!(err.contains("leaked [REDACTED]"));
+    assert!(!err.contains("service-role-secret"));
+
+    let msg = loop {
+        if let WorkerEvents::BootFailure(event) = events_rx.recv().await.unwrap().event {
+            break event.msg;
+        }
+    };
+
+    assert!(msg.contains("leaked [REDACTED]"));
+    assert!(!msg.contains("service-role-secret"));
+}
@@ -0,0 +1,169 @@
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use event_worker::channel::{event_channel, EventChannelOpts, EventDropPolicy};
+use event_worker::events::{
+    EventMetadata, EventsDroppedEvent, LogEvent, LogLevel, PseudoEvent, WorkerEventWithMetadata,
+    WorkerEvents,
+};
+
+fn metadata(service_path: &str) -> EventMetadata {
+    EventMetadata {
+        service_path: Some(service_path.to_string()),
+        ..Default::default()
+    }
+}
+
+fn log_event(service_path: &str, msg: &str) -> WorkerEventWithMetadata {
+    WorkerEventWithMetadata {
+        event: WorkerEvents::Log(LogEvent {
+            msg: msg.to_string(),
+            level: LogLevel::Info,
+            location: None,
+            fields: None,
+        }),
+        metadata: metadata(service_path),
+    }
+}
+
+fn log_msg(event: &WorkerEventWithMetadata) -> &str {
+    match &event.event {
+        WorkerEvents::Log(LogEvent { msg, .. }) => msg,
+        _ => panic!("expected a log event, got {:?}", event.event),
+    }
+}
+
+fn opts(capacity: usize, policy: EventDropPolicy) -> EventChannelOpts {
+    EventChannelOpts {
+        capacity,
+        policy,
+        drop_report_interval_ms: 0,
+        block_timeout_ms: 1000,
+    }
+}
+
+#[tokio::test]
+async fn test_event_channel_drop_oldest() {
+    let (tx, mut rx) = event_channel(opts(2, EventDropPolicy::DropOldest));
+
+    for msg in ["a", "b", "c"] {
+        tx.send(log_event("svc", msg)).unwrap();
+    }
+
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "b");
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "c");
+    assert_eq!(tx.counters().dropped_events.load(Ordering::Relaxed), 1);
+
+    drop(tx);
+
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_event_channel_drop_newest() {
+    let (tx, mut rx) = event_channel(opts(2, EventDropPolicy::DropNewest));
+
+    for msg in ["a", "b", "c"] {
+        tx.send(log_event("svc", msg)).unwrap();
+    }
+
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "a");
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "b");
+    assert_eq!(tx.counters().dropped_events.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn test_event_channel_reports_dropped_events_per_service() {
+    let (tx, mut rx) = event_channel(EventChannelOpts {
+        drop_report_interval_ms: 100,
+        ..opts(1, EventDropPolicy::DropNewest)
+    });
+
+    tx.send(log_event("svc-a", "kept")).unwrap();
+    tx.send(log_event("svc-a", "dropped")).unwrap();
+    tx.send(log_event("svc-a", "dropped")).unwrap();
+    tx.send(log_event("svc-b", "dropped")).unwrap();
+
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "kept");
+
+    let mut reports = vec![];
+
+    for _ in 0..2 {
+        let event = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        match event.event {
+            WorkerEvents::EventsDropped(EventsDroppedEvent { count }) => {
+                reports.push((event.metadata.service_path.unwrap(), count));
+            }
+
+            event => panic!("expected a report, got {:?}", event),
+        }
+    }
+
+    reports.sort();
+
+    assert_eq!(
+        reports,
+        vec![("svc-a".to_string(), 2), ("svc-b".to_string(), 1)]
+    );
+
+    let dropped_events_by_service = tx.counters().dropped_events_by_service;
+    let dropped_events_by_service = dropped_events_by_service.lock().unwrap();
+
+    assert_eq!(dropped_events_by_service.get("svc-a"), Some(&2));
+    assert_eq!(dropped_events_by_service.get("svc-b"), Some(&1));
+}
+
+#[tokio::test]
+async fn test_event_channel_block_logs() {
+    let (tx, mut rx) = event_channel(opts(1, EventDropPolicy::BlockLogs));
+
+    tx.send(log_event("svc", "a")).unwrap();
+
+    // non-log events are never blocked on
+    tx.send(WorkerEventWithMetadata {
+        event: WorkerEvents::EventLoopCompleted(PseudoEvent {}),
+        metadata: metadata("svc"),
+    })
+    .unwrap();
+
+    let handle = std::thread::spawn({
+        let tx = tx.clone();
+        move || tx.send(log_event("svc", "b")).unwrap()
+    });
+
+    // wait until the sender is blocked on the full channel
+    while tx.counters().blocked_logs.load(Ordering::Relaxed) == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    assert!(!handle.is_finished());
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "a");
+
+    handle.join().unwrap();
+
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "b");
+    assert_eq!(tx.counters().dropped_events.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn test_event_channel_block_logs_timeout() {
+    let (tx, mut rx) = event_channel(EventChannelOpts {
+        block_timeout_ms: 100,
+        ..opts(1, EventDropPolicy::BlockLogs)
+    });
+
+    tx.send(log_event("svc", "a")).unwrap();
+
+    // the consumer never catches up, so the log is dropped after the timeout
+    let started_at = Instant::now();
+
+    tx.send(log_event("svc", "b")).unwrap();
+
+    assert!(started_at.elapsed() >= Duration::from_millis(100));
+    assert_eq!(log_msg(&rx.recv().await.unwrap()), "a");
+    assert_eq!(tx.counters().dropped_events.load(Ordering::Relaxed), 1);
+}
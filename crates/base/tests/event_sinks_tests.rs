@@ -0,0 +1,174 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use base::event_sinks::{spawn_event_sinks, EventSinkConfig, FileSinkConfig, OtlpSinkConfig};
+use deno_core::serde_json::{self, Value};
+use deno_core::url::Url;
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::{
+    EventMetadata, LogEvent, LogLevel, WorkerEventWithMetadata, WorkerEvents,
+};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use tokio::sync::mpsc;
+
+fn log_event(msg: &str) -> WorkerEventWithMetadata {
+    WorkerEventWithMetadata {
+        event: WorkerEvents::Log(LogEvent {
+            msg: msg.to_string(),
+            level: LogLevel::Warning,
+            location: None,
+            fields: None,
+        }),
+        metadata: EventMetadata {
+            service_path: Some("hello_world".to_string()),
+            execution_id: Some(uuid::Uuid::nil()),
+            version: None,
+        },
+    }
+}
+
+fn read_lines(path: &Path) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|it| serde_json::from_str(it).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_file_sink_writes_json_lines_and_rotates() {
+    let dir = std::env::temp_dir().join(format!("event-sinks-{}", uuid::Uuid::new_v4()));
+    let path = dir.join("events.log");
+
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let tx = spawn_event_sinks(
+        vec![EventSinkConfig::File(FileSinkConfig {
+            path: path.clone(),
+            max_size_bytes: 512,
+            max_files: 2,
+        })],
+        None,
+        EventChannelOpts::default(),
+    )
+    .unwrap();
+
+    for idx in 0..20 {
+        tx.send(log_event(&format!("message {}", idx))).unwrap();
+    }
+
+    let rotated_path = dir.join("events.log.2");
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !rotated_path.exists()
+            || !read_lines(&path)
+                .iter()
+                .any(|it| it["event"]["Log"]["msg"] == "message 19")
+        {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    assert!(!dir.join("events.log.3").exists());
+
+    for file in [&path, &dir.join("events.log.1"), &rotated_path] {
+        assert!(std::fs::metadata(file).unwrap().len() <= 512);
+
+        for line in read_lines(file) {
+            assert_eq!(line["event_type"], "Log");
+            assert_eq!(line["metadata"]["service_path"], "hello_world");
+            assert!(line["timestamp"].is_u64());
+        }
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_otlp_sink_exports_log_records() {
+    let (body_tx, mut body_rx) = mpsc::unbounded_channel::<(String, Value)>();
+    let make_svc = make_service_fn(move |_| {
+        let body_tx = body_tx.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let body_tx = body_tx.clone();
+
+                async move {
+                    let path = req.uri().path().to_string();
+                    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+
+                    body_tx
+                        .send((path, serde_json::from_slice(&body).unwrap()))
+                        .unwrap();
+
+                    Ok::<_, Infallible>(Response::new(Body::from("{}")))
+                }
+            }))
+        }
+    });
+
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let addr = server.local_addr();
+
+    drop(tokio::spawn(server));
+
+    let tx = spawn_event_sinks(
+        vec![EventSinkConfig::Otlp(OtlpSinkConfig {
+            batch_size: 2,
+            ..OtlpSinkConfig::new(Url::parse(&format!("http://{}/v1/logs", addr)).unwrap())
+        })],
+        None,
+        EventChannelOpts::default(),
+    )
+    .unwrap();
+
+    tx.send(log_event("first")).unwrap();
+    tx.send(log_event("second")).unwrap();
+
+    let (path, body) = tokio::time::timeout(Duration::from_secs(5), body_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(path, "/v1/logs");
+
+    let records = body["resourceLogs"][0]["scopeLogs"][0]["logRecords"]
+        .as_array()
+        .unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["body"]["stringValue"], "first");
+    assert_eq!(records[1]["body"]["stringValue"], "second");
+    assert_eq!(records[0]["severityText"], "WARN");
+    assert!(records[0]["attributes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|it| it["key"] == "service.path" && it["value"]["stringValue"] == "hello_world"));
+}
+
+#[tokio::test]
+async fn test_event_sinks_forward_to_events_worker() {
+    let (events_worker_tx, mut events_worker_rx) = event_channel(EventChannelOpts::default());
+    let tx = spawn_event_sinks(
+        vec![EventSinkConfig::Stdout],
+        Some(events_worker_tx),
+        EventChannelOpts::default(),
+    )
+    .unwrap();
+
+    tx.send(log_event("forwarded")).unwrap();
+
+    let event = events_worker_rx.recv().await.unwrap();
+
+    assert!(matches!(
+        event.event,
+        WorkerEvents::Log(LogEvent { msg, .. }) if msg == "forwarded"
+    ));
+}
@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use base::rt_worker::worker_ctx::{create_events_worker, TerminationToken};
+use event_worker::channel::EventChannelOpts;
+use event_worker::events::{
+    EventMetadata, LogEvent, LogLevel, PseudoEvent, WorkerEventWithMetadata, WorkerEvents,
+};
+use serial_test::serial;
+
+fn log_event(msg: &str) -> WorkerEventWithMetadata {
+    WorkerEventWithMetadata {
+        event: WorkerEvents::Log(LogEvent {
+            msg: msg.to_string(),
+            level: LogLevel::Info,
+            location: None,
+            fields: None,
+        }),
+        metadata: EventMetadata::default(),
+    }
+}
+
+fn with_service_path(mut event: WorkerEventWithMetadata, path: &str) -> WorkerEventWithMetadata {
+    event.metadata.service_path = Some(path.to_string());
+    event
+}
+
+async fn wait_for_restarts(restarts: &AtomicUsize, count: usize) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while restarts.load(Ordering::Relaxed) < count {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn test_events_worker_is_restarted_after_crash() {
+    let termination_token = TerminationToken::new();
+    let (_, events_tx) = create_events_worker(
+        "./test_cases/events_worker_crash".into(),
+        None,
+        false,
+        None,
+        EventChannelOpts::default(),
+        Some(termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let counters = events_tx.counters();
+
+    events_tx.send(log_event("crash")).unwrap();
+    wait_for_restarts(&counters.consumer_restarts, 1).await;
+
+    // the restarted instance keeps consuming the same channel
+    events_tx.send(log_event("crash")).unwrap();
+    wait_for_restarts(&counters.consumer_restarts, 2).await;
+
+    assert_eq!(counters.dropped_events.load(Ordering::Relaxed), 0);
+
+    termination_token.cancel_and_wait().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_events_worker_only_receives_subscribed_events() {
+    let termination_token = TerminationToken::new();
+    let (_, events_tx) = create_events_worker(
+        "./test_cases/events_worker_filter".into(),
+        None,
+        false,
+        None,
+        EventChannelOpts::default(),
+        Some(termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let counters = events_tx.counters();
+
+    // the worker throws on any event it receives
+    events_tx
+        .send(with_service_path(log_event("skipped"), "other/fn"))
+        .unwrap();
+    events_tx
+        .send(with_service_path(
+            WorkerEventWithMetadata {
+                event: WorkerEvents::EventLoopCompleted(PseudoEvent {}),
+                metadata: EventMetadata::default(),
+            },
+            "crash/fn",
+        ))
+        .unwrap();
+    events_tx.send(log_event("skipped")).unwrap();
+    events_tx
+        .send(with_service_path(log_event("received"), "crash/fn"))
+        .unwrap();
+
+    wait_for_restarts(&counters.consumer_restarts, 1).await;
+
+    // the skipped events are all taken from the channel before the received
+    // one, so a worker that got any of them would have crashed on it instead
+    assert_eq!(counters.unsubscribed_events.load(Ordering::Relaxed), 3);
+    assert_eq!(counters.consumer_restarts.load(Ordering::Relaxed), 1);
+
+    termination_token.cancel_and_wait().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_events_worker_subscriptions_receive_overlapping_events() {
+    let termination_token = TerminationToken::new();
+    let (_, events_tx) = create_events_worker(
+        "./test_cases/events_worker_subscriptions".into(),
+        None,
+        false,
+        None,
+        EventChannelOpts::default(),
+        Some(termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let counters = events_tx.counters();
+
+    // the worker only crashes once both of its subscriptions saw the event
+    events_tx
+        .send(with_service_path(log_event("crash"), "crash/fn"))
+        .unwrap();
+
+    wait_for_restarts(&counters.consumer_restarts, 1).await;
+
+    termination_token.cancel_and_wait().await;
+}
@@ -0,0 +1,80 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use deno_core::serde_json::json;
+use sb_core::permissions::FsAccessRules;
+use sb_workers::context::UserWorkerRuntimeOpts;
+use serial_test::serial;
+
+use crate::integration_test_helper::{run_worker_and_collect_json_log, test_user_runtime_opts};
+
+#[test]
+fn test_fs_access_rules_matching() {
+    let rules = FsAccessRules::new(
+        Some(vec![PathBuf::from("./test_cases/fs_access_rules")]),
+        Some(vec![]),
+    );
+
+    assert!(rules.is_read_allowed(&PathBuf::from("./test_cases/fs_access_rules/index.ts")));
+    assert!(rules.is_read_allowed(&PathBuf::from("./test_cases/fs_access_rules/missing/file")));
+    assert!(!rules.is_read_allowed(&PathBuf::from(
+        "./test_cases/fs_access_rules/../main/index.ts"
+    )));
+    // a sibling sharing the prefix is not within the directory
+    assert!(!rules.is_read_allowed(&PathBuf::from("./test_cases/fs_access_rules_other")));
+    assert!(!rules.is_write_allowed(&PathBuf::from("./test_cases/fs_access_rules/index.ts")));
+
+    assert!(FsAccessRules::default().is_write_allowed(&PathBuf::from("/tmp")));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_fs_access_rules_apply_to_user_workers() {
+    let write_dir = std::env::temp_dir().join(format!("fs-access-{}", uuid::Uuid::new_v4()));
+
+    std::fs::create_dir_all(&write_dir).unwrap();
+    std::os::unix::fs::symlink("/etc", write_dir.join("etc")).unwrap();
+
+    let results = run_worker_and_collect_json_log(
+        "./test_cases/fs_access_rules",
+        HashMap::from([(
+            "WRITE_DIR".to_string(),
+            write_dir.to_string_lossy().to_string(),
+        )]),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            fs_access_rules: Some(FsAccessRules::new(
+                Some(vec![
+                    PathBuf::from("./test_cases/fs_access_rules"),
+                    write_dir.clone(),
+                ]),
+                Some(vec![write_dir.clone()]),
+            )),
+            ..test_user_runtime_opts()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        results,
+        json!({
+            "read service dir": "allowed",
+            "read outside": "denied",
+            "read through symlink": "denied",
+            "write service dir": "denied",
+            "write allowed dir": "allowed",
+            "read dir outside": "denied",
+            "cwd": "allowed",
+            "chdir": "undefined",
+        })
+    );
+    assert_eq!(
+        std::fs::read_to_string(write_dir.join("out.txt")).unwrap(),
+        "meow"
+    );
+
+    std::fs::remove_dir_all(&write_dir).unwrap();
+}
@@ -43,6 +43,7 @@ async fn test_not_trigger_pku_sigsegv_due_to_jit_compilation_non_cli() {
             worker_pool_tx,
             shared_metric_src: None,
             event_worker_metric_src: None,
+            fs_access_rules: Default::default(),
         }),
     };
 
@@ -0,0 +1,86 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use base::rt_worker::worker_pool::SupervisorPolicy;
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::{LogEvent, LogLevel};
+use sb_workers::context::UserWorkerRuntimeOpts;
+use serial_test::serial;
+
+use crate::integration_test_helper::{
+    collect_events_until_shutdown, create_test_user_worker, test_user_runtime_opts,
+    user_worker_opts,
+};
+
+#[tokio::test]
+#[serial]
+async fn test_console_levels_are_preserved_and_filtered() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/console_levels",
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            log_level: LogLevel::Info,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let (logs, _) = collect_events_until_shutdown(&mut events_rx).await;
+
+    let levels = logs
+        .iter()
+        .map(|LogEvent { msg, level, .. }| (msg.trim_end(), *level))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        levels,
+        vec![
+            ("log message", LogLevel::Info),
+            ("info message", LogLevel::Info),
+            ("warn message", LogLevel::Warning),
+            ("error message", LogLevel::Error),
+        ]
+    );
+
+    let location = logs[3].location.as_ref().unwrap();
+
+    assert!(location.file_name.ends_with("console_levels/index.ts"));
+    assert_eq!(location.line_number, 5);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_structured_log_fields() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/structured_logs",
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            structured_logs: true,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let (logs, _) = collect_events_until_shutdown(&mut events_rx).await;
+
+    assert_eq!(logs.len(), 2);
+
+    let fields = logs[0].fields.as_ref().unwrap();
+
+    assert_eq!(fields["user"], "meow");
+    assert_eq!(fields["count"], "3");
+    assert_eq!(fields["self"], "[Circular]");
+    assert_eq!(fields["_truncated"], true);
+    assert!(!fields.contains_key("big"));
+    assert!(logs[1].fields.is_none());
+}
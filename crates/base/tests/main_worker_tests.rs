@@ -133,6 +133,7 @@ async fn test_main_worker_boot_error() {
             worker_pool_tx,
             shared_metric_src: None,
             event_worker_metric_src: None,
+            fs_access_rules: Default::default(),
         }),
     };
 
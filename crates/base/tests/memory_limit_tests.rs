@@ -0,0 +1,74 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use base::rt_worker::worker_pool::SupervisorPolicy;
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::ShutdownReason;
+use sb_workers::context::{HeapSnapshotOpts, UserWorkerRuntimeOpts};
+use serial_test::serial;
+
+use crate::integration_test_helper::{
+    collect_events_until_shutdown, create_test_user_worker, test_user_runtime_opts,
+    user_worker_opts,
+};
+
+#[tokio::test]
+#[serial]
+async fn test_combined_heap_and_external_memory_limit() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/external_memory",
+        UserWorkerRuntimeOpts {
+            memory_limit_mb: 16,
+            memory_sample_interval_ms: 50,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let (_, shutdown) = collect_events_until_shutdown(&mut events_rx).await;
+
+    assert!(matches!(shutdown.reason, ShutdownReason::Memory));
+    assert!(shutdown.memory_high_water_mark.external >= 15 * 1024 * 1024);
+    assert!(shutdown.memory_high_water_mark.total >= 16 * 1024 * 1024);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_heap_snapshot_on_memory_limit() {
+    let dir = std::env::temp_dir().join(format!("heap-snapshot-{}", uuid::Uuid::new_v4()));
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/external_memory",
+        UserWorkerRuntimeOpts {
+            memory_limit_mb: 16,
+            memory_sample_interval_ms: 50,
+            events_msg_tx: Some(events_tx),
+            heap_snapshot: Some(HeapSnapshotOpts {
+                dir: dir.clone(),
+                max_size_bytes: 256 * 1024 * 1024,
+                min_interval_ms: 0,
+            }),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let (_, shutdown) = collect_events_until_shutdown(&mut events_rx).await;
+
+    assert!(matches!(shutdown.reason, ShutdownReason::Memory));
+
+    let path = shutdown.heap_snapshot.unwrap();
+
+    assert!(path.starts_with(&dir));
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,258 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::collections::HashMap;
+
+use deno_core::serde_json::json;
+use sb_core::permissions::{is_private_ip, NetAccessRules};
+use sb_workers::context::UserWorkerRuntimeOpts;
+use serial_test::serial;
+use tokio::net::TcpListener;
+
+use crate::integration_test_helper::{
+    run_worker_and_collect_json_log, serve_websocket_echo, test_user_runtime_opts,
+};
+
+fn rules(allow: Option<&[&str]>, deny: &[&str]) -> NetAccessRules {
+    let to_strings = |it: &[&str]| it.iter().map(|it| it.to_string()).collect::<Vec<_>>();
+
+    NetAccessRules::parse(allow.map(to_strings).as_deref(), &to_strings(deny)).unwrap()
+}
+
+#[test]
+fn test_net_access_rules_matching() {
+    let rules = rules(
+        Some(&[
+            "api.example.com",
+            "*.supabase.co:443",
+            "10.0.0.0/8",
+            "fd00::/8",
+            "*:8080",
+        ]),
+        &["internal.supabase.co", "10.1.0.0/16"],
+    );
+
+    assert!(rules.is_allowed("api.example.com", Some(443)));
+    assert!(rules.is_allowed("API.example.com.", Some(80)));
+    assert!(!rules.is_allowed("example.com", Some(443)));
+
+    assert!(rules.is_allowed("db.supabase.co", Some(443)));
+    assert!(!rules.is_allowed("db.supabase.co", Some(80)));
+    assert!(!rules.is_allowed("supabase.co", Some(443)));
+    assert!(!rules.is_allowed("internal.supabase.co", Some(443)));
+
+    assert!(rules.is_allowed("10.2.3.4", Some(5432)));
+    assert!(!rules.is_allowed("10.1.3.4", Some(5432)));
+    assert!(!rules.is_allowed("11.0.0.1", Some(80)));
+    assert!(rules.is_allowed("[fd12::1]", Some(80)));
+
+    assert!(rules.is_allowed("anything.dev", Some(8080)));
+
+    assert!(NetAccessRules::default().is_allowed("anything.dev", Some(80)));
+}
+
+#[test]
+fn test_net_access_rules_reject_invalid_entries() {
+    for entry in ["", "*.", "a.*.com", "10.0.0.0/33", "host:port", "[::1"] {
+        assert!(
+            NetAccessRules::parse(None, &[entry.to_string()]).is_err(),
+            "{} should be rejected",
+            entry
+        );
+    }
+}
+
+#[test]
+fn test_private_ips() {
+    for ip in [
+        "0.0.0.0",
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "198.18.0.1",
+        "224.0.0.1",
+        "255.255.255.255",
+        "::",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "ff02::1",
+        "::ffff:127.0.0.1",
+        "64:ff9b::a9fe:a9fe",
+    ] {
+        assert!(
+            is_private_ip(&ip.parse().unwrap()),
+            "{} should be private",
+            ip
+        );
+    }
+
+    for ip in [
+        "1.1.1.1",
+        "172.32.0.1",
+        "100.128.0.1",
+        "2606:4700::1111",
+        "::ffff:8.8.8.8",
+    ] {
+        assert!(
+            !is_private_ip(&ip.parse().unwrap()),
+            "{} should be public",
+            ip
+        );
+    }
+}
+
+#[test]
+fn test_deny_private_net_rejects_private_literals() {
+    let rules = NetAccessRules {
+        deny_private_net: true,
+        ..Default::default()
+    };
+
+    assert!(!rules.is_allowed("127.0.0.1", Some(80)));
+    assert!(!rules.is_allowed("[::1]", Some(80)));
+    assert!(!rules.is_allowed("169.254.169.254", Some(80)));
+    assert!(rules.is_allowed("1.1.1.1", Some(443)));
+    // hostnames are checked once they are resolved
+    assert!(rules.is_allowed("localhost", Some(80)));
+    assert!(!rules.is_allowed_addr(&"127.0.0.1".parse().unwrap(), Some(80)));
+}
+
+#[test]
+fn test_net_access_rules_cidr_matching_ipv4_mapped() {
+    let rules = rules(None, &["10.0.0.0/8", "::ffff:192.168.0.0/112"]);
+
+    assert!(!rules.is_allowed("[::ffff:10.1.2.3]", Some(80)));
+    assert!(!rules.is_allowed("192.168.1.1", Some(80)));
+    assert!(rules.is_allowed("[::ffff:11.0.0.1]", Some(80)));
+}
+
+#[test]
+fn test_net_access_rules_deny_cidr_checks_resolved_addresses() {
+    assert!(!rules(None, &["*.example.com"]).checks_resolved_addrs());
+
+    let rules = rules(None, &["10.0.0.0/8", "127.0.0.1:5432"]);
+
+    assert!(rules.checks_resolved_addrs());
+    assert!(!rules.is_allowed_addr(&"10.1.2.3".parse().unwrap(), Some(80)));
+    assert!(!rules.is_allowed_addr(&"::ffff:10.1.2.3".parse().unwrap(), Some(80)));
+    assert!(rules.is_allowed_addr(&"127.0.0.1".parse().unwrap(), Some(80)));
+    assert!(!rules.is_allowed_addr(&"127.0.0.1".parse().unwrap(), Some(5432)));
+    // the port is not known when `fetch` resolves the host
+    assert!(!rules.is_allowed_addr(&"127.0.0.1".parse().unwrap(), None));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_net_access_rules_apply_to_fetch_websocket_and_connect() {
+    let results = run_worker_and_collect_json_log(
+        "./test_cases/net_access_rules",
+        HashMap::new(),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            net_access_rules: rules(
+                Some(&["127.0.0.0/8", "*.example.com:443"]),
+                &["127.0.0.1:2"],
+            ),
+            ..test_user_runtime_opts()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        results,
+        json!({
+            "fetch 127.0.0.1:1": "allowed",
+            "fetch 127.0.0.1:2": "denied",
+            "fetch localhost:1": "denied",
+            "fetch api.example.com:444": "denied",
+            "websocket 127.0.0.1:2": "denied",
+            "connect 127.0.0.1:1": "allowed",
+            "connect 10.0.0.1:80": "denied",
+        })
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_deny_private_net_checks_resolved_addresses() {
+    let results = run_worker_and_collect_json_log(
+        "./test_cases/deny_private_net",
+        HashMap::new(),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            net_access_rules: NetAccessRules {
+                deny_private_net: true,
+                ..Default::default()
+            },
+            ..test_user_runtime_opts()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        results,
+        json!({
+            "fetch 127.0.0.1": "denied",
+            "fetch 169.254.169.254": "denied",
+            "fetch [::ffff:10.0.0.1]": "denied",
+            "fetch localhost": "denied",
+            "websocket 192.168.0.1": "denied",
+            "websocket localhost": "denied",
+            "websocket wss localhost": "denied",
+            "connect localhost": "denied",
+            "connectTls localhost": "denied",
+        })
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_deny_cidr_checks_resolved_addresses() {
+    let results = run_worker_and_collect_json_log(
+        "./test_cases/net_deny_cidr",
+        HashMap::new(),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            net_access_rules: rules(None, &["127.0.0.0/8", "::1"]),
+            ..test_user_runtime_opts()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        results,
+        json!({
+            "fetch localhost": "denied",
+            "fetch [::ffff:127.0.0.1]": "denied",
+            "websocket localhost": "denied",
+            "connect localhost": "denied",
+            "connectTls localhost": "denied",
+        })
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_websocket_connects_to_resolved_addresses() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(serve_websocket_echo(listener));
+
+    let results = run_worker_and_collect_json_log(
+        "./test_cases/websocket_echo",
+        HashMap::from([("WS_URL".to_string(), format!("ws://localhost:{}/", port))]),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            net_access_rules: rules(Some(&["localhost"]), &[]),
+            ..test_user_runtime_opts()
+        },
+    )
+    .await;
+
+    assert_eq!(results, json!({ "echo": "meow" }));
+}
@@ -0,0 +1,147 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use base::integration_test;
+use base::otel::{init_tracing, SpanContext, TracingConfig};
+use deno_core::serde_json::{self, Value};
+use deno_core::url::Url;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use serial_test::serial;
+use tokio::sync::mpsc;
+
+const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+fn find_span<'a>(spans: &'a [Value], name: &str) -> &'a Value {
+    spans
+        .iter()
+        .find(|it| it["name"] == name)
+        .unwrap_or_else(|| panic!("span {} was not exported", name))
+}
+
+#[tokio::test]
+#[serial]
+async fn test_request_spans_are_exported_and_propagated() {
+    let (spans_tx, mut spans_rx) = mpsc::unbounded_channel::<Vec<Value>>();
+
+    // local collector stub, which also echoes the trace context of the
+    // outbound requests of the user worker
+    let make_svc = make_service_fn(move |_| {
+        let spans_tx = spans_tx.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let spans_tx = spans_tx.clone();
+
+                async move {
+                    if req.uri().path() == "/outbound" {
+                        let traceparent = req
+                            .headers()
+                            .get("traceparent")
+                            .map(|it| it.to_str().unwrap().to_string())
+                            .unwrap_or_default();
+
+                        return Ok::<_, Infallible>(Response::new(Body::from(traceparent)));
+                    }
+
+                    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                    let payload = serde_json::from_slice::<Value>(&body).unwrap();
+                    let spans = payload["resourceSpans"][0]["scopeSpans"][0]["spans"]
+                        .as_array()
+                        .cloned()
+                        .unwrap_or_default();
+
+                    spans_tx.send(spans).unwrap();
+
+                    Ok::<_, Infallible>(Response::new(Body::from("{}")))
+                }
+            }))
+        }
+    });
+
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let addr = server.local_addr();
+
+    drop(tokio::spawn(server));
+
+    init_tracing(TracingConfig {
+        flush_interval_ms: 100,
+        ..TracingConfig::new(Url::parse(&format!("http://{}/v1/traces", addr)).unwrap())
+    })
+    .unwrap();
+
+    // NOTE: The port is released right away so that the server can bind it.
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let client = reqwest::Client::new();
+    let req = client
+        .get(format!(
+            "http://localhost:{}/traced_fetch?target=http://{}/outbound",
+            port, addr
+        ))
+        .header(
+            "traceparent",
+            format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID),
+        )
+        .build()
+        .unwrap();
+
+    let request_builder = Some(reqwest::RequestBuilder::from_parts(client, req));
+    let (outbound_tx, mut outbound_rx) = mpsc::unbounded_channel::<String>();
+
+    integration_test!(
+        "./test_cases/main",
+        port,
+        "",
+        None,
+        None,
+        request_builder,
+        (|resp: Result<reqwest::Response, reqwest::Error>| async move {
+            let res = resp.unwrap();
+
+            assert_eq!(res.status().as_u16(), 200);
+            outbound_tx.send(res.text().await.unwrap()).unwrap();
+        })
+    );
+
+    let outbound = SpanContext::from_traceparent(&outbound_rx.recv().await.unwrap()).unwrap();
+    let mut spans = vec![];
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while spans.len() < 4 {
+            spans.extend(spans_rx.recv().await.unwrap());
+        }
+    })
+    .await
+    .unwrap();
+
+    for span in &spans {
+        assert_eq!(span["traceId"], TRACE_ID);
+    }
+
+    let server_span = find_span(&spans, "HTTP GET");
+    let main_worker_span = find_span(&spans, "main_worker.request");
+    let create_span = find_span(&spans, "user_worker.create");
+    let request_span = find_span(&spans, "user_worker.request");
+
+    assert_eq!(server_span["parentSpanId"], PARENT_SPAN_ID);
+    assert_eq!(server_span["kind"], 2);
+    assert_eq!(main_worker_span["parentSpanId"], server_span["spanId"]);
+    assert_eq!(create_span["parentSpanId"], main_worker_span["spanId"]);
+    assert_eq!(request_span["parentSpanId"], main_worker_span["spanId"]);
+
+    // the outbound `fetch` of the user worker is a child of its request span
+    assert_eq!(
+        outbound.to_traceparent(),
+        format!(
+            "00-{}-{}-01",
+            TRACE_ID,
+            request_span["spanId"].as_str().unwrap()
+        )
+    );
+}
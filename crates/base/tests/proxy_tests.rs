@@ -0,0 +1,289 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use deno_core::serde_json::{json, Value};
+use deno_core::url::Url;
+use sb_core::http_client::ProxyConfig;
+use sb_core::permissions::NetAccessRules;
+use sb_workers::context::UserWorkerRuntimeOpts;
+use serial_test::serial;
+use tokio::io::{copy_bidirectional, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::integration_test_helper::{
+    run_worker_and_collect_json_log, serve_websocket_echo, test_user_runtime_opts,
+};
+
+#[test]
+fn test_proxy_config() {
+    let proxy = ProxyConfig::parse(
+        "socks5h://proxy:1080",
+        &["*.internal".to_string(), "10.0.0.0/8".to_string()],
+    )
+    .unwrap();
+
+    let applies_to = |url: &str| proxy.applies_to(&Url::parse(url).unwrap());
+
+    assert!(applies_to("https://example.com/"));
+    assert!(!applies_to("http://db.internal/"));
+    assert!(!applies_to("http://10.1.2.3:8080/"));
+
+    assert!(ProxyConfig::parse("ftp://proxy:21", &[]).is_err());
+    assert!(ProxyConfig::parse("proxy:3128", &[]).is_err());
+    assert!(ProxyConfig::parse("http://proxy:3128", &["[".to_string()]).is_err());
+
+    // `fetch` and the WebSocket clients resolve the destinations of socks5
+    // proxies without checking the addresses
+    let deny_private_net = NetAccessRules {
+        deny_private_net: true,
+        ..Default::default()
+    };
+
+    let check = |url: &str, rules: &NetAccessRules| {
+        ProxyConfig::parse(url, &[])
+            .unwrap()
+            .check_net_access_rules(rules)
+    };
+
+    assert!(check("socks5://proxy:1080", &deny_private_net).is_err());
+    assert!(check("socks5://proxy:1080", &NetAccessRules::default()).is_ok());
+    assert!(check("socks5h://proxy:1080", &deny_private_net).is_ok());
+    assert!(check("http://proxy:3128", &deny_private_net).is_ok());
+}
+
+/// Answers every request with its request line, which is in absolute form
+/// when it was sent to a proxy.
+async fn serve_request_lines(listener: TcpListener) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            return;
+        };
+
+        tokio::spawn(async move {
+            let mut stream = BufReader::new(stream);
+            let mut request_line = String::new();
+            let mut line = String::new();
+
+            stream.read_line(&mut request_line).await.unwrap();
+
+            while stream.read_line(&mut line).await.unwrap() > 2 {
+                line.clear();
+            }
+
+            let body = request_line.trim_end();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+    }
+}
+
+async fn run_user_worker_proxy(net_access_rules: NetAccessRules) -> Value {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(serve_request_lines(listener));
+
+    run_worker_and_collect_json_log(
+        "./test_cases/user_worker_proxy",
+        HashMap::from([("PROXY_PORT".to_string(), port.to_string())]),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            net_access_rules,
+            proxy: Some(
+                ProxyConfig::parse(
+                    &format!("http://127.0.0.1:{}", port),
+                    &["127.0.0.1".to_string()],
+                )
+                .unwrap(),
+            ),
+            ..test_user_runtime_opts()
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn test_user_worker_proxy() {
+    let results = run_user_worker_proxy(NetAccessRules::default()).await;
+
+    assert_eq!(
+        results["proxied"],
+        "GET http://proxied.invalid/hello HTTP/1.1"
+    );
+    assert_eq!(results["direct"], "GET /hello HTTP/1.1");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_user_worker_proxy_with_deny_private_net() {
+    let results = run_user_worker_proxy(NetAccessRules {
+        deny_private_net: true,
+        ..Default::default()
+    })
+    .await;
+
+    // the destinations reached through the proxy are only checked by name
+    assert_eq!(
+        results["proxied"],
+        "GET http://proxied.invalid/hello HTTP/1.1"
+    );
+    assert!(results["direct"]
+        .as_str()
+        .unwrap()
+        .contains("is not allowed"));
+}
+
+/// Forwards the first request to `target`, whatever the host it is for, and
+/// returns its request line.
+async fn serve_forward_proxy(listener: TcpListener, target: SocketAddr) -> String {
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+
+    stream.read_line(&mut request_line).await.unwrap();
+
+    let mut upstream = TcpStream::connect(target).await.unwrap();
+
+    upstream.write_all(request_line.as_bytes()).await.unwrap();
+
+    tokio::spawn(async move {
+        let _ = copy_bidirectional(&mut stream, &mut upstream).await;
+    });
+
+    request_line.trim_end().to_string()
+}
+
+/// Tunnels the first SOCKS5 `CONNECT` request to `target`, whatever the
+/// address it asks for, and returns that address.
+async fn serve_socks5_proxy(listener: TcpListener, target: SocketAddr) -> String {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut head = [0u8; 2];
+
+    stream.read_exact(&mut head).await.unwrap();
+
+    let mut methods = vec![0u8; head[1] as usize];
+
+    stream.read_exact(&mut methods).await.unwrap();
+    stream.write_all(&[0x05, 0x00]).await.unwrap();
+
+    let mut request = [0u8; 4];
+
+    stream.read_exact(&mut request).await.unwrap();
+
+    let host = match request[3] {
+        0x01 => {
+            let mut ip = [0u8; 4];
+
+            stream.read_exact(&mut ip).await.unwrap();
+            Ipv4Addr::from(ip).to_string()
+        }
+
+        0x04 => {
+            let mut ip = [0u8; 16];
+
+            stream.read_exact(&mut ip).await.unwrap();
+            format!("[{}]", Ipv6Addr::from(ip))
+        }
+
+        _ => {
+            let mut name = vec![0u8; stream.read_u8().await.unwrap() as usize];
+
+            stream.read_exact(&mut name).await.unwrap();
+            String::from_utf8(name).unwrap()
+        }
+    };
+
+    let port = stream.read_u16().await.unwrap();
+
+    stream
+        .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+        .await
+        .unwrap();
+
+    let mut upstream = TcpStream::connect(target).await.unwrap();
+
+    tokio::spawn(async move {
+        let _ = copy_bidirectional(&mut stream, &mut upstream).await;
+    });
+
+    format!("{}:{}", host, port)
+}
+
+/// Runs a WebSocket echo through the proxy, and returns the results of the
+/// worker along with the destination the proxy was asked for.
+async fn run_websocket_echo_through_proxy(proxy_scheme: &str, ws_url: &str) -> (Value, String) {
+    let echo_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let echo_addr = echo_listener.local_addr().unwrap();
+    let proxy_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy_url = format!(
+        "{}://127.0.0.1:{}",
+        proxy_scheme,
+        proxy_listener.local_addr().unwrap().port()
+    );
+
+    tokio::spawn(serve_websocket_echo(echo_listener));
+
+    let requested = if proxy_scheme == "http" {
+        tokio::spawn(serve_forward_proxy(proxy_listener, echo_addr))
+    } else {
+        tokio::spawn(serve_socks5_proxy(proxy_listener, echo_addr))
+    };
+
+    let results = run_worker_and_collect_json_log(
+        "./test_cases/websocket_echo",
+        HashMap::from([("WS_URL".to_string(), ws_url.to_string())]),
+        UserWorkerRuntimeOpts {
+            worker_timeout_ms: 1000,
+            proxy: Some(ProxyConfig::parse(&proxy_url, &[]).unwrap()),
+            ..test_user_runtime_opts()
+        },
+    )
+    .await;
+
+    (results, requested.await.unwrap())
+}
+
+#[tokio::test]
+#[serial]
+async fn test_websocket_through_http_proxy() {
+    let (results, requested) = run_websocket_echo_through_proxy("http", "ws://echo.invalid/").await;
+
+    // plain WebSocket connections are sent to HTTP proxies as any other
+    // `http` request, rather than tunneled
+    assert_eq!(results, json!({ "echo": "meow" }));
+    assert_eq!(requested, "GET http://echo.invalid/ HTTP/1.1");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_websocket_through_socks5h_proxy() {
+    let (results, requested) =
+        run_websocket_echo_through_proxy("socks5h", "ws://echo.invalid/").await;
+
+    assert_eq!(results, json!({ "echo": "meow" }));
+    assert_eq!(requested, "echo.invalid:80");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_websocket_through_socks5_proxy() {
+    let (results, requested) =
+        run_websocket_echo_through_proxy("socks5", "ws://localhost:8080/").await;
+
+    assert_eq!(results, json!({ "echo": "meow" }));
+
+    // the host is resolved before asking the proxy
+    let requested = requested.parse::<SocketAddr>().unwrap();
+
+    assert!(requested.ip().is_loopback());
+    assert_eq!(requested.port(), 8080);
+}
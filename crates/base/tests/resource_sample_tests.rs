@@ -0,0 +1,51 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use base::rt_worker::worker_pool::SupervisorPolicy;
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::{ResourceSampleEvent, WorkerEvents};
+use sb_workers::context::UserWorkerRuntimeOpts;
+use serial_test::serial;
+
+use crate::integration_test_helper::{
+    create_test_user_worker, test_user_runtime_opts, user_worker_opts,
+};
+
+#[tokio::test]
+#[serial]
+async fn test_resource_samples_are_emitted_periodically() {
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let opts = user_worker_opts(
+        "./test_cases/std_user_worker",
+        UserWorkerRuntimeOpts {
+            resource_sample_interval_ms: 50,
+            events_msg_tx: Some(events_tx),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    let (_, _scope) = create_test_user_worker((opts, SupervisorPolicy::PerWorker))
+        .await
+        .unwrap();
+
+    let mut samples = 0;
+
+    while samples < 2 {
+        let WorkerEvents::ResourceSample(ResourceSampleEvent {
+            memory_used,
+            inflight_requests,
+            served_requests,
+            ..
+        }) = events_rx.recv().await.unwrap().event
+        else {
+            continue;
+        };
+
+        assert!(memory_used.heap > 0);
+        assert_eq!(memory_used.total, memory_used.heap + memory_used.external);
+        assert_eq!(inflight_requests, 0);
+        assert_eq!(served_requests, 0);
+
+        samples += 1;
+    }
+}
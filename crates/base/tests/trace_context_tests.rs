@@ -0,0 +1,118 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use base::otel::{init_tracing, TracingConfig};
+use base::rt_worker::worker_ctx::{create_user_worker_pool, TerminationToken};
+use base::rt_worker::worker_pool::{SupervisorPolicy, WorkerPoolPolicy};
+use deno_core::serde_json::{self, Value};
+use deno_core::url::Url;
+use http::{Request, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use sb_workers::context::{ResponseEnd, UserWorkerMsgs};
+use serial_test::serial;
+use tokio::sync::oneshot;
+
+use crate::integration_test_helper::{test_user_runtime_opts, user_worker_opts};
+
+#[tokio::test]
+#[serial]
+async fn test_trace_context_of_concurrent_requests() {
+    // echoes the trace context of the outbound requests of the user worker,
+    // and swallows the exported spans
+    let make_svc = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+            if req.uri().path() != "/outbound" {
+                return Ok::<_, Infallible>(Response::new(Body::from("{}")));
+            }
+
+            let traceparent = req
+                .headers()
+                .get("traceparent")
+                .map(|it| it.to_str().unwrap().to_string())
+                .unwrap_or_default();
+
+            Ok::<_, Infallible>(Response::new(Body::from(traceparent)))
+        }))
+    });
+
+    let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_svc);
+    let addr = server.local_addr();
+
+    drop(tokio::spawn(server));
+
+    init_tracing(TracingConfig::new(
+        Url::parse(&format!("http://{}/v1/traces", addr)).unwrap(),
+    ))
+    .unwrap();
+
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let (result_tx, result_rx) = oneshot::channel();
+    let opts = user_worker_opts(
+        "./test_cases/traced_fetch_concurrent",
+        test_user_runtime_opts(),
+    );
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Create(opts, result_tx))
+        .unwrap();
+
+    let key = result_rx.await.unwrap().unwrap().key;
+    let trace_ids = [
+        "4bf92f3577b34da6a3ce929d0e0e4736",
+        "0af7651916cd43dd8448eb211c80319c",
+    ];
+
+    let res_rxs = trace_ids.map(|trace_id| {
+        let (res_tx, res_rx) = oneshot::channel();
+        let req = Request::builder()
+            .uri(format!(
+                "/traced_fetch_concurrent?target=http://{}/outbound",
+                addr
+            ))
+            .header(
+                "traceparent",
+                format!("00-{}-00f067aa0ba902b7-01", trace_id),
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        worker_pool_tx
+            .send(UserWorkerMsgs::SendRequest(key, req, res_tx, None))
+            .unwrap();
+
+        res_rx
+    });
+
+    for (trace_id, res_rx) in trace_ids.into_iter().zip(res_rxs) {
+        let (res, req_end_tx) = res_rx.await.unwrap().unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let body = serde_json::from_slice::<Value>(&body).unwrap();
+        // the request given explicitly carries its own trace context...
+        assert!(body["explicit"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("00-{}-", trace_id)));
+
+        // ...but which of the concurrent requests is served can't be told
+        assert_eq!(body["implicit"], "");
+
+        let _ = req_end_tx.send(ResponseEnd::default());
+    }
+
+    pool_termination_token.cancel_and_wait().await;
+}
@@ -4,6 +4,7 @@ mod integration_test_helper;
 use std::collections::HashMap;
 
 use sb_workers::context::{WorkerContextInitOpts, WorkerRuntimeOpts};
+use sb_workers::errors::WorkerBootError;
 
 use crate::integration_test_helper::{create_test_user_worker, test_user_runtime_opts};
 
@@ -25,5 +26,9 @@ async fn test_worker_boot_invalid_imports() {
     let result = create_test_user_worker(opts).await;
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().to_string(), "worker boot error");
+
+    let err = result.unwrap_err();
+
+    assert_eq!(err.to_string(), "worker boot error");
+    assert!(err.downcast_ref::<WorkerBootError>().is_some());
 }
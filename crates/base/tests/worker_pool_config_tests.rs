@@ -0,0 +1,276 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use base::rt_worker::worker_ctx::{create_user_worker_pool, TerminationToken};
+use base::rt_worker::worker_pool::{SupervisorPolicy, WorkerPoolPolicy};
+use base::rt_worker::worker_pool_config::WorkerPoolConfig;
+use sb_workers::context::{UserWorkerMsgs, UserWorkerRuntimeOpts, WorkerContextInitOpts};
+use serial_test::serial;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::integration_test_helper::{test_user_runtime_opts, user_worker_opts};
+
+fn hello_world_opts(conf: UserWorkerRuntimeOpts) -> WorkerContextInitOpts {
+    WorkerContextInitOpts {
+        env_vars: HashMap::from([
+            ("SUPABASE_URL".to_string(), "http://localhost".to_string()),
+            ("SECRET_TOKEN".to_string(), "secret".to_string()),
+        ]),
+        ..user_worker_opts("./examples/hello-world", conf)
+    }
+}
+
+#[test]
+fn test_worker_pool_config_toml_clamps_requested_limits() {
+    let config = WorkerPoolConfig::from_toml_str(
+        r#"
+        [[service]]
+        path = "./examples/hello-*"
+        max_parallelism = 2
+        net_access_disabled = true
+        env_allowlist = ["SUPABASE_*"]
+
+        [service.limits]
+        memory_limit_mb = 64
+        cpu_time_soft_limit_ms = 20
+        cpu_time_hard_limit_ms = 40
+
+        [[service]]
+        path = "*"
+        "#,
+    )
+    .unwrap();
+
+    let service = config.find("./examples/hello-world").unwrap();
+    let mut opts = hello_world_opts(UserWorkerRuntimeOpts {
+        memory_limit_mb: 512,
+        cpu_time_soft_limit_ms: 10,
+        cpu_time_hard_limit_ms: 0,
+        ..Default::default()
+    });
+
+    service.apply(&mut opts);
+
+    let conf = opts.conf.as_user_worker().unwrap();
+
+    assert_eq!(service.max_parallelism, Some(2));
+    assert_eq!(conf.memory_limit_mb, 64);
+    assert_eq!(conf.cpu_time_soft_limit_ms, 10);
+    assert_eq!(conf.cpu_time_hard_limit_ms, 40);
+    assert!(conf.net_access_disabled);
+    assert_eq!(
+        opts.env_vars.keys().collect::<Vec<_>>(),
+        vec![&"SUPABASE_URL".to_string()]
+    );
+
+    let fallback = config.find("./examples/oak").unwrap();
+    assert!(fallback.max_parallelism.is_none());
+}
+
+#[test]
+fn test_worker_pool_config_json() {
+    let config = WorkerPoolConfig::from_json_str(
+        r#"{ "service": [{ "path": "./examples/*", "limits": { "worker_timeout_ms": 1000 } }] }"#,
+    )
+    .unwrap();
+
+    let mut opts = hello_world_opts(UserWorkerRuntimeOpts::default());
+
+    config.find("./examples/oak").unwrap().apply(&mut opts);
+
+    assert_eq!(opts.conf.as_user_worker().unwrap().worker_timeout_ms, 1000);
+    assert_eq!(opts.env_vars.len(), 2);
+    assert!(config.find("./functions/oak").is_none());
+}
+
+#[test]
+fn test_worker_pool_config_only_caps_zero_cpu_time_limits() {
+    let config = WorkerPoolConfig::from_toml_str(
+        r#"
+        [[service]]
+        path = "*"
+
+        [service.limits]
+        memory_limit_mb = 64
+        low_memory_multiplier = 5
+        cpu_time_soft_limit_ms = 20
+        cpu_time_hard_limit_ms = 40
+        "#,
+    )
+    .unwrap();
+
+    let mut opts = hello_world_opts(UserWorkerRuntimeOpts {
+        memory_limit_mb: 0,
+        low_memory_multiplier: 0,
+        cpu_time_soft_limit_ms: 0,
+        cpu_time_hard_limit_ms: 0,
+        ..Default::default()
+    });
+
+    config.find("./examples/oak").unwrap().apply(&mut opts);
+
+    let conf = opts.conf.as_user_worker().unwrap();
+
+    assert_eq!(conf.memory_limit_mb, 0);
+    assert_eq!(conf.low_memory_multiplier, 0);
+    assert_eq!(conf.cpu_time_soft_limit_ms, 20);
+    assert_eq!(conf.cpu_time_hard_limit_ms, 40);
+}
+
+#[test]
+fn test_worker_pool_config_supervisor_policy() {
+    let config = WorkerPoolConfig::from_toml_str(
+        r#"
+        [[service]]
+        path = "./examples/oak"
+        policy = "oneshot"
+
+        [[service]]
+        path = "*"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config.find("./examples/oak").unwrap().policy,
+        Some(SupervisorPolicy::oneshot())
+    );
+    assert!(config
+        .find("./examples/hello-world")
+        .unwrap()
+        .policy
+        .is_none());
+    assert!(WorkerPoolConfig::from_toml_str(
+        r#"
+        [[service]]
+        path = "*"
+        policy = "per_tenant"
+        "#,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_worker_pool_config_rejects_unknown_fields() {
+    assert!(WorkerPoolConfig::from_toml_str(
+        r#"
+        [[service]]
+        path = "*"
+        memory_limit_mb = 64
+        "#,
+    )
+    .is_err());
+}
+
+fn write_max_parallelism(path: &Path, max_parallelism: usize) {
+    std::fs::write(
+        path,
+        format!(
+            "[[service]]\npath = \"./test_cases/std_user_worker\"\nmax_parallelism = {}\n",
+            max_parallelism
+        ),
+    )
+    .unwrap();
+}
+
+async fn try_create_user_worker(tx: &mpsc::UnboundedSender<UserWorkerMsgs>) -> bool {
+    let (result_tx, result_rx) = oneshot::channel();
+    let mut opts = hello_world_opts(test_user_runtime_opts());
+
+    opts.service_path = "./test_cases/std_user_worker".into();
+    tx.send(UserWorkerMsgs::Create(opts, result_tx)).unwrap();
+
+    result_rx.await.unwrap().is_ok()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_config_reload_resizes_max_parallelism() {
+    let dir = std::env::temp_dir().join(format!("pool-config-{}", uuid::Uuid::new_v4()));
+    let config_path = dir.join("pool.toml");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    write_max_parallelism(&config_path, 1);
+
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerRequest { oneshot: false }, 4, 500)
+            .with_config_path(config_path.clone()),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    // NOTE: Workers of the per request policy stay busy until they served a
+    // request, so every create request needs a permit of its own.
+    assert!(try_create_user_worker(&worker_pool_tx).await);
+    assert!(!try_create_user_worker(&worker_pool_tx).await);
+
+    write_max_parallelism(&config_path, 2);
+
+    let mut resized = false;
+
+    for _ in 0..20 {
+        if try_create_user_worker(&worker_pool_tx).await {
+            resized = true;
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert!(resized);
+    assert!(!try_create_user_worker(&worker_pool_tx).await);
+
+    pool_termination_token.cancel_and_wait().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+async fn create_user_worker(tx: &mpsc::UnboundedSender<UserWorkerMsgs>) -> uuid::Uuid {
+    let (result_tx, result_rx) = oneshot::channel();
+    let mut opts = hello_world_opts(test_user_runtime_opts());
+
+    opts.service_path = "./test_cases/std_user_worker".into();
+    tx.send(UserWorkerMsgs::Create(opts, result_tx)).unwrap();
+
+    result_rx.await.unwrap().unwrap().key
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_config_overrides_supervisor_policy() {
+    let dir = std::env::temp_dir().join(format!("pool-config-{}", uuid::Uuid::new_v4()));
+    let config_path = dir.join("pool.toml");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        &config_path,
+        "[[service]]\npath = \"./test_cases/std_user_worker\"\npolicy = \"per_request\"\n",
+    )
+    .unwrap();
+
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 500)
+            .with_config_path(config_path.clone()),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    // NOTE: Under the per worker policy of the pool, the second create
+    // request would get the worker of the first one.
+    let first = create_user_worker(&worker_pool_tx).await;
+    let second = create_user_worker(&worker_pool_tx).await;
+
+    assert_ne!(first, second);
+
+    pool_termination_token.cancel_and_wait().await;
+    std::fs::remove_dir_all(&dir).unwrap();
+}
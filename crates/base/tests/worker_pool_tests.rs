@@ -0,0 +1,342 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use base::rt_worker::supervisor::strategy_per_worker::PerWorkerStrategy;
+use base::rt_worker::supervisor::{Arguments, SupervisorStrategy};
+use base::rt_worker::worker_ctx::{create_user_worker_pool, TerminationToken};
+use base::rt_worker::worker_pool::{SupervisorPolicy, WorkerPoolPolicy};
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::{
+    RequestCompletedEvent, RequestStartedEvent, ShutdownReason, WorkerEvents,
+};
+use futures_util::future::BoxFuture;
+use http::{Request, StatusCode};
+use hyper::Body;
+use sb_workers::context::{ResponseEnd, UserWorkerInfo, UserWorkerMsgs, UserWorkerRuntimeOpts};
+use serial_test::serial;
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+use crate::integration_test_helper::{
+    create_pool_user_worker, test_user_runtime_opts, user_worker_opts,
+};
+
+async fn list_user_workers(tx: &mpsc::UnboundedSender<UserWorkerMsgs>) -> Vec<UserWorkerInfo> {
+    let (result_tx, result_rx) = oneshot::channel();
+
+    tx.send(UserWorkerMsgs::List(result_tx)).unwrap();
+    result_rx.await.unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_list_retire_and_terminate() {
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let key = create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v1").await;
+    let workers = list_user_workers(&worker_pool_tx).await;
+
+    assert_eq!(workers.len(), 1);
+    assert_eq!(workers[0].key, key);
+    assert_eq!(workers[0].service_path, "./test_cases/std_user_worker");
+    assert!(!workers[0].is_retired);
+
+    let (stats_tx, stats_rx) = oneshot::channel();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Stats(key, stats_tx))
+        .unwrap();
+
+    assert!(stats_rx.await.unwrap().unwrap().heap_stats.is_some());
+
+    let (retire_tx, retire_rx) = oneshot::channel();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Retire(key, retire_tx))
+        .unwrap();
+
+    retire_rx.await.unwrap().unwrap();
+
+    assert!(list_user_workers(&worker_pool_tx).await[0].is_retired);
+    assert_ne!(
+        create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v1").await,
+        key
+    );
+
+    let (terminate_tx, terminate_rx) = oneshot::channel();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Terminate(Uuid::new_v4(), terminate_tx))
+        .unwrap();
+
+    assert!(terminate_rx.await.unwrap().is_err());
+
+    let (terminate_tx, terminate_rx) = oneshot::channel();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Terminate(key, terminate_tx))
+        .unwrap();
+
+    terminate_rx.await.unwrap().unwrap();
+
+    while list_user_workers(&worker_pool_tx)
+        .await
+        .iter()
+        .any(|it| it.key == key)
+    {
+        tokio::task::yield_now().await;
+    }
+
+    pool_termination_token.cancel_and_wait().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_request_timeout() {
+    let pool_termination_token = TerminationToken::new();
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        Some(events_tx),
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let (result_tx, result_rx) = oneshot::channel();
+    let opts = user_worker_opts(
+        "./test_cases/request_timeout",
+        UserWorkerRuntimeOpts {
+            request_timeout_ms: 500,
+            ..test_user_runtime_opts()
+        },
+    );
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Create(opts, result_tx))
+        .unwrap();
+
+    let key = result_rx.await.unwrap().unwrap().key;
+    let send_request = |path: &str| {
+        let (res_tx, res_rx) = oneshot::channel();
+        let req = Request::builder()
+            .uri(path)
+            .method("GET")
+            .body(Body::empty())
+            .unwrap();
+
+        worker_pool_tx
+            .send(UserWorkerMsgs::SendRequest(key, req, res_tx, None))
+            .unwrap();
+
+        res_rx
+    };
+
+    let slow_res_rx = send_request("/request_timeout");
+    let fast_res_rx = send_request("/request_timeout/fast");
+
+    // the other request on the same worker is not affected
+    let (res, req_end_tx) = fast_res_rx.await.unwrap().unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        hyper::body::to_bytes(res.into_body()).await.unwrap(),
+        "fast"
+    );
+
+    let _ = req_end_tx.send(ResponseEnd::default());
+    let (res, req_end_tx) = slow_res_rx.await.unwrap().unwrap();
+
+    assert_eq!(res.status(), StatusCode::GATEWAY_TIMEOUT);
+
+    let _ = req_end_tx.send(ResponseEnd::default());
+
+    // the handler of the timed out request sees it as aborted
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let WorkerEvents::Log(event) = events_rx.recv().await.unwrap().event {
+                if event.msg.contains("request aborted") {
+                    break;
+                }
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    pool_termination_token.cancel_and_wait().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_request_cpu_time_header() {
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let (result_tx, result_rx) = oneshot::channel();
+    let opts = user_worker_opts(
+        "./test_cases/slow_resp",
+        UserWorkerRuntimeOpts {
+            cpu_time_header: Some("x-cpu-time-ms".to_string()),
+            ..test_user_runtime_opts()
+        },
+    );
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Create(opts, result_tx))
+        .unwrap();
+
+    let key = result_rx.await.unwrap().unwrap().key;
+    let (res_tx, res_rx) = oneshot::channel();
+    let req = Request::builder()
+        .uri("/slow_resp")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::SendRequest(key, req, res_tx, None))
+        .unwrap();
+
+    let (res, req_end_tx) = res_rx.await.unwrap().unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res
+        .headers()
+        .get("x-cpu-time-ms")
+        .and_then(|it| it.to_str().ok())
+        .and_then(|it| it.parse::<u64>().ok())
+        .is_some());
+
+    let _ = req_end_tx.send(ResponseEnd::default());
+
+    pool_termination_token.cancel_and_wait().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_request_events() {
+    let pool_termination_token = TerminationToken::new();
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        Some(events_tx),
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let (result_tx, result_rx) = oneshot::channel();
+    let opts = user_worker_opts("./test_cases/slow_resp", test_user_runtime_opts());
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::Create(opts, result_tx))
+        .unwrap();
+
+    let key = result_rx.await.unwrap().unwrap().key;
+    let (res_tx, res_rx) = oneshot::channel();
+    let req = Request::builder()
+        .uri("/slow_resp")
+        .method("POST")
+        .body(Body::from("hello world"))
+        .unwrap();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::SendRequest(key, req, res_tx, None))
+        .unwrap();
+
+    let (res, req_end_tx) = res_rx.await.unwrap().unwrap();
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+
+    let _ = req_end_tx.send(ResponseEnd {
+        response_bytes: body.len(),
+        aborted: false,
+    });
+
+    let mut is_started = false;
+
+    loop {
+        let event = events_rx.recv().await.unwrap();
+
+        assert!(event.metadata.execution_id == Some(key));
+
+        match event.event {
+            WorkerEvents::RequestStarted(RequestStartedEvent { method, path }) => {
+                assert_eq!(method, "POST");
+                assert_eq!(path, "/slow_resp");
+                is_started = true;
+            }
+
+            WorkerEvents::RequestCompleted(RequestCompletedEvent {
+                method,
+                path,
+                status,
+                request_bytes,
+                response_bytes,
+                aborted,
+                ..
+            }) => {
+                assert!(is_started);
+                assert_eq!(method, "POST");
+                assert_eq!(path, "/slow_resp");
+                assert_eq!(status, Some(200));
+                assert_eq!(request_bytes, 11);
+                assert_eq!(response_bytes, body.len());
+                assert!(!aborted);
+                break;
+            }
+
+            _ => {}
+        }
+    }
+
+    pool_termination_token.cancel_and_wait().await;
+}
+
+struct CountingStrategy(Arc<AtomicUsize>);
+
+impl SupervisorStrategy for CountingStrategy {
+    fn supervise(&self, args: Arguments) -> BoxFuture<'static, (ShutdownReason, i64)> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        PerWorkerStrategy.supervise(args)
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_custom_supervisor_strategy() {
+    let supervised = Arc::new(AtomicUsize::new(0));
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600)
+            .with_supervisor_strategy(
+                Arc::new(CountingStrategy(supervised.clone())) as Arc<dyn SupervisorStrategy>
+            ),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v1").await;
+    pool_termination_token.cancel_and_wait().await;
+
+    assert_eq!(supervised.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,115 @@
+#[path = "../src/utils/integration_test_helper.rs"]
+mod integration_test_helper;
+
+use std::time::Duration;
+
+use base::rt_worker::worker_ctx::{create_user_worker_pool, TerminationToken};
+use base::rt_worker::worker_pool::{SupervisorPolicy, WorkerPoolPolicy};
+use event_worker::channel::{event_channel, EventChannelOpts};
+use event_worker::events::{ShutdownReason, WorkerEvents};
+use http::{Request, StatusCode};
+use hyper::Body;
+use sb_workers::context::{ResponseEnd, UserWorkerMsgs};
+use serial_test::serial;
+use tokio::sync::oneshot;
+
+use crate::integration_test_helper::create_pool_user_worker;
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_routes_to_newest_version() {
+    let pool_termination_token = TerminationToken::new();
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        None,
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let v1 = create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v1").await;
+
+    assert_eq!(
+        create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v1").await,
+        v1
+    );
+
+    let v2 = create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v2").await;
+
+    assert_ne!(v1, v2);
+    assert_eq!(
+        create_pool_user_worker(&worker_pool_tx, "./test_cases/std_user_worker", "v2").await,
+        v2
+    );
+
+    pool_termination_token.cancel_and_wait().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn test_worker_pool_drains_outdated_version() {
+    let pool_termination_token = TerminationToken::new();
+    let (events_tx, mut events_rx) = event_channel(EventChannelOpts::default());
+    let (_, worker_pool_tx) = create_user_worker_pool(
+        WorkerPoolPolicy::new(SupervisorPolicy::PerWorker, 2, 4 * 1000 * 3600),
+        Some(events_tx),
+        Some(pool_termination_token.clone()),
+    )
+    .await
+    .unwrap();
+
+    let v1 = create_pool_user_worker(&worker_pool_tx, "./test_cases/slow_handler", "v1").await;
+    let (res_tx, res_rx) = oneshot::channel();
+    let req = Request::builder()
+        .uri("/slow_handler")
+        .method("GET")
+        .body(Body::empty())
+        .unwrap();
+
+    worker_pool_tx
+        .send(UserWorkerMsgs::SendRequest(v1, req, res_tx, None))
+        .unwrap();
+
+    let v2 = create_pool_user_worker(&worker_pool_tx, "./test_cases/slow_handler", "v2").await;
+
+    assert_ne!(v1, v2);
+
+    // the in-flight request of the outdated worker is served to the end
+    let (res, req_end_tx) = res_rx.await.unwrap().unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        hyper::body::to_bytes(res.into_body()).await.unwrap(),
+        "meow"
+    );
+
+    while let Ok(Some(event)) = tokio::time::timeout(Duration::ZERO, events_rx.recv()).await {
+        assert!(
+            !(event.metadata.execution_id == Some(v1)
+                && matches!(event.event, WorkerEvents::Shutdown(_))),
+            "outdated worker must not shut down before its requests are finished"
+        );
+    }
+
+    let _ = req_end_tx.send(ResponseEnd::default());
+
+    // ...and shuts down once it's done with it
+    let shutdown = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let event = events_rx.recv().await.unwrap();
+
+            if event.metadata.execution_id != Some(v1) {
+                continue;
+            }
+            if let WorkerEvents::Shutdown(shutdown) = event.event {
+                break shutdown;
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(shutdown.reason, ShutdownReason::EarlyDrop);
+
+    pool_termination_token.cancel_and_wait().await;
+}
@@ -3,14 +3,21 @@ mod logger;
 use anyhow::{anyhow, bail, Error};
 use base::commands::start_server;
 use base::deno_runtime::MAYBE_DENO_VERSION;
+use base::event_sinks::{EventSinkConfig, FileSinkConfig, OtlpSinkConfig};
+use base::otel::{init_tracing, TracingConfig};
+use base::rt_worker::boot_circuit_breaker::BootCircuitBreakerPolicy;
 use base::rt_worker::worker_pool::{SupervisorPolicy, WorkerPoolPolicy};
 use base::server::WorkerEntrypoints;
+use base::utils::units::mib_to_bytes;
 use clap::builder::{FalseyValueParser, TypedValueParser};
 use clap::{arg, crate_version, value_parser, ArgAction, Command};
 use deno_core::url::Url;
+use event_worker::channel::{EventChannelOpts, EventDropPolicy};
+use sb_core::permissions::FsAccessRules;
 use sb_graph::emitter::EmitterFactory;
 use sb_graph::import_map::load_import_map;
 use sb_graph::{extract_from_file, generate_binary_eszip};
+use sb_workers::context::HeapSnapshotOpts;
 use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
@@ -77,6 +84,112 @@ fn cli() -> Command {
                     arg!(--"request-wait-timeout" <MILLISECONDS> "Maximum time in milliseconds that can wait to establish a connection with a worker")
                     .value_parser(value_parser!(u64))
                 )
+                .arg(
+                    arg!(--"worker-pool-config" <Path> "Path to a TOML or JSON file that maps service paths to user worker limits (reloaded on change)")
+                    .value_parser(value_parser!(PathBuf))
+                )
+                .arg(
+                    arg!(--"boot-failure-threshold" <COUNT> "Quarantine a service after this many boot failures within the boot failure window")
+                    .value_parser(value_parser!(usize))
+                )
+                .arg(
+                    arg!(--"boot-failure-window" <MILLISECONDS> "Time window in milliseconds in which boot failures of a service are counted")
+                    .value_parser(value_parser!(u64))
+                    .requires("boot-failure-threshold")
+                )
+                .arg(
+                    arg!(--"boot-failure-cooldown" <MILLISECONDS> "Time in milliseconds a quarantined service fails fast before a boot is attempted again")
+                    .value_parser(value_parser!(u64))
+                    .requires("boot-failure-threshold")
+                )
+                .arg(
+                    arg!(--"heap-snapshot-dir" <DIR> "Directory to write a heap snapshot into when a user worker reaches its memory limit")
+                    .value_parser(value_parser!(PathBuf))
+                )
+                .arg(
+                    arg!(--"heap-snapshot-max-size" <MIB> "Maximum size of a heap snapshot in MiB. Larger snapshots are discarded")
+                    .value_parser(value_parser!(u64))
+                    .default_value("256")
+                    .requires("heap-snapshot-dir")
+                )
+                .arg(
+                    arg!(--"heap-snapshot-interval" <MILLISECONDS> "Minimum time in milliseconds between two heap snapshots")
+                    .value_parser(value_parser!(u64))
+                    .default_value("60000")
+                    .requires("heap-snapshot-dir")
+                )
+                .arg(
+                    arg!(--"event-sink-stdout" "Write the worker events to stdout as JSON lines")
+                    .action(ArgAction::SetTrue)
+                )
+                .arg(
+                    arg!(--"event-sink-file" <PATH> "Write the worker events to the given file as JSON lines")
+                    .value_parser(value_parser!(PathBuf))
+                )
+                .arg(
+                    arg!(--"event-sink-file-max-size" <MIB> "Size in MiB at which the event sink file is rotated (0 = never)")
+                    .value_parser(value_parser!(u64))
+                    .default_value("100")
+                    .requires("event-sink-file")
+                )
+                .arg(
+                    arg!(--"event-sink-file-max-files" <COUNT> "Number of rotated event sink files to keep")
+                    .value_parser(value_parser!(usize))
+                    .default_value("5")
+                    .requires("event-sink-file")
+                )
+                .arg(
+                    arg!(--"event-sink-otlp-endpoint" <URL> "Export the worker events as OTLP logs to the given endpoint (e.g. http://localhost:4318/v1/logs)")
+                    .value_parser(value_parser!(Url))
+                )
+                .arg(
+                    arg!(--"event-sink-otlp-header" <HEADER> "Header sent to the OTLP endpoint, in the form of 'name=value'")
+                    .action(ArgAction::Append)
+                    .requires("event-sink-otlp-endpoint")
+                )
+                .arg(
+                    arg!(--"otel-traces-endpoint" <URL> "Export the request traces over OTLP to the given endpoint (e.g. http://localhost:4318/v1/traces)")
+                    .value_parser(value_parser!(Url))
+                )
+                .arg(
+                    arg!(--"otel-traces-header" <HEADER> "Header sent to the OTLP traces endpoint, in the form of 'name=value'")
+                    .action(ArgAction::Append)
+                    .requires("otel-traces-endpoint")
+                )
+                .arg(
+                    arg!(--"events-channel-capacity" <COUNT> "Maximum number of worker events queued for the events worker and the event sinks")
+                    .value_parser(value_parser!(usize))
+                    .default_value("10000")
+                )
+                .arg(
+                    arg!(--"events-drop-policy" <POLICY> "What to do with worker events once the event channel is full")
+                    .value_parser(["drop-oldest", "drop-newest", "block-logs"])
+                    .default_value("drop-oldest")
+                )
+                .arg(
+                    arg!(--"events-drop-report-interval" <MILLISECONDS> "Interval at which the number of dropped worker events is reported (0 = never)")
+                    .value_parser(value_parser!(u64))
+                    .default_value("10000")
+                )
+                .arg(
+                    arg!(--"events-block-timeout" <MILLISECONDS> "Longest time a worker is blocked on emitting a log with the block-logs policy, after which the log is dropped")
+                    .value_parser(value_parser!(u64))
+                    .default_value("1000")
+                )
+                .arg(
+                    arg!(--"cpu-profile-dir" <DIR> "Directory to write the CPU profiles of user workers into. Enables CPU profiling of user workers")
+                    .value_parser(value_parser!(PathBuf))
+                )
+                .arg(
+                    arg!(--"main-allow-read" <PATH> "Path the main worker may read from. The main worker can read any path if omitted")
+                    .value_parser(value_parser!(PathBuf))
+                    .action(ArgAction::Append)
+                )
+                .arg(
+                    arg!(--"main-allow-write" <PATH> "Path the main worker may write to. The main worker can write to any path if omitted")
+                    .value_parser(value_parser!(PathBuf))
+                    .action(ArgAction::Append)
+                )
         )
         .subcommand(
             Command::new("bundle")
@@ -150,24 +263,162 @@ fn main() -> Result<(), anyhow::Error> {
                     sub_matches.get_one::<usize>("max-parallelism").cloned();
                 let maybe_request_wait_timeout =
                     sub_matches.get_one::<u64>("request-wait-timeout").cloned();
+                let maybe_worker_pool_config_path = sub_matches
+                    .get_one::<PathBuf>("worker-pool-config")
+                    .cloned();
+                let maybe_boot_circuit_breaker = sub_matches
+                    .get_one::<usize>("boot-failure-threshold")
+                    .cloned()
+                    .map(|failure_threshold| {
+                        let default = BootCircuitBreakerPolicy::default();
+
+                        BootCircuitBreakerPolicy {
+                            failure_threshold,
+                            window_ms: sub_matches
+                                .get_one::<u64>("boot-failure-window")
+                                .cloned()
+                                .unwrap_or(default.window_ms),
+                            cooldown_ms: sub_matches
+                                .get_one::<u64>("boot-failure-cooldown")
+                                .cloned()
+                                .unwrap_or(default.cooldown_ms),
+                        }
+                    });
+                let maybe_heap_snapshot = sub_matches
+                    .get_one::<PathBuf>("heap-snapshot-dir")
+                    .cloned()
+                    .map(|dir| HeapSnapshotOpts {
+                        dir,
+                        max_size_bytes: mib_to_bytes(
+                            sub_matches
+                                .get_one::<u64>("heap-snapshot-max-size")
+                                .cloned()
+                                .unwrap(),
+                        ) as usize,
+                        min_interval_ms: sub_matches
+                            .get_one::<u64>("heap-snapshot-interval")
+                            .cloned()
+                            .unwrap(),
+                    });
+
+                let maybe_cpu_profile_dir =
+                    sub_matches.get_one::<PathBuf>("cpu-profile-dir").cloned();
+
+                let main_fs_access_rules = FsAccessRules::new(
+                    sub_matches
+                        .get_many::<PathBuf>("main-allow-read")
+                        .map(|it| it.cloned().collect()),
+                    sub_matches
+                        .get_many::<PathBuf>("main-allow-write")
+                        .map(|it| it.cloned().collect()),
+                );
+
+                let mut event_sinks = vec![];
+
+                if sub_matches.get_flag("event-sink-stdout") {
+                    event_sinks.push(EventSinkConfig::Stdout);
+                }
+                if let Some(path) = sub_matches.get_one::<PathBuf>("event-sink-file").cloned() {
+                    event_sinks.push(EventSinkConfig::File(FileSinkConfig {
+                        path,
+                        max_size_bytes: mib_to_bytes(
+                            sub_matches
+                                .get_one::<u64>("event-sink-file-max-size")
+                                .cloned()
+                                .unwrap(),
+                        ),
+                        max_files: sub_matches
+                            .get_one::<usize>("event-sink-file-max-files")
+                            .cloned()
+                            .unwrap(),
+                    }));
+                }
+                if let Some(endpoint) = sub_matches
+                    .get_one::<Url>("event-sink-otlp-endpoint")
+                    .cloned()
+                {
+                    let mut config = OtlpSinkConfig::new(endpoint);
+
+                    for header in sub_matches
+                        .get_many::<String>("event-sink-otlp-header")
+                        .unwrap_or_default()
+                    {
+                        let Some((name, value)) = header.split_once('=') else {
+                            bail!("invalid otlp header: {}", header);
+                        };
+
+                        config
+                            .headers
+                            .push((name.trim().into(), value.trim().into()));
+                    }
+
+                    event_sinks.push(EventSinkConfig::Otlp(config));
+                }
+
+                if let Some(endpoint) = sub_matches.get_one::<Url>("otel-traces-endpoint").cloned()
+                {
+                    let mut config = TracingConfig::new(endpoint);
+
+                    for header in sub_matches
+                        .get_many::<String>("otel-traces-header")
+                        .unwrap_or_default()
+                    {
+                        let Some((name, value)) = header.split_once('=') else {
+                            bail!("invalid otlp header: {}", header);
+                        };
+
+                        config
+                            .headers
+                            .push((name.trim().into(), value.trim().into()));
+                    }
+
+                    init_tracing(config)?;
+                }
+
+                let events_channel_opts = EventChannelOpts {
+                    capacity: sub_matches
+                        .get_one::<usize>("events-channel-capacity")
+                        .cloned()
+                        .unwrap(),
+                    policy: sub_matches
+                        .get_one::<String>("events-drop-policy")
+                        .unwrap()
+                        .parse::<EventDropPolicy>()?,
+                    drop_report_interval_ms: sub_matches
+                        .get_one::<u64>("events-drop-report-interval")
+                        .cloned()
+                        .unwrap(),
+                    block_timeout_ms: sub_matches
+                        .get_one::<u64>("events-block-timeout")
+                        .cloned()
+                        .unwrap(),
+                };
 
                 start_server(
                     ip.as_str(),
                     port,
                     main_service_path,
                     event_service_manager_path,
-                    Some(WorkerPoolPolicy::new(
-                        maybe_supervisor_policy,
-                        if let Some(true) = maybe_supervisor_policy
-                            .as_ref()
-                            .map(SupervisorPolicy::is_oneshot)
-                        {
-                            Some(1)
-                        } else {
-                            maybe_max_parallelism
-                        },
-                        maybe_request_wait_timeout,
-                    )),
+                    event_sinks,
+                    events_channel_opts,
+                    Some(
+                        WorkerPoolPolicy::new(
+                            maybe_supervisor_policy,
+                            if let Some(true) = maybe_supervisor_policy
+                                .as_ref()
+                                .map(SupervisorPolicy::is_oneshot)
+                            {
+                                Some(1)
+                            } else {
+                                maybe_max_parallelism
+                            },
+                            maybe_request_wait_timeout,
+                        )
+                        .with_config_path(maybe_worker_pool_config_path)
+                        .with_boot_circuit_breaker(maybe_boot_circuit_breaker)
+                        .with_heap_snapshot(maybe_heap_snapshot)
+                        .with_cpu_profile_dir(maybe_cpu_profile_dir),
+                    ),
                     import_map_path,
                     no_module_cache,
                     None,
@@ -175,6 +426,7 @@ fn main() -> Result<(), anyhow::Error> {
                         main: maybe_main_entrypoint,
                         events: maybe_events_entrypoint,
                     },
+                    main_fs_access_rules,
                     None,
                 )
                 .await?;
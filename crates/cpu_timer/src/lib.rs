@@ -133,14 +133,32 @@ impl CPUTimer {
     #[cfg(target_os = "linux")]
     pub fn reset(&self) -> Result<(), Error> {
         use anyhow::Context;
-        use linux::*;
 
         let timer = self.timer.try_lock().context("failed to get the lock")?;
 
-        let initial_expiry_secs = timer.initial_expiry / 1000;
-        let initial_expiry_msecs = timer.initial_expiry % 1000;
-        let interval_secs = timer.interval / 1000;
-        let interval_msecs = timer.interval % 1000;
+        Self::set_time(&timer, timer.initial_expiry, timer.interval)
+    }
+
+    /// Re-arms the timer to fire only once, after the thread has spent another
+    /// `expiry` ms of CPU time. The initial expiry and interval given at start
+    /// no longer apply until the next `reset`.
+    #[cfg(target_os = "linux")]
+    pub fn reset_once(&self, expiry: u64) -> Result<(), Error> {
+        use anyhow::Context;
+
+        let timer = self.timer.try_lock().context("failed to get the lock")?;
+
+        Self::set_time(&timer, expiry, 0)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_time(timer: &CPUTimerVal, initial_expiry: u64, interval: u64) -> Result<(), Error> {
+        use linux::*;
+
+        let initial_expiry_secs = initial_expiry / 1000;
+        let initial_expiry_msecs = initial_expiry % 1000;
+        let interval_secs = interval / 1000;
+        let interval_msecs = interval % 1000;
         let mut tmspec: libc::itimerspec = unsafe { std::mem::zeroed() };
 
         tmspec.it_value.tv_sec = initial_expiry_secs as i64;
@@ -169,6 +187,11 @@ impl CPUTimer {
     pub fn reset(&self) -> Result<(), Error> {
         Ok(())
     }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn reset_once(&self, _: u64) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub fn get_thread_time() -> Result<i64, Error> {
@@ -0,0 +1,358 @@
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Error};
+use tokio::sync::Notify;
+
+use crate::events::{EventMetadata, EventsDroppedEvent, WorkerEventWithMetadata, WorkerEvents};
+
+/// What a sender does when the channel is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EventDropPolicy {
+    /// Evicts the oldest queued event to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Discards the new event.
+    DropNewest,
+    /// Blocks the worker emitting a log until there is room again, for at most
+    /// `EventChannelOpts::block_timeout_ms`. Any other event, and a log that
+    /// is still blocked after the timeout, is discarded like with
+    /// `DropNewest`.
+    BlockLogs,
+}
+
+impl FromStr for EventDropPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "drop-oldest" => Self::DropOldest,
+            "drop-newest" => Self::DropNewest,
+            "block-logs" => Self::BlockLogs,
+            _ => bail!("unknown event drop policy: {}", s),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventChannelOpts {
+    pub capacity: usize,
+    pub policy: EventDropPolicy,
+    /// Interval of the `EventsDropped` reports emitted by the receiver
+    /// (0 = never reported).
+    pub drop_report_interval_ms: u64,
+    /// Longest time a log is blocked on with `EventDropPolicy::BlockLogs`.
+    pub block_timeout_ms: u64,
+}
+
+impl Default for EventChannelOpts {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            policy: EventDropPolicy::default(),
+            drop_report_interval_ms: 10_000,
+            block_timeout_ms: 1000,
+        }
+    }
+}
+
+/// Counters of the event pipeline, shared with the metric source.
+#[derive(Debug, Clone, Default)]
+pub struct EventChannelCounters {
+    /// Events discarded because the channel was full.
+    pub dropped_events: Arc<AtomicUsize>,
+    /// Events discarded because the channel was full, by service path.
+    pub dropped_events_by_service: Arc<Mutex<HashMap<String, usize>>>,
+    /// Number of times the consumer (i.e. the events worker) was restarted.
+    pub consumer_restarts: Arc<AtomicUsize>,
+    /// Logs that had to wait for room in the channel (see
+    /// `EventDropPolicy::BlockLogs`).
+    pub blocked_logs: Arc<AtomicUsize>,
+    /// Events that none of the subscriptions of the events worker matched.
+    pub unsubscribed_events: Arc<AtomicUsize>,
+}
+
+impl EventChannelCounters {
+    /// Counts an event discarded because the channel was full.
+    pub fn record_drop(&self, metadata: &EventMetadata) {
+        self.dropped_events.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(service_path) = metadata.service_path.as_ref() {
+            *self
+                .dropped_events_by_service
+                .lock()
+                .unwrap()
+                .entry(service_path.clone())
+                .or_default() += 1;
+        }
+    }
+}
+
+/// Receiver shared by the successive instances of the events worker, so the
+/// queued events outlive a crashed instance.
+pub type SharedEventReceiver = Arc<tokio::sync::Mutex<EventReceiver>>;
+
+#[derive(Debug)]
+struct State {
+    queue: VecDeque<WorkerEventWithMetadata>,
+    // dropped events by service path since the last report
+    dropped: HashMap<Option<String>, u64>,
+    senders: usize,
+    is_receiver_dropped: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    opts: EventChannelOpts,
+    state: Mutex<State>,
+    not_full: Condvar,
+    notify: Notify,
+    counters: EventChannelCounters,
+}
+
+impl Shared {
+    fn record_drop(&self, state: &mut State, metadata: &EventMetadata) {
+        *state
+            .dropped
+            .entry(metadata.service_path.clone())
+            .or_default() += 1;
+
+        self.counters.record_drop(metadata);
+    }
+}
+
+/// Creates a bounded channel for the worker events. Unlike `mpsc::channel`,
+/// sending never requires an async context and a full channel is handled
+/// according to `EventChannelOpts::policy`.
+pub fn event_channel(opts: EventChannelOpts) -> (EventSender, EventReceiver) {
+    event_channel_with_counters(opts, EventChannelCounters::default())
+}
+
+/// Same as `event_channel`, but reports to the given counters (e.g. the ones
+/// of the downstream channel).
+pub fn event_channel_with_counters(
+    opts: EventChannelOpts,
+    counters: EventChannelCounters,
+) -> (EventSender, EventReceiver) {
+    let next_report_at = (opts.drop_report_interval_ms > 0)
+        .then(|| Instant::now() + Duration::from_millis(opts.drop_report_interval_ms));
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::with_capacity(opts.capacity.clamp(1, 1024)),
+            dropped: HashMap::new(),
+            senders: 1,
+            is_receiver_dropped: false,
+        }),
+        opts,
+        not_full: Condvar::new(),
+        notify: Notify::new(),
+        counters,
+    });
+
+    (
+        EventSender {
+            shared: shared.clone(),
+        },
+        EventReceiver {
+            shared,
+            next_report_at,
+            reports: VecDeque::new(),
+        },
+    )
+}
+
+#[derive(Debug)]
+pub struct EventSender {
+    shared: Arc<Shared>,
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.senders -= 1;
+
+        if state.senders == 0 {
+            drop(state);
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+impl EventSender {
+    /// Queues the event. An event discarded because the channel is full is
+    /// not an error; it is only counted. Fails if the receiver is gone.
+    pub fn send(&self, event: WorkerEventWithMetadata) -> Result<(), Error> {
+        let shared = &self.shared;
+        let capacity = shared.opts.capacity.max(1);
+        let mut state = shared.state.lock().unwrap();
+
+        if state.is_receiver_dropped {
+            bail!("event receiver is closed");
+        }
+
+        if state.queue.len() >= capacity {
+            match shared.opts.policy {
+                EventDropPolicy::DropOldest => {
+                    if let Some(evicted) = state.queue.pop_front() {
+                        shared.record_drop(&mut state, &evicted.metadata);
+                    }
+                }
+
+                EventDropPolicy::BlockLogs if matches!(event.event, WorkerEvents::Log(_)) => {
+                    let deadline =
+                        Instant::now() + Duration::from_millis(shared.opts.block_timeout_ms);
+
+                    shared.counters.blocked_logs.fetch_add(1, Ordering::Relaxed);
+
+                    while state.queue.len() >= capacity && !state.is_receiver_dropped {
+                        let timeout = deadline.saturating_duration_since(Instant::now());
+
+                        if timeout.is_zero() {
+                            break;
+                        }
+
+                        state = shared.not_full.wait_timeout(state, timeout).unwrap().0;
+                    }
+
+                    if state.is_receiver_dropped {
+                        bail!("event receiver is closed");
+                    }
+
+                    // NOTE: The consumer is stuck; dropping the log is better
+                    // than stalling the worker indefinitely.
+                    if state.queue.len() >= capacity {
+                        shared.record_drop(&mut state, &event.metadata);
+                        return Ok(());
+                    }
+                }
+
+                EventDropPolicy::DropNewest | EventDropPolicy::BlockLogs => {
+                    shared.record_drop(&mut state, &event.metadata);
+                    return Ok(());
+                }
+            }
+        }
+
+        state.queue.push_back(event);
+        drop(state);
+        shared.notify.notify_one();
+
+        Ok(())
+    }
+
+    pub fn counters(&self) -> EventChannelCounters {
+        self.shared.counters.clone()
+    }
+}
+
+enum Next {
+    Event(WorkerEventWithMetadata),
+    Wait,
+    Closed,
+}
+
+#[derive(Debug)]
+pub struct EventReceiver {
+    shared: Arc<Shared>,
+    next_report_at: Option<Instant>,
+    reports: VecDeque<WorkerEventWithMetadata>,
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().is_receiver_dropped = true;
+        self.shared.not_full.notify_all();
+    }
+}
+
+impl EventReceiver {
+    pub fn counters(&self) -> EventChannelCounters {
+        self.shared.counters.clone()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.opts.capacity.max(1)
+    }
+
+    /// Receives the next event. The `EventsDropped` reports are interleaved
+    /// with the queued events. Returns `None` once all senders are dropped and
+    /// the queue is drained.
+    pub async fn recv(&mut self) -> Option<WorkerEventWithMetadata> {
+        loop {
+            match self.next() {
+                Next::Event(event) => return Some(event),
+                Next::Closed => return None,
+                Next::Wait => match self.next_report_at {
+                    Some(at) => {
+                        tokio::select! {
+                            _ = self.shared.notify.notified() => {}
+                            _ = tokio::time::sleep_until(at.into()) => {}
+                        }
+                    }
+
+                    None => self.shared.notify.notified().await,
+                },
+            }
+        }
+    }
+
+    fn next(&mut self) -> Next {
+        if let Some(report) = self.reports.pop_front() {
+            return Next::Event(report);
+        }
+
+        let mut state = self.shared.state.lock().unwrap();
+
+        if let Some(at) = self.next_report_at {
+            let now = Instant::now();
+
+            if at <= now {
+                self.next_report_at =
+                    Some(now + Duration::from_millis(self.shared.opts.drop_report_interval_ms));
+
+                self.reports
+                    .extend(state.dropped.drain().map(|(service_path, count)| {
+                        WorkerEventWithMetadata {
+                            event: WorkerEvents::EventsDropped(EventsDroppedEvent { count }),
+                            metadata: EventMetadata {
+                                service_path,
+                                ..Default::default()
+                            },
+                        }
+                    }));
+
+                if let Some(report) = self.reports.pop_front() {
+                    return Next::Event(report);
+                }
+            }
+        }
+
+        if let Some(event) = state.queue.pop_front() {
+            drop(state);
+            self.shared.not_full.notify_one();
+
+            return Next::Event(event);
+        }
+
+        if state.senders == 0 {
+            Next::Closed
+        } else {
+            Next::Wait
+        }
+    }
+}
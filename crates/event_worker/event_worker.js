@@ -1,14 +1,37 @@
 import { primordials, core } from "ext:core/mod.js";
 const { SymbolAsyncIterator } = primordials;
 
-const { op_event_accept } = core.ensureFastOps()
+const { op_event_filter_create, op_event_accept } = core.ensureFastOps()
 
 class SupabaseEventListener {
+	#filterRid;
+	#closed = false;
+
+	/**
+	 * @param {{ eventTypes?: string[], servicePaths?: string[] }} [opts]
+	 * Only the events of the given types and coming from the service paths
+	 * matching one of the patterns (`*` is a wildcard) are yielded.
+	 */
+	constructor(opts = {}) {
+		this.#filterRid = op_event_filter_create({
+			eventTypes: opts.eventTypes ?? null,
+			servicePaths: opts.servicePaths ?? null,
+		});
+	}
+
 	async nextEvent() {
+		if (this.#closed) {
+			return { value: undefined, done: true };
+		}
+
 		try {
-			const reqEvt = await op_event_accept();
+			const reqEvt = await op_event_accept(this.#filterRid);
 			const done = reqEvt === 'Done';
 
+			if (done) {
+				this.close();
+			}
+
 			let value = undefined;
 			if (!done) {
 				const rawEvent = reqEvt['Event'];
@@ -28,6 +51,14 @@ class SupabaseEventListener {
 		}
 	}
 
+	/** Stops the subscription. Pending and future events are discarded. */
+	close() {
+		if (!this.#closed) {
+			this.#closed = true;
+			core.tryClose(this.#filterRid);
+		}
+	}
+
 	[SymbolAsyncIterator]() {
 		const scopedClass = this;
 
@@ -35,6 +66,10 @@ class SupabaseEventListener {
 			async next() {
 				return await scopedClass.nextEvent();
 			},
+			async return() {
+				scopedClass.close();
+				return { value: undefined, done: true };
+			},
 		};
 	}
 }
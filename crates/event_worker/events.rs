@@ -1,26 +1,29 @@
+use std::path::PathBuf;
+
+use deno_core::serde_json::{Map, Value};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PseudoEvent {}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BootEvent {
     pub boot_time: usize,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BootFailureEvent {
     pub msg: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct WorkerMemoryUsed {
     pub total: usize,
     pub heap: usize,
     pub external: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ShutdownReason {
     WallClockTime,
     CPUTime,
@@ -29,34 +32,120 @@ pub enum ShutdownReason {
     TerminationRequested,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ShutdownEvent {
     pub reason: ShutdownReason,
     pub cpu_time_used: usize,
     pub memory_used: WorkerMemoryUsed,
+    /// Highest memory usage observed by the supervisor over the lifetime of
+    /// the worker. Each field is tracked independently.
+    pub memory_high_water_mark: WorkerMemoryUsed,
+    /// Heap snapshot written before the worker was terminated due to the
+    /// memory limit.
+    pub heap_snapshot: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ResourceSampleEvent {
+    pub cpu_time_used: usize,
+    pub memory_used: WorkerMemoryUsed,
+    pub inflight_requests: usize,
+    pub served_requests: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RequestStartedEvent {
+    pub method: String,
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RequestCompletedEvent {
+    pub method: String,
+    pub path: String,
+    /// `None` if the worker failed to respond.
+    pub status: Option<u16>,
+    pub duration_ms: usize,
+    pub request_bytes: usize,
+    pub response_bytes: usize,
+    pub cpu_time_used: usize,
+    /// Whether the client went away before the response was fully sent.
+    pub aborted: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct UncaughtExceptionEvent {
     pub exception: String,
     pub cpu_time_used: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LogEvent {
     pub msg: String,
     pub level: LogLevel,
+    /// Where the log was emitted from in the user code, if known.
+    #[serde(default)]
+    pub location: Option<LogLocation>,
+    /// Object arguments of the console call, when structured logs are enabled
+    /// for the worker. `_truncated` is set if some fields were left out.
+    #[serde(default)]
+    pub fields: Option<Map<String, Value>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
+    #[default]
+    #[serde(alias = "debug")]
     Debug,
+    #[serde(alias = "info")]
     Info,
+    #[serde(alias = "warn", alias = "warning")]
     Warning,
+    #[serde(alias = "error")]
     Error,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl LogLevel {
+    /// Maps the level passed to the print function of `console.Console`
+    /// (0 = debug, 1 = log/info, 2 = warn, 3 = error).
+    pub fn from_console_level(level: u32) -> Self {
+        match level {
+            0 => Self::Debug,
+            1 => Self::Info,
+            2 => Self::Warning,
+            _ => Self::Error,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogLocation {
+    pub file_name: String,
+    pub line_number: usize,
+    pub column_number: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum BootCircuitBreakerState {
+    Open,
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BootCircuitBreakerEvent {
+    pub state: BootCircuitBreakerState,
+    pub failures: usize,
+    pub error: Option<String>,
+}
+
+/// Number of events of a service that were dropped by a full event channel
+/// since the previous report.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EventsDroppedEvent {
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum WorkerEvents {
     Boot(BootEvent),
     BootFailure(BootFailureEvent),
@@ -64,15 +153,39 @@ pub enum WorkerEvents {
     Shutdown(ShutdownEvent),
     EventLoopCompleted(PseudoEvent),
     Log(LogEvent),
+    BootCircuitBreaker(BootCircuitBreakerEvent),
+    ResourceSample(ResourceSampleEvent),
+    RequestStarted(RequestStartedEvent),
+    RequestCompleted(RequestCompletedEvent),
+    EventsDropped(EventsDroppedEvent),
+}
+
+impl WorkerEvents {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            Self::Boot(_) => "Boot",
+            Self::BootFailure(_) => "BootFailure",
+            Self::UncaughtException(_) => "UncaughtException",
+            Self::Shutdown(_) => "Shutdown",
+            Self::EventLoopCompleted(_) => "EventLoopCompleted",
+            Self::Log(_) => "Log",
+            Self::BootCircuitBreaker(_) => "BootCircuitBreaker",
+            Self::ResourceSample(_) => "ResourceSample",
+            Self::RequestStarted(_) => "RequestStarted",
+            Self::RequestCompleted(_) => "RequestCompleted",
+            Self::EventsDropped(_) => "EventsDropped",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Default, Debug, Clone)]
 pub struct EventMetadata {
     pub service_path: Option<String>,
     pub execution_id: Option<Uuid>,
+    pub version: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct WorkerEventWithMetadata {
     pub event: WorkerEvents,
     pub metadata: EventMetadata,
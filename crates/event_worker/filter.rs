@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashSet, VecDeque};
+
+use deno_core::Resource;
+use serde::Deserialize;
+use tokio::sync::Notify;
+
+use crate::channel::EventReceiver;
+use crate::events::WorkerEventWithMetadata;
+
+/// Filter of an `EventManager`. Events that don't match are discarded before
+/// they are serialized into the isolate.
+#[derive(Deserialize, Default, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventFilter {
+    /// Accepted event types, e.g. `Shutdown` (`None` = any).
+    #[serde(default)]
+    pub event_types: Option<HashSet<String>>,
+    /// Accepted service paths. `*` matches any sequence of characters
+    /// (`None` = any).
+    #[serde(default)]
+    pub service_paths: Option<Vec<String>>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &WorkerEventWithMetadata) -> bool {
+        if let Some(event_types) = self.event_types.as_ref() {
+            if !event_types.contains(event.event.event_type()) {
+                return false;
+            }
+        }
+
+        if let Some(patterns) = self.service_paths.as_ref() {
+            let Some(service_path) = event.metadata.service_path.as_deref() else {
+                return false;
+            };
+
+            if !patterns
+                .iter()
+                .any(|pattern| matches_wildcard(pattern, service_path))
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Subscription of an `EventManager`. Every subscription has its own queue,
+/// so that subscriptions with overlapping filters each receive the event.
+#[derive(Debug, Default)]
+pub struct EventSubscription {
+    filter: EventFilter,
+    queue: RefCell<VecDeque<WorkerEventWithMetadata>>,
+    notify: Notify,
+}
+
+impl EventSubscription {
+    pub fn new(filter: EventFilter) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+
+    /// Queues the event if it matches the filter, and tells whether it did.
+    /// The oldest queued event is evicted once the queue holds `capacity`
+    /// events.
+    pub fn offer(&self, event: &WorkerEventWithMetadata, rx: &EventReceiver) -> bool {
+        if !self.filter.matches(event) {
+            return false;
+        }
+
+        let mut queue = self.queue.borrow_mut();
+
+        if queue.len() >= rx.capacity() {
+            if let Some(evicted) = queue.pop_front() {
+                rx.counters().record_drop(&evicted.metadata);
+            }
+        }
+
+        queue.push_back(event.clone());
+        self.notify.notify_one();
+
+        true
+    }
+
+    pub fn pop(&self) -> Option<WorkerEventWithMetadata> {
+        self.queue.borrow_mut().pop_front()
+    }
+
+    pub async fn wait(&self) {
+        self.notify.notified().await;
+    }
+}
+
+impl Resource for EventSubscription {
+    fn name(&self) -> Cow<str> {
+        "eventSubscription".into()
+    }
+}
+
+fn matches_wildcard(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let value = value.as_bytes();
+
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+
+    while v < value.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == value[v] {
+            p += 1;
+            v += 1;
+        } else if let Some((star_p, star_v)) = backtrack {
+            // let the last `*` consume one more character
+            backtrack = Some((star_p, star_v + 1));
+            p = star_p + 1;
+            v = star_v + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|it| *it == b'*')
+}
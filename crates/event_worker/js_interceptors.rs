@@ -1,23 +1,117 @@
-use crate::events::{EventMetadata, LogEvent, LogLevel, WorkerEvents};
-use crate::WorkerEventWithMetadata;
+use crate::channel::EventSender;
+use crate::events::{
+    EventMetadata, LogEvent, LogLevel, LogLocation, WorkerEventWithMetadata, WorkerEvents,
+};
 use deno_core::error::AnyError;
 use deno_core::op2;
+use deno_core::serde_json::{self, Map, Value};
+use deno_core::v8;
 use deno_core::OpState;
 use log::error;
-use tokio::sync::mpsc;
 
-#[op2(fast)]
+/// Logs below this level are dropped by `op_user_worker_log`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MinLogLevel(pub LogLevel);
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Values (e.g. of the secret environment variables) that are replaced with
+/// `[REDACTED]` in the messages and fields of `op_user_worker_log`.
+#[derive(Debug, Clone, Default)]
+pub struct LogRedactions(Vec<String>);
+
+impl LogRedactions {
+    pub fn new(values: impl IntoIterator<Item = String>) -> Self {
+        let mut values = values
+            .into_iter()
+            .filter(|it| !it.is_empty())
+            .collect::<Vec<_>>();
+
+        // replace the longest values first, in case one contains another
+        values.sort_by_key(|it| std::cmp::Reverse(it.len()));
+        values.dedup();
+
+        Self(values)
+    }
+
+    pub fn redact(&self, value: &str) -> String {
+        self.0.iter().fold(value.to_string(), |acc, it| {
+            acc.replace(it.as_str(), REDACTED)
+        })
+    }
+
+    fn redact_json(&self, value: &mut Value) {
+        match value {
+            Value::String(it) => *it = self.redact(it),
+            Value::Array(it) => it.iter_mut().for_each(|it| self.redact_json(it)),
+            Value::Object(it) => it.values_mut().for_each(|it| self.redact_json(it)),
+            _ => {}
+        }
+    }
+}
+
+// Number of frames inspected to skip past the console implementation.
+const MAX_LOCATION_FRAMES: usize = 16;
+
+fn log_location(scope: &mut v8::HandleScope) -> Option<LogLocation> {
+    let stack_trace = v8::StackTrace::current_stack_trace(scope, MAX_LOCATION_FRAMES)?;
+
+    for idx in 0..stack_trace.get_frame_count() {
+        let Some(frame) = stack_trace.get_frame(scope, idx) else {
+            continue;
+        };
+
+        let Some(file_name) = frame
+            .get_script_name_or_source_url(scope)
+            .map(|it| it.to_rust_string_lossy(scope))
+        else {
+            continue;
+        };
+
+        if file_name.is_empty() || file_name.starts_with("ext:") || file_name.starts_with("node:") {
+            continue;
+        }
+
+        return Some(LogLocation {
+            file_name,
+            line_number: frame.get_line_number(),
+            column_number: frame.get_column(),
+        });
+    }
+
+    None
+}
+
+#[op2]
 fn op_user_worker_log(
+    scope: &mut v8::HandleScope,
     state: &mut OpState,
     #[string] msg: &str,
-    is_err: bool,
+    level: u32,
+    #[string] fields: Option<String>,
 ) -> Result<(), AnyError> {
-    let maybe_tx = state.try_borrow::<mpsc::UnboundedSender<WorkerEventWithMetadata>>();
-    let mut level = LogLevel::Info;
-    if is_err {
-        level = LogLevel::Error;
+    let level = LogLevel::from_console_level(level);
+    let min_level = state
+        .try_borrow::<MinLogLevel>()
+        .copied()
+        .unwrap_or_default();
+
+    if level < min_level.0 {
+        return Ok(());
+    }
+
+    let redactions = state.try_borrow::<LogRedactions>();
+    let msg = redactions.map_or_else(|| msg.to_string(), |it| it.redact(msg));
+    let mut fields = fields.and_then(|it| serde_json::from_str::<Map<String, Value>>(&it).ok());
+
+    if let (Some(redactions), Some(fields)) = (redactions, fields.as_mut()) {
+        fields
+            .values_mut()
+            .for_each(|it| redactions.redact_json(it));
     }
 
+    let maybe_tx = state.try_borrow::<EventSender>();
+
     if let Some(tx) = maybe_tx {
         let event_metadata = state
             .try_borrow::<EventMetadata>()
@@ -28,13 +122,15 @@ fn op_user_worker_log(
 
         tx.send(WorkerEventWithMetadata {
             event: WorkerEvents::Log(LogEvent {
-                msg: msg.to_string(),
+                msg,
                 level,
+                location: log_location(scope),
+                fields,
             }),
             metadata,
         })?;
     } else {
-        error!("[{:?}] {}", level, msg.to_string());
+        error!("[{:?}] {}", level, msg);
     }
 
     Ok(())
@@ -1,39 +1,109 @@
-use crate::events::{RawEvent, WorkerEventWithMetadata};
+use crate::channel::SharedEventReceiver;
+use crate::events::RawEvent;
+use crate::filter::{EventFilter, EventSubscription};
 use anyhow::{bail, Error};
 use deno_core::op2;
 use deno_core::OpState;
+use deno_core::ResourceId;
 use std::cell::RefCell;
-use std::rc::Rc;
-use tokio::sync::mpsc;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::Ordering;
 
+pub mod channel;
 pub mod events;
+pub mod filter;
 pub mod js_interceptors;
 
+/// Subscriptions of the `EventManager`s alive in the events worker.
+#[derive(Default)]
+struct EventSubscriptions(Vec<Weak<EventSubscription>>);
+
+#[op2]
+#[smi]
+fn op_event_filter_create(state: &mut OpState, #[serde] filter: Option<EventFilter>) -> ResourceId {
+    let rid = state
+        .resource_table
+        .add(EventSubscription::new(filter.unwrap_or_default()));
+
+    let subscription = state.resource_table.get::<EventSubscription>(rid).unwrap();
+
+    if !state.has::<EventSubscriptions>() {
+        state.put(EventSubscriptions::default());
+    }
+
+    state
+        .borrow_mut::<EventSubscriptions>()
+        .0
+        .push(Rc::downgrade(&subscription));
+
+    rid
+}
+
 #[op2(async)]
 #[serde]
-async fn op_event_accept(state: Rc<RefCell<OpState>>) -> Result<RawEvent, Error> {
-    let rx = {
-        let mut op_state = state.borrow_mut();
-        op_state.try_take::<mpsc::UnboundedReceiver<WorkerEventWithMetadata>>()
+async fn op_event_accept(
+    state: Rc<RefCell<OpState>>,
+    #[smi] filter_rid: ResourceId,
+) -> Result<RawEvent, Error> {
+    let (rx, subscription) = {
+        let op_state = state.borrow();
+        (
+            op_state.try_borrow::<SharedEventReceiver>().cloned(),
+            op_state
+                .resource_table
+                .get::<EventSubscription>(filter_rid)?,
+        )
     };
     if rx.is_none() {
         bail!("events worker receiver not available")
     }
-    let mut rx = rx.unwrap();
 
-    let data = rx.recv().await;
+    // NOTE: The receiver is shared with the events worker supervisor. It must
+    // not be taken out of the state, otherwise the queued events would be lost
+    // if the worker exits while waiting here.
+    let rx = rx.unwrap();
+
+    loop {
+        if let Some(event) = subscription.pop() {
+            return Ok(RawEvent::Event(event));
+        }
+
+        // NOTE: Whichever subscription holds the receiver hands the events it
+        // receives to all the subscriptions.
+        tokio::select! {
+            _ = subscription.wait() => {}
+            mut rx = rx.lock() => {
+                if let Some(event) = subscription.pop() {
+                    return Ok(RawEvent::Event(event));
+                }
+
+                let Some(event) = rx.recv().await else {
+                    return Ok(RawEvent::Done);
+                };
+
+                let mut op_state = state.borrow_mut();
+                let subscriptions = op_state.borrow_mut::<EventSubscriptions>();
+
+                subscriptions.0.retain(|it| it.strong_count() > 0);
+
+                let mut is_subscribed = false;
 
-    let mut op_state = state.borrow_mut();
-    op_state.put::<mpsc::UnboundedReceiver<WorkerEventWithMetadata>>(rx);
+                for it in subscriptions.0.iter().filter_map(Weak::upgrade) {
+                    is_subscribed |= it.offer(&event, &rx);
+                }
 
-    match data {
-        Some(event) => Ok(RawEvent::Event(event)),
-        None => Ok(RawEvent::Done),
+                if !is_subscribed {
+                    rx.counters()
+                        .unsubscribed_events
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
     }
 }
 
 deno_core::extension!(
     sb_user_event_worker,
-    ops = [op_event_accept],
+    ops = [op_event_filter_create, op_event_accept],
     esm = ["event_worker.js"]
 );
@@ -104,7 +104,7 @@ fn get_hyper_error_class(_error: &hyper::Error) -> &'static str {
 pub fn get_error_class_name(e: &AnyError) -> Option<&'static str> {
     deno_core::error::get_custom_error_class(e)
         .or_else(|| deno_web::get_error_class_name(e))
-        .or_else(|| deno_websocket::get_network_error_class_name(e))
+        .or_else(|| sb_websocket::get_network_error_class_name(e))
         .or_else(|| e.downcast_ref::<hyper::Error>().map(get_hyper_error_class))
         .or_else(|| {
             e.downcast_ref::<Arc<hyper::Error>>()
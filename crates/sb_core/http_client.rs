@@ -0,0 +1,220 @@
+use std::error::Error;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use deno_core::error::{type_error, AnyError};
+use deno_core::url::Url;
+use deno_core::OpState;
+use deno_fetch::reqwest;
+use deno_fetch::reqwest::dns::{Addrs, Resolve, Resolving};
+use deno_fetch::reqwest::header::{HeaderMap, USER_AGENT};
+use deno_fetch::reqwest::redirect::Policy;
+use deno_fetch::reqwest::Proxy;
+use deno_fetch::CreateHttpClientOptions;
+use hyper::client::connect::dns::Name;
+
+use crate::net::resolve_allowed_addrs;
+use crate::permissions::{NetAccessRules, NetRule};
+
+/// Proxy the `fetch` requests and the WebSocket connections of a user worker
+/// go through, in place of the one taken from the environment of the process
+/// (which applies to the module downloads as well).
+///
+/// Both go through the proxy connector of `reqwest` (see
+/// [`create_http_client`]), which tunnels the `https`/`wss` connections with
+/// `CONNECT` and sends the plain `http`/`ws` ones to HTTP(S) proxies as is, so
+/// the proxy has to forward the upgrade of plain WebSocket connections.
+///
+/// NOTE: The destinations reached through the proxy are resolved by the
+/// proxy, so only their names are checked against the net access rules (e.g.
+/// `deny_private_net`); the proxy is expected to enforce its own egress
+/// policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// `http`, `https`, `socks5` or `socks5h` url of the proxy.
+    pub url: Url,
+    /// Destinations which are connected to directly, in the same syntax as
+    /// the net access rules (e.g. `*.internal`, `10.0.0.0/8`).
+    pub no_proxy: Vec<NetRule>,
+}
+
+impl ProxyConfig {
+    pub fn parse(url: &str, no_proxy: &[String]) -> Result<Self, AnyError> {
+        let invalid = || type_error(format!("invalid proxy url: {}", url));
+        let url = Url::parse(url).map_err(|_| invalid())?;
+
+        if !matches!(url.scheme(), "http" | "https" | "socks5" | "socks5h")
+            || url.host_str().is_none()
+        {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            url,
+            no_proxy: no_proxy
+                .iter()
+                .map(|it| NetRule::parse(it))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Whether requests to the url go through the proxy.
+    pub fn applies_to(&self, url: &Url) -> bool {
+        let host = url
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_lowercase();
+        let ip = host.parse::<IpAddr>().ok();
+        let port = url.port_or_known_default();
+
+        !self.no_proxy.iter().any(|it| it.matches(&host, ip, port))
+    }
+
+    /// Rejects the rules which check the resolved addresses along with a
+    /// `socks5` proxy, to which `reqwest` sends the addresses it resolved
+    /// without going through the resolver that checks them.
+    pub fn check_net_access_rules(&self, rules: &NetAccessRules) -> Result<(), AnyError> {
+        if self.url.scheme() == "socks5" && rules.checks_resolved_addrs() {
+            return Err(type_error(
+                "a socks5 proxy can't be used along with rules that check the resolved addresses \
+                 (e.g. denyPrivateNet), use socks5h instead",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves the hosts of `fetch` requests and drops the addresses which are
+/// not allowed, so that a host can't be rebound to a private address after
+/// its name was checked.
+///
+/// NOTE: The host of the proxy is trusted, as it is configured by the
+/// operator rather than the user code. Destinations reached through the
+/// proxy are resolved by the proxy, so only their names are checked.
+struct NetAccessResolver {
+    rules: Arc<NetAccessRules>,
+    proxy_host: Option<String>,
+}
+
+impl Resolve for NetAccessResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let rules = if self.proxy_host.as_deref() == Some(name.as_str()) {
+            Arc::new(NetAccessRules::default())
+        } else {
+            self.rules.clone()
+        };
+
+        Box::pin(async move {
+            // NOTE: The port is not known here, it is set by the connector.
+            let addrs: Addrs = Box::new(
+                resolve_allowed_addrs(&rules, name.as_str(), None)
+                    .await?
+                    .into_iter(),
+            );
+
+            Ok::<_, Box<dyn Error + Send + Sync>>(addrs)
+        })
+    }
+}
+
+/// Options of the default client of `deno_fetch`, as it creates them from the
+/// options of the extension.
+pub fn default_http_client_options(state: &OpState) -> Result<CreateHttpClientOptions, AnyError> {
+    let options = state.borrow::<deno_fetch::Options>();
+
+    Ok(CreateHttpClientOptions {
+        root_cert_store: options.root_cert_store()?,
+        ca_certs: vec![],
+        proxy: options.proxy.clone(),
+        unsafely_ignore_certificate_errors: options.unsafely_ignore_certificate_errors.clone(),
+        client_cert_chain_and_key: options.client_cert_chain_and_key.clone(),
+        pool_max_idle_per_host: None,
+        pool_idle_timeout: None,
+        http1: true,
+        http2: true,
+    })
+}
+
+/// Creates a client the same as `deno_fetch::create_http_client` does, except
+/// that the hosts are resolved by a resolver which checks the addresses
+/// against the net access rules, and that the proxy of the worker (if any)
+/// takes precedence over the one of the options.
+pub fn create_http_client(
+    user_agent: &str,
+    options: CreateHttpClientOptions,
+    net_access_rules: NetAccessRules,
+    proxy: Option<ProxyConfig>,
+) -> Result<reqwest::Client, AnyError> {
+    let mut tls_config = deno_tls::create_client_config(
+        options.root_cert_store,
+        options.ca_certs,
+        options.unsafely_ignore_certificate_errors,
+        options.client_cert_chain_and_key,
+        deno_tls::SocketUse::Http,
+    )?;
+
+    let mut alpn_protocols = vec![];
+    if options.http2 {
+        alpn_protocols.push("h2".into());
+    }
+    if options.http1 {
+        alpn_protocols.push("http/1.1".into());
+    }
+    tls_config.alpn_protocols = alpn_protocols;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, user_agent.parse()?);
+
+    let proxy_host = match (proxy.as_ref(), options.proxy.as_ref()) {
+        (Some(proxy), _) => proxy.url.host_str().map(str::to_string),
+        (None, Some(proxy)) => Url::parse(&proxy.url)
+            .ok()
+            .and_then(|it| it.host_str().map(str::to_string)),
+        (None, None) => None,
+    };
+    let mut builder = reqwest::Client::builder()
+        .redirect(Policy::none())
+        .default_headers(headers)
+        .use_preconfigured_tls(tls_config)
+        .dns_resolver(Arc::new(NetAccessResolver {
+            rules: Arc::new(net_access_rules),
+            proxy_host,
+        }));
+
+    // NOTE: Setting a proxy disables the ones taken from the environment.
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::custom(move |url| {
+            proxy.applies_to(url).then(|| proxy.url.clone())
+        }));
+    } else if let Some(proxy) = options.proxy {
+        let mut reqwest_proxy = Proxy::all(&proxy.url)?;
+
+        if let Some(basic_auth) = &proxy.basic_auth {
+            reqwest_proxy = reqwest_proxy.basic_auth(&basic_auth.username, &basic_auth.password);
+        }
+
+        builder = builder.proxy(reqwest_proxy);
+    }
+
+    if let Some(pool_max_idle_per_host) = options.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+    }
+
+    if let Some(pool_idle_timeout) = options.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(pool_idle_timeout.map(Duration::from_millis));
+    }
+
+    match (options.http1, options.http2) {
+        (true, false) => builder = builder.http1_only(),
+        (false, true) => builder = builder.http2_prior_knowledge(),
+        (true, true) => {}
+        (false, false) => return Err(type_error("Either `http1` or `http2` needs to be true")),
+    }
+
+    builder.build().map_err(|err| err.into())
+}
@@ -36,8 +36,10 @@ import {
 	setNumCpus,
 	setUserAgent,
 } from 'ext:sb_core_main_js/js/navigator.js';
+import { toLogFields } from 'ext:sb_core_main_js/js/logFields.js';
+import { withTraceparent } from 'ext:sb_core_main_js/js/traceContext.js';
 import { promiseRejectMacrotaskCallback } from 'ext:sb_core_main_js/js/promises.js';
-import { denoOverrides, fsVars } from 'ext:sb_core_main_js/js/denoOverrides.js';
+import { denoOverrides } from 'ext:sb_core_main_js/js/denoOverrides.js';
 import * as performance from 'ext:deno_web/15_performance.js';
 import * as messagePort from 'ext:deno_web/13_message_port.js';
 import { SupabaseEventListener } from 'ext:sb_user_event_worker/event_worker.js';
@@ -362,16 +364,72 @@ globalThis.bootstrapSBEdge = (
 		delete globalThis.EdgeRuntime;
 
 		// override console
+		let pendingLogFields = null;
+		const userConsole = new console.Console((msg, level) => {
+			const fields = pendingLogFields;
+
+			pendingLogFields = null;
+			return ops.op_user_worker_log(msg, level, fields);
+		});
+
+		if (opts.structuredLogs) {
+			// attach the object arguments as JSON fields to the log event
+			for (const method of ['log', 'debug', 'info', 'warn', 'error']) {
+				const print = userConsole[method];
+
+				userConsole[method] = (...args) => {
+					pendingLogFields = toLogFields(args);
+
+					try {
+						print(...args);
+					} finally {
+						pendingLogFields = null;
+					}
+				};
+			}
+		}
+
 		ObjectDefineProperties(globalThis, {
-			console: nonEnumerable(
-				new console.Console((msg, level) => {
-					return ops.op_user_worker_log(msg, level > 1);
-				}),
-			),
+			console: nonEnumerable(userConsole),
 		});
 
-		// remove all fs APIs except Deno.cwd
-		deleteDenoApis(Object.keys(fsVars).filter((k) => k !== 'cwd'));
+		if (opts.tracing) {
+			ObjectDefineProperties(globalThis, {
+				fetch: writable(withTraceparent(fetch.fetch)),
+			});
+		}
+
+		// fs access is checked against the allowed paths of the worker, but
+		// the working directory is shared by the whole process
+		deleteDenoApis(['chdir']);
+
+		// give the user code a chance to flush its state before the supervisor
+		// terminates the worker
+		const beforeunload = ops.op_user_worker_wait_for_beforeunload();
+
+		core.unrefOpPromise(beforeunload);
+		beforeunload.then(async (reason) => {
+			if (reason === null) {
+				return;
+			}
+
+			const pending = [];
+
+			try {
+				globalThis.dispatchEvent(
+					new event.CustomEvent('beforeunload', {
+						detail: {
+							reason,
+							waitUntil: (promise) => pending.push(promise),
+						},
+					}),
+				);
+
+				await Promise.allSettled(pending);
+			} finally {
+				ops.op_user_worker_beforeunload_done();
+			}
+		});
 	}
 
 	if (isEventsWorker) {
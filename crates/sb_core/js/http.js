@@ -1,6 +1,7 @@
 import { HttpConn } from 'ext:deno_http/01_http.js';
 import { RequestPrototype } from 'ext:deno_fetch/23_request.js';
 import { core, primordials } from "ext:core/mod.js";
+import { enterRequest } from 'ext:sb_core_main_js/js/traceContext.js';
 
 const { internalRidSymbol } = core;
 const { ObjectPrototypeIsPrototypeOf } = primordials;
@@ -54,6 +55,17 @@ function serveHttp(conn) {
 
 		nextRequest.request[watcher] = watcherRid;
 
+		const leaveRequest = enterRequest(nextRequest.request);
+		const respondWith = nextRequest.respondWith;
+
+		nextRequest.respondWith = async (resp) => {
+			try {
+				return await respondWith(resp);
+			} finally {
+				leaveRequest();
+			}
+		};
+
 		return nextRequest;
 	};
 
@@ -0,0 +1,147 @@
+import { primordials } from 'ext:core/mod.js';
+
+const {
+	ArrayIsArray,
+	ArrayPrototypeMap,
+	JSONStringify,
+	ObjectKeys,
+	ObjectPrototypeIsPrototypeOf,
+	ErrorPrototype,
+	MapPrototypeSet,
+	SafeMap,
+	SafeWeakSet,
+	WeakSetPrototypeAdd,
+	WeakSetPrototypeDelete,
+	WeakSetPrototypeHas,
+} = primordials;
+
+// Budget of the serialized fields of a single log, in characters. Fields that
+// don't fit are left out and `_truncated` is set instead.
+const MAX_LOG_FIELDS_SIZE = 16 * 1024;
+const MAX_LOG_FIELDS_DEPTH = 8;
+
+function toJsonSafe(value, depth, ancestors) {
+	switch (typeof value) {
+		case 'bigint':
+			return value.toString();
+		case 'function':
+		case 'symbol':
+		case 'undefined':
+			return undefined;
+		case 'object':
+			break;
+		default:
+			return value;
+	}
+
+	if (value === null) {
+		return null;
+	}
+	if (WeakSetPrototypeHas(ancestors, value)) {
+		return '[Circular]';
+	}
+	if (depth >= MAX_LOG_FIELDS_DEPTH) {
+		return ArrayIsArray(value) ? '[Array]' : '[Object]';
+	}
+	if (ObjectPrototypeIsPrototypeOf(ErrorPrototype, value)) {
+		return { name: value.name, message: value.message, stack: value.stack };
+	}
+	if (typeof value.toJSON === 'function') {
+		return toJsonSafe(value.toJSON(), depth, ancestors);
+	}
+
+	WeakSetPrototypeAdd(ancestors, value);
+
+	try {
+		if (ArrayIsArray(value)) {
+			return ArrayPrototypeMap(
+				value,
+				(it) => toJsonSafe(it, depth + 1, ancestors) ?? null,
+			);
+		}
+
+		const result = {};
+
+		for (const key of ObjectKeys(value)) {
+			const field = toJsonSafe(value[key], depth + 1, ancestors);
+
+			if (field !== undefined) {
+				result[key] = field;
+			}
+		}
+
+		return result;
+	} finally {
+		WeakSetPrototypeDelete(ancestors, value);
+	}
+}
+
+function isFieldSource(arg) {
+	return arg !== null && typeof arg === 'object' && !ArrayIsArray(arg) &&
+		!ObjectPrototypeIsPrototypeOf(ErrorPrototype, arg);
+}
+
+/**
+ * Merges the own enumerable properties of the object arguments of a console
+ * call into a JSON object. Returns `null` if there are no such arguments.
+ */
+function toLogFields(args) {
+	const fields = new SafeMap();
+	let size = 2; // {}
+	let hasFields = false;
+	let isTruncated = false;
+
+	for (const arg of args) {
+		if (!isFieldSource(arg)) {
+			continue;
+		}
+
+		hasFields = true;
+
+		for (const key of ObjectKeys(arg)) {
+			let value;
+
+			try {
+				value = JSONStringify(
+					toJsonSafe(arg[key], 1, new SafeWeakSet([arg])),
+				);
+			} catch {
+				value = JSONStringify('[Unserializable]');
+			}
+
+			if (value === undefined) {
+				continue;
+			}
+
+			const entrySize = JSONStringify(key).length + value.length + 2;
+
+			if (size + entrySize > MAX_LOG_FIELDS_SIZE) {
+				isTruncated = true;
+				continue;
+			}
+
+			MapPrototypeSet(fields, key, value);
+			size += entrySize;
+		}
+	}
+
+	if (!hasFields) {
+		return null;
+	}
+
+	let json = '{';
+	let isFirst = true;
+
+	for (const { 0: key, 1: value } of fields) {
+		json += `${isFirst ? '' : ','}${JSONStringify(key)}:${value}`;
+		isFirst = false;
+	}
+
+	if (isTruncated) {
+		json += `${isFirst ? '' : ','}"_truncated":true`;
+	}
+
+	return json + '}';
+}
+
+export { toLogFields };
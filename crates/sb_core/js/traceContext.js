@@ -0,0 +1,61 @@
+import { primordials } from 'ext:core/mod.js';
+import { Headers } from 'ext:deno_fetch/20_headers.js';
+import { RequestPrototype } from 'ext:deno_fetch/23_request.js';
+
+const { ObjectPrototypeIsPrototypeOf, SafeMap } = primordials;
+
+// trace context of the requests being served, by request
+const inflightRequests = new SafeMap();
+
+/**
+ * Tracks the trace context of the request until the returned function is
+ * called, i.e. until the request has been responded to.
+ */
+function enterRequest(request) {
+	inflightRequests.set(request, request.headers.get('traceparent'));
+	return () => inflightRequests.delete(request);
+}
+
+// NOTE: Without an async context, the request an outbound request belongs to
+// can only be told when the worker serves a single request at a time. While
+// several requests are served concurrently, the trace context must be passed
+// explicitly with the `traceContext` option.
+function implicitTraceparent() {
+	if (inflightRequests.size !== 1) {
+		return null;
+	}
+
+	const [traceparent] = inflightRequests.values();
+	return traceparent;
+}
+
+/**
+ * Wraps `fetch` so that the outbound requests carry the trace context of the
+ * request being served, unless they set their own. The request can be given
+ * explicitly, e.g. `fetch(url, { traceContext: req })`.
+ */
+function withTraceparent(fetch) {
+	return function fetchWithTraceparent(input, init = undefined) {
+		const traceparent = init?.traceContext
+			? init.traceContext.headers.get('traceparent')
+			: implicitTraceparent();
+
+		if (traceparent === null) {
+			return fetch(input, init);
+		}
+
+		const headers = new Headers(
+			init?.headers ??
+				(ObjectPrototypeIsPrototypeOf(RequestPrototype, input) ? input.headers : undefined),
+		);
+
+		if (headers.has('traceparent')) {
+			return fetch(input, init);
+		}
+
+		headers.set('traceparent', traceparent);
+		return fetch(input, { ...init, headers });
+	};
+}
+
+export { enterRequest, withTraceparent };
@@ -1,7 +1,8 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use deno_core::error::AnyError;
 use deno_core::v8::IsolateHandle;
@@ -9,7 +10,6 @@ use deno_core::OpState;
 use deno_core::{op2, JsRuntime};
 use enum_as_inner::EnumAsInner;
 use futures::task::AtomicWaker;
-use futures::FutureExt;
 use log::error;
 use serde::Serialize;
 use tokio::sync::oneshot;
@@ -22,22 +22,43 @@ pub mod emit;
 pub mod errors_rt;
 pub mod external_memory;
 pub mod file_fetcher;
+pub mod http_client;
 pub mod http_start;
 pub mod net;
 pub mod permissions;
 pub mod runtime;
 pub mod transpiler;
 pub mod util;
+pub mod ws;
 
 #[derive(Debug, Default, Clone)]
 pub struct SharedMetricSource {
     active_user_workers: Arc<AtomicUsize>,
     retired_user_workers: Arc<AtomicUsize>,
+    drained_user_workers: Arc<AtomicUsize>,
     received_requests: Arc<AtomicUsize>,
     handled_requests: Arc<AtomicUsize>,
+    dropped_events: Arc<AtomicUsize>,
+    dropped_events_by_service: Arc<Mutex<HashMap<String, usize>>>,
+    events_worker_restarts: Arc<AtomicUsize>,
+    active_user_worker_versions: Arc<Mutex<HashMap<String, usize>>>,
 }
 
 impl SharedMetricSource {
+    /// Uses the counters maintained by the event channel for the dropped
+    /// worker events and the restarts of the events worker.
+    pub fn with_event_channel_counters(
+        mut self,
+        dropped_events: Arc<AtomicUsize>,
+        dropped_events_by_service: Arc<Mutex<HashMap<String, usize>>>,
+        events_worker_restarts: Arc<AtomicUsize>,
+    ) -> Self {
+        self.dropped_events = dropped_events;
+        self.dropped_events_by_service = dropped_events_by_service;
+        self.events_worker_restarts = events_worker_restarts;
+        self
+    }
+
     pub fn incl_active_user_workers(&self) {
         self.active_user_workers.fetch_add(1, Ordering::Relaxed);
     }
@@ -50,6 +71,28 @@ impl SharedMetricSource {
         self.retired_user_workers.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn incl_drained_user_worker(&self) {
+        self.drained_user_workers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn incl_active_user_worker_version(&self, version: &str) {
+        let mut versions = self.active_user_worker_versions.lock().unwrap();
+
+        *versions.entry(version.to_string()).or_default() += 1;
+    }
+
+    pub fn decl_active_user_worker_version(&self, version: &str) {
+        let mut versions = self.active_user_worker_versions.lock().unwrap();
+
+        if let Some(count) = versions.get_mut(version) {
+            *count -= 1;
+
+            if *count == 0 {
+                versions.remove(version);
+            }
+        }
+    }
+
     pub fn incl_received_requests(&self) {
         self.received_requests.fetch_add(1, Ordering::Relaxed);
     }
@@ -61,8 +104,13 @@ impl SharedMetricSource {
     pub fn reset(&self) {
         self.active_user_workers.store(0, Ordering::Relaxed);
         self.retired_user_workers.store(0, Ordering::Relaxed);
+        self.drained_user_workers.store(0, Ordering::Relaxed);
         self.received_requests.store(0, Ordering::Relaxed);
         self.handled_requests.store(0, Ordering::Relaxed);
+        self.dropped_events.store(0, Ordering::Relaxed);
+        self.dropped_events_by_service.lock().unwrap().clear();
+        self.events_worker_restarts.store(0, Ordering::Relaxed);
+        self.active_user_worker_versions.lock().unwrap().clear();
     }
 }
 
@@ -96,29 +144,10 @@ impl WorkerMetricSource {
 
         Self { handle, waker }
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct RuntimeMetricSource {
-    pub main: WorkerMetricSource,
-    pub event: Option<WorkerMetricSource>,
-    pub shared: SharedMetricSource,
-}
 
-impl RuntimeMetricSource {
-    pub fn new(
-        main: WorkerMetricSource,
-        maybe_event: Option<WorkerMetricSource>,
-        maybe_shared: Option<SharedMetricSource>,
-    ) -> Self {
-        Self {
-            main,
-            event: maybe_event,
-            shared: maybe_shared.unwrap_or_default(),
-        }
-    }
-
-    async fn get_heap_statistics(&mut self) -> RuntimeHeapStatistics {
+    /// Requests the heap statistics from the isolate. Returns `None` if the
+    /// isolate has already been dropped.
+    pub async fn get_heap_statistics(&self) -> Option<WorkerHeapStatistics> {
         #[repr(C)]
         struct InterruptData {
             heap_tx: oneshot::Sender<WorkerHeapStatistics>,
@@ -150,44 +179,61 @@ impl RuntimeMetricSource {
             }
         }
 
-        let request_heap_statistics_fn = |arg: Option<&mut WorkerMetricSource>| {
-            let Some(source) = arg else {
-                return async { None::<WorkerHeapStatistics> }.boxed();
-            };
-
+        let rx = {
             let (tx, rx) = oneshot::channel::<WorkerHeapStatistics>();
             let data_ptr_mut = Box::into_raw(Box::new(InterruptData { heap_tx: tx }));
 
-            if !source
+            if !self
                 .handle
                 .request_interrupt(interrupt_fn, data_ptr_mut as *mut std::ffi::c_void)
             {
                 drop(unsafe { Box::from_raw(data_ptr_mut) });
-                return async { None }.boxed();
+                return None;
             }
 
-            let waker = source.waker.clone();
-
-            async move {
-                waker.wake();
-                rx.await.ok()
-            }
-            .boxed()
+            rx
         };
 
+        self.waker.wake();
+        rx.await.ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeMetricSource {
+    pub main: WorkerMetricSource,
+    pub event: Option<WorkerMetricSource>,
+    pub shared: SharedMetricSource,
+}
+
+impl RuntimeMetricSource {
+    pub fn new(
+        main: WorkerMetricSource,
+        maybe_event: Option<WorkerMetricSource>,
+        maybe_shared: Option<SharedMetricSource>,
+    ) -> Self {
+        Self {
+            main,
+            event: maybe_event,
+            shared: maybe_shared.unwrap_or_default(),
+        }
+    }
+
+    async fn get_heap_statistics(&mut self) -> RuntimeHeapStatistics {
         RuntimeHeapStatistics {
-            main_worker_heap_stats: request_heap_statistics_fn(Some(&mut self.main))
-                .await
-                .unwrap_or_default(),
+            main_worker_heap_stats: self.main.get_heap_statistics().await.unwrap_or_default(),
 
-            event_worker_heap_stats: request_heap_statistics_fn(self.event.as_mut()).await,
+            event_worker_heap_stats: match self.event.as_ref() {
+                Some(source) => source.get_heap_statistics().await,
+                None => None,
+            },
         }
     }
 }
 
 #[derive(Debug, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
-struct WorkerHeapStatistics {
+pub struct WorkerHeapStatistics {
     total_heap_size: usize,
     total_heap_executable: usize,
     total_physical_size: usize,
@@ -212,8 +258,13 @@ struct RuntimeHeapStatistics {
 struct RuntimeSharedStatistics {
     active_user_workers_count: usize,
     retired_user_workers_count: usize,
+    drained_user_workers_count: usize,
     received_requests_count: usize,
     handled_requests_count: usize,
+    dropped_events_count: usize,
+    dropped_events_by_service: HashMap<String, usize>,
+    events_worker_restarts_count: usize,
+    active_user_worker_versions: HashMap<String, usize>,
 }
 
 impl RuntimeSharedStatistics {
@@ -221,8 +272,13 @@ impl RuntimeSharedStatistics {
         Self {
             active_user_workers_count: src.active_user_workers.load(Ordering::Relaxed),
             retired_user_workers_count: src.retired_user_workers.load(Ordering::Relaxed),
+            drained_user_workers_count: src.drained_user_workers.load(Ordering::Relaxed),
             received_requests_count: src.received_requests.load(Ordering::Relaxed),
             handled_requests_count: src.handled_requests.load(Ordering::Relaxed),
+            dropped_events_count: src.dropped_events.load(Ordering::Relaxed),
+            dropped_events_by_service: src.dropped_events_by_service.lock().unwrap().clone(),
+            events_worker_restarts_count: src.events_worker_restarts.load(Ordering::Relaxed),
+            active_user_worker_versions: src.active_user_worker_versions.lock().unwrap().clone(),
         }
     }
 }
@@ -297,6 +353,8 @@ deno_core::extension!(
         "js/permissions.js",
         "js/errors.js",
         "js/fieldUtils.js",
+        "js/logFields.js",
+        "js/traceContext.js",
         "js/promises.js",
         "js/http.js",
         "js/denoOverrides.js",
@@ -1,5 +1,7 @@
 use anyhow::Error;
 use deno_core::error::bad_resource;
+use deno_core::error::invalid_hostname;
+use deno_core::error::type_error;
 use deno_core::error::AnyError;
 use deno_core::op2;
 use deno_core::AsyncRefCell;
@@ -13,17 +15,33 @@ use deno_core::Resource;
 use deno_core::ResourceId;
 use deno_net::io::UnixStreamResource;
 use deno_net::ops::IpAddr;
+use deno_net::ops_tls::TlsStream;
+use deno_net::ops_tls::TlsStreamResource;
+use deno_net::DefaultTlsOptions;
+use deno_net::UnsafelyIgnoreCertificateErrors;
+use deno_tls::rustls::ServerName;
+use deno_tls::SocketUse;
+use serde::Deserialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::os::fd::AsRawFd;
 use std::os::fd::RawFd;
+use std::path::Path;
 use std::rc::Rc;
+use std::sync::Arc;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
 use tokio::sync::mpsc;
 use tokio::sync::watch;
 
 use crate::conn_sync::ConnSync;
+use crate::permissions::NetAccessRules;
+use crate::permissions::Permissions;
+
+/// Same as the buffer size of the TLS streams of `deno_net`.
+const TLS_BUFFER_SIZE: Option<NonZeroUsize> = NonZeroUsize::new(65536);
 
 pub struct TcpStreamResource {
     rd: AsyncRefCell<tokio::net::tcp::OwnedReadHalf>,
@@ -154,6 +172,191 @@ pub async fn op_net_accept(
     ))
 }
 
+/// Resolves the host and drops the addresses which are not allowed by the
+/// rules, so the caller only ever connects to vetted addresses.
+/// A missing `port` stands for any port.
+pub async fn resolve_allowed_addrs(
+    rules: &NetAccessRules,
+    host: &str,
+    port: Option<u16>,
+) -> Result<Vec<SocketAddr>, AnyError> {
+    let addrs = deno_net::resolve_addr::resolve_addr(host, port.unwrap_or(0))
+        .await?
+        .collect::<Vec<_>>();
+
+    if addrs.is_empty() {
+        return Err(deno_core::error::generic_error(format!(
+            "could not resolve {}",
+            host
+        )));
+    }
+
+    let allowed = addrs
+        .into_iter()
+        .filter(|it| rules.is_allowed_addr(&it.ip(), port))
+        .collect::<Vec<_>>();
+
+    if allowed.is_empty() {
+        return Err(deno_core::error::custom_error(
+            "PermissionDenied",
+            format!("net access to {} is not allowed", host),
+        ));
+    }
+
+    Ok(allowed)
+}
+
+fn net_access_rules(state: &Rc<RefCell<OpState>>) -> NetAccessRules {
+    state
+        .borrow()
+        .borrow::<Permissions>()
+        .net_access_rules()
+        .clone()
+}
+
+/// Replaces `op_net_connect_tcp` of `deno_net` to connect to the address
+/// that was checked, rather than resolving the host again.
+#[op2(async)]
+#[serde]
+pub async fn op_net_connect_tcp(
+    state: Rc<RefCell<OpState>>,
+    #[serde] addr: IpAddr,
+) -> Result<(ResourceId, IpAddr, IpAddr), AnyError> {
+    {
+        let mut s = state.borrow_mut();
+        deno_net::NetPermissions::check_net(
+            s.borrow_mut::<Permissions>(),
+            &(&addr.hostname, Some(addr.port)),
+            "Deno.connect()",
+        )?;
+    }
+
+    let rules = net_access_rules(&state);
+    let addrs = resolve_allowed_addrs(&rules, &addr.hostname, Some(addr.port)).await?;
+    let tcp_stream = tokio::net::TcpStream::connect(&addrs[..]).await?;
+    let local_addr = tcp_stream.local_addr()?;
+    let remote_addr = tcp_stream.peer_addr()?;
+
+    let rid = state
+        .borrow_mut()
+        .resource_table
+        .add(deno_net::io::TcpStreamResource::new(
+            tcp_stream.into_split(),
+        ));
+
+    Ok((rid, IpAddr::from(local_addr), IpAddr::from(remote_addr)))
+}
+
+/// Same as `ConnectTlsArgs` of `deno_net`, whose fields are private.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectTlsArgs {
+    cert_file: Option<String>,
+    ca_certs: Vec<String>,
+    cert_chain: Option<String>,
+    private_key: Option<String>,
+    alpn_protocols: Option<Vec<String>>,
+}
+
+/// Replaces `op_net_connect_tls` of `deno_net` to connect to the address
+/// that was checked, rather than resolving the host again.
+#[op2(async)]
+#[serde]
+pub async fn op_net_connect_tls(
+    state: Rc<RefCell<OpState>>,
+    #[serde] addr: IpAddr,
+    #[serde] args: ConnectTlsArgs,
+) -> Result<(ResourceId, IpAddr, IpAddr), AnyError> {
+    let cert_file = args.cert_file.as_deref();
+    let unsafely_ignore_certificate_errors = state
+        .borrow()
+        .try_borrow::<UnsafelyIgnoreCertificateErrors>()
+        .and_then(|it| it.0.clone());
+
+    {
+        let mut s = state.borrow_mut();
+        let permissions = s.borrow_mut::<Permissions>();
+
+        deno_net::NetPermissions::check_net(
+            permissions,
+            &(&addr.hostname, Some(addr.port)),
+            "Deno.connectTls()",
+        )?;
+
+        if let Some(path) = cert_file {
+            deno_net::NetPermissions::check_read(
+                permissions,
+                Path::new(path),
+                "Deno.connectTls()",
+            )?;
+        }
+    }
+
+    let mut ca_certs = args
+        .ca_certs
+        .into_iter()
+        .map(|it| it.into_bytes())
+        .collect::<Vec<_>>();
+
+    if let Some(path) = cert_file {
+        ca_certs.push(tokio::fs::read(path).await?);
+    }
+
+    let root_cert_store = state
+        .borrow()
+        .borrow::<DefaultTlsOptions>()
+        .root_cert_store()?;
+    let server_name =
+        ServerName::try_from(&*addr.hostname).map_err(|_| invalid_hostname(&addr.hostname))?;
+
+    let cert_chain_and_key = if args.cert_chain.is_some() || args.private_key.is_some() {
+        let cert_chain = args
+            .cert_chain
+            .ok_or_else(|| type_error("No certificate chain provided"))?;
+        let private_key = args
+            .private_key
+            .ok_or_else(|| type_error("No private key provided"))?;
+
+        Some((cert_chain, private_key))
+    } else {
+        None
+    };
+
+    let mut tls_config = deno_tls::create_client_config(
+        root_cert_store,
+        ca_certs,
+        unsafely_ignore_certificate_errors,
+        cert_chain_and_key,
+        SocketUse::GeneralSsl,
+    )?;
+
+    if let Some(alpn_protocols) = args.alpn_protocols {
+        tls_config.alpn_protocols = alpn_protocols
+            .into_iter()
+            .map(|it| it.into_bytes())
+            .collect();
+    }
+
+    let rules = net_access_rules(&state);
+    let addrs = resolve_allowed_addrs(&rules, &addr.hostname, Some(addr.port)).await?;
+    let tcp_stream = tokio::net::TcpStream::connect(&addrs[..]).await?;
+    let local_addr = tcp_stream.local_addr()?;
+    let remote_addr = tcp_stream.peer_addr()?;
+    let tls_stream = TlsStream::new_client_side(
+        tcp_stream,
+        Arc::new(tls_config),
+        server_name,
+        TLS_BUFFER_SIZE,
+    );
+
+    let rid = state
+        .borrow_mut()
+        .resource_table
+        .add(TlsStreamResource::new(tls_stream.into_split()));
+
+    Ok((rid, IpAddr::from(local_addr), IpAddr::from(remote_addr)))
+}
+
 // TODO: This should be a global ext
 #[op2(fast)]
 pub fn op_net_unsupported(_state: &mut OpState) -> Result<(), AnyError> {
@@ -166,6 +369,10 @@ deno_core::extension!(
         "op_net_listen_tcp" => op_net_listen::DECL,
         "op_net_accept_tcp" => op_net_accept::DECL,
 
+        // check the resolved addresses of outbound connections
+        "op_net_connect_tcp" => op_net_connect_tcp::DECL,
+        "op_net_connect_tls" => op_net_connect_tls::DECL,
+
         // disable listening on TLS, UDP and Unix sockets
         "op_net_listen_tls" => op_net_unsupported::DECL,
         "op_net_listen_udp" => op_net_unsupported::DECL,
@@ -1,33 +1,532 @@
-use deno_core::error::{custom_error, AnyError};
-use deno_core::url::Url;
+use deno_core::error::{custom_error, type_error, AnyError};
+use deno_core::normalize_path;
+use deno_core::url::{Host, Url};
 use deno_fs::OpenOptions;
-use std::path::Path;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPattern {
+    Any,
+    Name(String),
+    /// `*.example.com`, matches the subdomains but not the domain itself.
+    Subdomains(String),
+    Cidr(IpAddr, u8),
+}
+
+/// A host (name, `*.` wildcard domain, IP or CIDR) with an optional port,
+/// e.g. `api.example.com`, `*.example.com:443`, `10.0.0.0/8`, `[::1]:8080` or
+/// `*:443`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetRule {
+    host: HostPattern,
+    port: Option<u16>,
+}
+
+impl NetRule {
+    pub fn parse(value: &str) -> Result<Self, AnyError> {
+        let invalid = || type_error(format!("invalid net access rule: {}", value));
+        let value_lower = value.trim().to_lowercase();
+
+        let (host, port) = if let Some(rest) = value_lower.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        } else if value_lower.matches(':').count() > 1 {
+            // bare IPv6
+            (value_lower.as_str(), None)
+        } else {
+            match value_lower.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (value_lower.as_str(), None),
+            }
+        };
+
+        let port = port
+            .map(|it| it.parse::<u16>().map_err(|_| invalid()))
+            .transpose()?;
+
+        let host = if host == "*" {
+            HostPattern::Any
+        } else if let Some(domain) = host.strip_prefix("*.") {
+            if domain.is_empty() || domain.contains('*') {
+                return Err(invalid());
+            }
+
+            HostPattern::Subdomains(domain.to_string())
+        } else if let Some((ip, prefix)) = host.split_once('/') {
+            let ip = ip.parse::<IpAddr>().map_err(|_| invalid())?;
+            let prefix = prefix.parse::<u8>().map_err(|_| invalid())?;
+
+            if prefix > max_prefix_len(&ip) {
+                return Err(invalid());
+            }
+
+            HostPattern::Cidr(ip, prefix)
+        } else if let Ok(ip) = host.parse::<IpAddr>() {
+            HostPattern::Cidr(ip, max_prefix_len(&ip))
+        } else if host.is_empty() || host.contains('*') {
+            return Err(invalid());
+        } else {
+            HostPattern::Name(host.to_string())
+        };
+
+        Ok(Self { host, port })
+    }
+
+    pub(crate) fn matches(&self, host: &str, ip: Option<IpAddr>, port: Option<u16>) -> bool {
+        if self.port.is_some() && self.port != port {
+            return false;
+        }
+
+        match &self.host {
+            HostPattern::Any => true,
+            HostPattern::Name(name) => name == host,
+            HostPattern::Subdomains(domain) => host
+                .strip_suffix(domain.as_str())
+                .map_or(false, |it| it.len() > 1 && it.ends_with('.')),
+            HostPattern::Cidr(net, prefix) => ip.map_or(false, |it| is_in_cidr(&it, net, *prefix)),
+        }
+    }
+
+    /// Whether this is a CIDR rule matching the address. A missing `port`
+    /// stands for any port.
+    fn matches_addr(&self, ip: &IpAddr, port: Option<u16>) -> bool {
+        if port.is_some() && self.port.is_some() && self.port != port {
+            return false;
+        }
+
+        match &self.host {
+            HostPattern::Cidr(net, prefix) => is_in_cidr(ip, net, *prefix),
+            _ => false,
+        }
+    }
+}
+
+fn max_prefix_len(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn is_in_cidr(ip: &IpAddr, net: &IpAddr, prefix: u8) -> bool {
+    // NOTE: IPv4-mapped IPv6 addresses (`::ffff:0:0/96`) reach the embedded
+    // IPv4 address, so they are matched as such.
+    let ip = ip.to_canonical();
+    let (net, prefix) = match net {
+        IpAddr::V6(v6) if prefix >= 96 => match v6.to_ipv4_mapped() {
+            Some(v4) => (IpAddr::V4(v4), prefix - 96),
+            None => (*net, prefix),
+        },
+        _ => (*net, prefix),
+    };
+
+    match (&ip, &net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(*ip) & mask == u32::from(*net) & mask
+        }
+
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(*ip) & mask == u128::from(*net) & mask
+        }
+
+        _ => false,
+    }
+}
+
+/// Whether the address is not publicly routable, i.e. unspecified, loopback,
+/// private (RFC 1918, unique local), shared (CGNAT), link-local (including
+/// the `169.254.169.254` metadata endpoint), documentation, benchmarking,
+/// multicast or reserved.
+pub fn is_private_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_ipv4(ip),
+        IpAddr::V6(ip) => is_private_ipv6(ip),
+    }
+}
+
+fn is_private_ipv4(ip: &Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || ip.is_broadcast()
+        // 0.0.0.0/8
+        || a == 0
+        // 100.64.0.0/10
+        || (a == 100 && (b & 0b1100_0000) == 64)
+        // 192.0.0.0/24
+        || (a == 192 && b == 0 && c == 0)
+        // 198.18.0.0/15
+        || (a == 198 && (b & 0b1111_1110) == 18)
+        // 240.0.0.0/4
+        || a >= 240
+}
+
+fn is_private_ipv6(ip: &Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // IPv4-mapped (`::ffff:0:0/96`) and NAT64 (`64:ff9b::/96`) addresses
+    // reach the embedded IPv4 address
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_private_ipv4(&ip);
+    }
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [_, _, _, _, _, _, hi, lo] = segments;
+        return is_private_ipv4(&Ipv4Addr::from(((hi as u32) << 16) | lo as u32));
+    }
+
+    ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // fc00::/7
+        || (segments[0] & 0xfe00) == 0xfc00
+        // fe80::/10 and the deprecated site-local fec0::/10
+        || (segments[0] & 0xffc0) == 0xfe80
+        || (segments[0] & 0xffc0) == 0xfec0
+        // 2001:db8::/32
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8)
+}
+
+/// Egress rules of a user worker. A destination matching a deny rule is
+/// always rejected. If there is an allow list, the destination must match
+/// one of its rules as well.
+///
+/// NOTE: The rules are checked against the host as it was given, before it
+/// is resolved, so allowed CIDR rules only match IP literals. Denied CIDR
+/// rules and `deny_private_net` are checked against the resolved addresses
+/// as well (see [`Self::is_allowed_addr`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetAccessRules {
+    pub allow: Option<Vec<NetRule>>,
+    pub deny: Vec<NetRule>,
+    /// Rejects destinations which are not publicly routable (see
+    /// [`is_private_ip`]).
+    pub deny_private_net: bool,
+}
+
+impl NetAccessRules {
+    pub fn parse(allow: Option<&[String]>, deny: &[String]) -> Result<Self, AnyError> {
+        Ok(Self {
+            allow: allow
+                .map(|it| it.iter().map(|it| NetRule::parse(it)).collect())
+                .transpose()?,
+            deny: deny
+                .iter()
+                .map(|it| NetRule::parse(it))
+                .collect::<Result<_, _>>()?,
+            deny_private_net: false,
+        })
+    }
+
+    pub fn is_allowed(&self, host: &str, port: Option<u16>) -> bool {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_lowercase();
+        let ip = host.parse::<IpAddr>().ok();
+
+        if ip.map_or(false, |it| !self.is_allowed_addr(&it, port)) {
+            return false;
+        }
+        if self.deny.iter().any(|it| it.matches(&host, ip, port)) {
+            return false;
+        }
+
+        match self.allow.as_ref() {
+            Some(allow) => allow.iter().any(|it| it.matches(&host, ip, port)),
+            None => true,
+        }
+    }
+
+    /// Checks an address the host was resolved to, right before connecting.
+    /// A missing `port` stands for any port, i.e. the CIDR rules denying a
+    /// single port deny the address altogether.
+    pub fn is_allowed_addr(&self, ip: &IpAddr, port: Option<u16>) -> bool {
+        if self.deny_private_net && is_private_ip(ip) {
+            return false;
+        }
+
+        !self.deny.iter().any(|it| it.matches_addr(ip, port))
+    }
+
+    /// Whether the addresses a host resolves to must be checked with
+    /// [`Self::is_allowed_addr`].
+    pub fn checks_resolved_addrs(&self) -> bool {
+        self.deny_private_net
+            || self
+                .deny
+                .iter()
+                .any(|it| matches!(it.host, HostPattern::Cidr(..)))
+    }
+}
+
+/// Makes the path absolute and resolves the symlinks of its longest existing
+/// ancestor, so that a path can't escape an allowed directory through `..`
+/// or a symlink.
+fn resolve_path(path: &Path) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(cwd) if path.is_relative() => normalize_path(cwd.join(path)),
+        _ => normalize_path(path),
+    };
+
+    let mut ancestor = path.as_path();
+    let mut rest = vec![];
+
+    loop {
+        if let Ok(resolved) = ancestor.canonicalize() {
+            return rest.iter().rev().fold(resolved, |acc, it| acc.join(it));
+        }
+
+        match (ancestor.parent(), ancestor.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                ancestor = parent;
+            }
+
+            _ => return path.clone(),
+        }
+    }
+}
+
+/// Paths a worker may read from and write to. A path is accessible if it is
+/// one of the listed paths or within one of the listed directories.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FsAccessRules {
+    /// `None` = any path.
+    pub allow_read: Option<Vec<PathBuf>>,
+    /// `None` = any path.
+    pub allow_write: Option<Vec<PathBuf>>,
+}
+
+impl FsAccessRules {
+    pub fn new(allow_read: Option<Vec<PathBuf>>, allow_write: Option<Vec<PathBuf>>) -> Self {
+        let resolve = |paths: Vec<PathBuf>| paths.iter().map(|it| resolve_path(it)).collect();
+
+        Self {
+            allow_read: allow_read.map(resolve),
+            allow_write: allow_write.map(resolve),
+        }
+    }
+
+    pub fn is_read_allowed(&self, path: &Path) -> bool {
+        is_path_allowed(self.allow_read.as_deref(), path)
+    }
+
+    pub fn is_write_allowed(&self, path: &Path) -> bool {
+        is_path_allowed(self.allow_write.as_deref(), path)
+    }
+}
+
+fn is_path_allowed(allow: Option<&[PathBuf]>, path: &Path) -> bool {
+    let Some(allow) = allow else {
+        return true;
+    };
+
+    let path = resolve_path(path);
+
+    allow.iter().any(|it| path.starts_with(it))
+}
+
+/// Environment variables a worker may read. Patterns may contain the `*`,
+/// `?` and `[...]` wildcards, e.g. `SUPABASE_*`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvAccessRules {
+    /// `None` = any variable.
+    pub allow: Option<Vec<glob::Pattern>>,
+    /// Variables which can be read one by one, but are left out of
+    /// `Deno.env.toObject()` and redacted from the logs.
+    pub secrets: Vec<glob::Pattern>,
+}
+
+impl EnvAccessRules {
+    pub fn parse(allow: Option<&[String]>, secrets: &[String]) -> Result<Self, AnyError> {
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|it| {
+                    glob::Pattern::new(it)
+                        .map_err(|_| type_error(format!("invalid env access rule: {}", it)))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            allow: allow.map(parse).transpose()?,
+            secrets: parse(secrets)?,
+        })
+    }
+
+    pub fn is_allowed(&self, key: &str) -> bool {
+        self.allow
+            .as_ref()
+            .map_or(true, |it| it.iter().any(|it| it.matches(key)))
+    }
+
+    pub fn is_secret(&self, key: &str) -> bool {
+        self.secrets.iter().any(|it| it.matches(key))
+    }
+
+    /// Values of the secret variables, which are redacted from the logs.
+    pub fn secret_values<'a>(
+        &'a self,
+        env_vars: &'a HashMap<String, String>,
+    ) -> impl Iterator<Item = String> + 'a {
+        env_vars
+            .iter()
+            .filter(|(key, _)| self.is_secret(key))
+            .map(|(_, value)| value.clone())
+    }
+}
 
 pub struct Permissions {
     net_access_disabled: bool,
+    net_access_rules: NetAccessRules,
+    fs_access_rules: FsAccessRules,
+    env_access_rules: EnvAccessRules,
 }
 
 impl Default for Permissions {
     fn default() -> Self {
-        Self::new(false)
+        Self::new(
+            false,
+            NetAccessRules::default(),
+            FsAccessRules::default(),
+            EnvAccessRules::default(),
+        )
     }
 }
 
 impl Permissions {
-    pub fn new(net_access_disabled: bool) -> Self {
+    pub fn new(
+        net_access_disabled: bool,
+        net_access_rules: NetAccessRules,
+        fs_access_rules: FsAccessRules,
+        env_access_rules: EnvAccessRules,
+    ) -> Self {
         Self {
             net_access_disabled,
+            net_access_rules,
+            fs_access_rules,
+            env_access_rules,
         }
     }
 
-    pub fn check_env(&mut self, _var: &str) -> Result<(), AnyError> {
+    fn check_net_access(&self, host: &str, port: Option<u16>) -> Result<(), AnyError> {
+        if self.net_access_disabled {
+            return Err(custom_error(
+                "PermissionDenied",
+                "net access disabled for the user worker",
+            ));
+        }
+
+        if !self.net_access_rules.is_allowed(host, port) {
+            return Err(custom_error(
+                "PermissionDenied",
+                match port {
+                    Some(port) => format!("net access to {}:{} is not allowed", host, port),
+                    None => format!("net access to {} is not allowed", host),
+                },
+            ));
+        }
+
         Ok(())
     }
 
+    /// Rejects the address a destination was resolved to if it is not
+    /// allowed, so that a host can't be rebound to a private address after
+    /// the hostname was checked.
+    pub fn check_resolved_addr(&self, addr: &SocketAddr) -> Result<(), AnyError> {
+        if !self
+            .net_access_rules
+            .is_allowed_addr(&addr.ip(), Some(addr.port()))
+        {
+            return Err(custom_error(
+                "PermissionDenied",
+                format!("net access to {} is not allowed", addr.ip()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn net_access_rules(&self) -> &NetAccessRules {
+        &self.net_access_rules
+    }
+
+    fn check_net_url_access(&self, url: &Url) -> Result<(), AnyError> {
+        let host = match url.host() {
+            Some(Host::Domain(domain)) => domain.to_string(),
+            Some(Host::Ipv4(ip)) => ip.to_string(),
+            Some(Host::Ipv6(ip)) => ip.to_string(),
+            None => return self.check_net_access("", None),
+        };
+
+        self.check_net_access(&host, url.port_or_known_default())
+    }
+
+    fn check_read_access(&self, path: &Path, display: Option<&str>) -> Result<(), AnyError> {
+        if !self.fs_access_rules.is_read_allowed(path) {
+            return Err(custom_error(
+                "PermissionDenied",
+                format!(
+                    "read access to {} is not allowed",
+                    display.map_or_else(|| path.display().to_string(), |it| it.to_string())
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn check_write_access(&self, path: &Path, display: Option<&str>) -> Result<(), AnyError> {
+        if !self.fs_access_rules.is_write_allowed(path) {
+            return Err(custom_error(
+                "PermissionDenied",
+                format!(
+                    "write access to {} is not allowed",
+                    display.map_or_else(|| path.display().to_string(), |it| it.to_string())
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_env(&mut self, var: &str) -> Result<(), AnyError> {
+        if !self.env_access_rules.is_allowed(var) {
+            return Err(custom_error(
+                "PermissionDenied",
+                format!("env access to {} is not allowed", var),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// `Deno.env.toObject()` is always allowed, but it only lists the
+    /// variables for which [`Self::is_env_listed`] holds.
     pub fn check_env_all(&mut self) -> Result<(), AnyError> {
         Ok(())
     }
 
+    /// Whether the variable is listed by `Deno.env.toObject()`.
+    pub fn is_env_listed(&self, var: &str) -> bool {
+        self.env_access_rules.is_allowed(var) && !self.env_access_rules.is_secret(var)
+    }
+
     pub fn check_read_blind(
         &mut self,
         _path: &Path,
@@ -40,9 +539,19 @@ impl Permissions {
 
 deno_core::extension!(
     sb_core_permissions,
-    options = { net_access_disabled: bool },
+    options = {
+        net_access_disabled: bool,
+        net_access_rules: NetAccessRules,
+        fs_access_rules: FsAccessRules,
+        env_access_rules: EnvAccessRules
+    },
     state = |state, options| {
-        state.put::<Permissions>(Permissions::new(options.net_access_disabled));
+        state.put::<Permissions>(Permissions::new(
+            options.net_access_disabled,
+            options.net_access_rules,
+            options.fs_access_rules,
+            options.env_access_rules,
+        ));
     }
 );
 
@@ -53,125 +562,130 @@ impl deno_web::TimersPermission for Permissions {
 }
 
 impl deno_fetch::FetchPermissions for Permissions {
-    fn check_net_url(&mut self, _url: &Url, _api_name: &str) -> Result<(), AnyError> {
-        if self.net_access_disabled {
-            return Err(custom_error(
-                "PermissionDenied",
-                "net access disabled for the user worker",
-            ));
-        }
-        Ok(())
+    fn check_net_url(&mut self, url: &Url, _api_name: &str) -> Result<(), AnyError> {
+        self.check_net_url_access(url)
     }
 
-    fn check_read(&mut self, _p: &Path, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_read(&mut self, path: &Path, _api_name: &str) -> Result<(), AnyError> {
+        self.check_read_access(path, None)
     }
 }
 
 impl deno_net::NetPermissions for Permissions {
     fn check_net<T: AsRef<str>>(
         &mut self,
-        _host: &(T, Option<u16>),
+        host: &(T, Option<u16>),
         _api_name: &str,
     ) -> Result<(), AnyError> {
-        if self.net_access_disabled {
-            return Err(custom_error(
-                "PermissionDenied",
-                "net access disabled for the user worker",
-            ));
-        }
-        Ok(())
+        self.check_net_access(host.0.as_ref(), host.1)
     }
 
-    fn check_read(&mut self, _path: &Path, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_read(&mut self, path: &Path, _api_name: &str) -> Result<(), AnyError> {
+        self.check_read_access(path, None)
     }
 
-    fn check_write(&mut self, _path: &Path, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_write(&mut self, path: &Path, _api_name: &str) -> Result<(), AnyError> {
+        self.check_write_access(path, None)
     }
 }
 
-impl deno_websocket::WebSocketPermissions for Permissions {
-    fn check_net_url(&mut self, _url: &Url, _api_name: &str) -> Result<(), AnyError> {
-        if self.net_access_disabled {
-            return Err(custom_error(
-                "PermissionDenied",
-                "net access disabled for the user worker",
-            ));
-        }
-
-        Ok(())
+impl sb_websocket::WebSocketPermissions for Permissions {
+    fn check_net_url(&mut self, url: &Url, _api_name: &str) -> Result<(), AnyError> {
+        self.check_net_url_access(url)
     }
 }
 
-/// TODO: File system should be protected even if it's for main.
-/// Some sort of permission before main is boostrapped should be put in place
-
 impl deno_fs::FsPermissions for Permissions {
-    fn check_read(&mut self, _path: &Path, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_read(&mut self, path: &Path, _api_name: &str) -> Result<(), AnyError> {
+        self.check_read_access(path, None)
     }
 
     fn check_read_all(&mut self, _api_name: &str) -> Result<(), AnyError> {
+        if self.fs_access_rules.allow_read.is_some() {
+            return Err(custom_error(
+                "PermissionDenied",
+                "read access to all paths is not allowed",
+            ));
+        }
+
         Ok(())
     }
 
     fn check_read_blind(
         &mut self,
-        _path: &Path,
-        _display: &str,
+        path: &Path,
+        display: &str,
         _api_name: &str,
     ) -> Result<(), AnyError> {
-        Ok(())
+        // NOTE: `Deno.cwd()` has always been available to the user workers.
+        if std::env::current_dir().map_or(false, |it| it == path) {
+            return Ok(());
+        }
+
+        self.check_read_access(path, Some(display))
     }
 
-    fn check_write(&mut self, _path: &Path, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_write(&mut self, path: &Path, _api_name: &str) -> Result<(), AnyError> {
+        self.check_write_access(path, None)
     }
 
-    fn check_write_partial(&mut self, _path: &Path, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_write_partial(&mut self, path: &Path, _api_name: &str) -> Result<(), AnyError> {
+        self.check_write_access(path, None)
     }
 
     fn check_write_all(&mut self, _api_name: &str) -> Result<(), AnyError> {
+        if self.fs_access_rules.allow_write.is_some() {
+            return Err(custom_error(
+                "PermissionDenied",
+                "write access to all paths is not allowed",
+            ));
+        }
+
         Ok(())
     }
 
     fn check_write_blind(
         &mut self,
-        _p: &Path,
-        _display: &str,
+        path: &Path,
+        display: &str,
         _api_name: &str,
     ) -> Result<(), AnyError> {
-        Ok(())
+        self.check_write_access(path, Some(display))
     }
 
     fn check(
         &mut self,
-        _open_options: &OpenOptions,
-        _path: &Path,
+        open_options: &OpenOptions,
+        path: &Path,
         _api_name: &str,
     ) -> Result<(), AnyError> {
+        if open_options.read {
+            self.check_read_access(path, None)?;
+        }
+
+        if open_options.write || open_options.append {
+            self.check_write_access(path, None)?;
+        }
+
         Ok(())
     }
 }
 
 impl sb_node::NodePermissions for Permissions {
-    fn check_net_url(&mut self, _url: &Url, _api_name: &str) -> Result<(), AnyError> {
-        Ok(())
+    fn check_net_url(&mut self, url: &Url, _api_name: &str) -> Result<(), AnyError> {
+        self.check_net_url_access(url)
     }
 
-    fn check_read(&self, _path: &Path) -> Result<(), AnyError> {
-        Ok(())
+    fn check_read(&self, path: &Path) -> Result<(), AnyError> {
+        self.check_read_access(path, None)
     }
 
     fn check_read_with_api_name(
         &self,
-        _path: &Path,
+        path: &Path,
         _api_name: Option<&str>,
     ) -> Result<(), AnyError> {
-        Ok(())
+        self.check_read_access(path, None)
     }
 
     fn check_sys(&self, _kind: &str, _api_name: &str) -> Result<(), AnyError> {
@@ -180,9 +694,9 @@ impl sb_node::NodePermissions for Permissions {
 
     fn check_write_with_api_name(
         &self,
-        _path: &Path,
+        path: &Path,
         _api_name: Option<&str>,
     ) -> Result<(), AnyError> {
-        Ok(())
+        self.check_write_access(path, None)
     }
 }
@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use bytes::Bytes;
+use deno_core::error::type_error;
+use deno_core::futures::future::LocalBoxFuture;
+use deno_core::futures::FutureExt;
+use deno_core::url::Url;
+use deno_core::OpState;
+use deno_fetch::reqwest;
+use http_body_util::Empty;
+use sb_websocket::{WsHandshakeResult, WsUpgradedStream};
+
+/// Client the WebSocket connections of a user worker are opened with. It is
+/// created the same as the client of `fetch`, except that it only speaks
+/// HTTP/1.1, as the connections can't be upgraded over HTTP/2.
+pub struct WsHttpClient(pub reqwest::Client);
+
+/// Does the opening handshake of the WebSocket connections through
+/// [`WsHttpClient`] (see `WsHandshakeHook` of `sb_websocket`), so that they go
+/// through the same address checks and proxy as the `fetch` requests.
+pub fn handshake(
+    state: Rc<RefCell<OpState>>,
+    request: http::Request<Empty<Bytes>>,
+) -> LocalBoxFuture<'static, WsHandshakeResult> {
+    async move {
+        let client = state.borrow().borrow::<WsHttpClient>().0.clone();
+        let mut url = Url::parse(&request.uri().to_string())?;
+        let scheme = if url.scheme() == "wss" {
+            "https"
+        } else {
+            "http"
+        };
+
+        url.set_scheme(scheme)
+            .map_err(|_| type_error(format!("invalid WebSocket url: {}", url)))?;
+
+        let mut req = client.get(url);
+
+        for (name, value) in request.headers() {
+            req = req.header(name.as_str(), value.as_bytes());
+        }
+
+        let res = req.send().await?;
+
+        if res.status() != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+            return Err(type_error(format!("Invalid status code: {}", res.status())));
+        }
+
+        let mut headers = http::HeaderMap::new();
+
+        for (name, value) in res.headers() {
+            headers.append(
+                http::HeaderName::from_bytes(name.as_str().as_bytes())?,
+                http::HeaderValue::from_bytes(value.as_bytes())?,
+            );
+        }
+
+        let upgraded: Box<dyn WsUpgradedStream> = Box::new(res.upgrade().await?);
+
+        Ok((upgraded, headers))
+    }
+    .boxed_local()
+}
@@ -28,8 +28,13 @@ fn op_set_env(
 #[serde]
 fn op_env(state: &mut OpState) -> Result<HashMap<String, String>, AnyError> {
     state.borrow_mut::<Permissions>().check_env_all()?;
+    let permissions = state.borrow::<Permissions>();
     let env_vars = state.borrow::<EnvVars>();
-    Ok(env_vars.clone())
+    Ok(env_vars
+        .iter()
+        .filter(|(key, _)| permissions.is_env_listed(key))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect())
 }
 
 #[op2]
@@ -1,51 +1,113 @@
 use anyhow::Error;
 use deno_core::FastString;
 use enum_as_inner::EnumAsInner;
-use event_worker::events::WorkerEventWithMetadata;
+use event_worker::channel::{EventSender, SharedEventReceiver};
+use event_worker::events::{LogLevel, ShutdownReason};
+use hyper::header::HeaderName;
 use hyper::{Body, Request, Response};
 use sb_core::conn_sync::ConnSync;
+use sb_core::http_client::ProxyConfig;
+use sb_core::permissions::{EnvAccessRules, FsAccessRules, NetAccessRules};
 use sb_core::util::sync::AtomicFlag;
-use sb_core::{MetricSource, SharedMetricSource};
+use sb_core::{MetricSource, SharedMetricSource, WorkerHeapStatistics, WorkerMetricSource};
+use serde::Serialize;
 use std::path::PathBuf;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::mpsc::unbounded_channel;
 use tokio::sync::{mpsc, oneshot, watch, Notify, OwnedSemaphorePermit};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
 use sb_graph::EszipPayloadKind;
 
+use crate::errors::WorkerBootError;
+
 #[derive(Debug, Clone)]
 pub struct UserWorkerRuntimeOpts {
     pub service_path: Option<String>,
     pub key: Option<Uuid>,
 
     pub pool_msg_tx: Option<mpsc::UnboundedSender<UserWorkerMsgs>>,
-    pub events_msg_tx: Option<mpsc::UnboundedSender<WorkerEventWithMetadata>>,
+    pub events_msg_tx: Option<EventSender>,
     pub cancel: Option<Arc<Notify>>,
 
     pub memory_limit_mb: u64,
+    pub memory_sample_interval_ms: u64,   // 0 = disabled
+    pub resource_sample_interval_ms: u64, // 0 = disabled
     pub low_memory_multiplier: u64,
 
-    pub worker_timeout_ms: u64, // wall clock limit
+    pub worker_timeout_ms: u64,  // wall clock limit
+    pub request_timeout_ms: u64, // per request wall clock limit (0 = unlimited)
+
+    /// Name of the response header that carries the CPU time attributed to
+    /// the request, in milliseconds. Not exposed when `None`.
+    pub cpu_time_header: Option<String>,
 
     pub cpu_time_soft_limit_ms: u64,
     pub cpu_time_hard_limit_ms: u64,
 
+    /// Budget given to the `beforeunload` handlers of the user code before the
+    /// isolate is terminated (0 = the event is not dispatched).
+    pub shutdown_grace_period_ms: u64,
+    pub shutdown_grace_cpu_time_ms: u64, // 0 = unlimited within the grace period
+
     pub force_create: bool,
     pub net_access_disabled: bool,
+    /// Hosts and ports the worker may (or may not) connect to.
+    pub net_access_rules: NetAccessRules,
+    /// Paths the worker may read from and write to (`None` = read-only
+    /// access to the service directory).
+    pub fs_access_rules: Option<FsAccessRules>,
+    /// Environment variables the worker may read, and the ones that are
+    /// redacted from its logs.
+    pub env_access_rules: EnvAccessRules,
+    /// Proxy the `fetch` requests and the WebSocket connections of the worker
+    /// go through (`None` = the proxy of the environment for `fetch`, if any).
+    pub proxy: Option<ProxyConfig>,
     pub custom_module_root: Option<String>,
     pub allow_remote_modules: bool,
+
+    /// Writes a heap snapshot when the worker hits its memory limit.
+    pub heap_snapshot: Option<HeapSnapshotOpts>,
+
+    /// Directory the CPU profiles of the worker are written into. The CPU
+    /// profiler is not available when `None`.
+    pub cpu_profile_dir: Option<PathBuf>,
+    /// Starts the CPU profiler as soon as the worker boots. The profile is
+    /// written after the given duration (0 = when the worker shuts down).
+    pub cpu_profile_duration_ms: Option<u64>,
+
+    /// Console output below this level is dropped.
+    pub log_level: LogLevel,
+    /// Attaches the object arguments of console calls to the log events as
+    /// JSON fields.
+    pub structured_logs: bool,
+    /// Trace context of the request that asked for the worker, used as the
+    /// parent of the worker creation span.
+    pub traceparent: Option<String>,
+
+    /// Identifies the deployment of the service (e.g. the hash of the eszip).
+    /// When a newer version is created, workers of the older versions are
+    /// drained.
+    pub version: Option<String>,
 }
 
 impl Default for UserWorkerRuntimeOpts {
     fn default() -> UserWorkerRuntimeOpts {
         UserWorkerRuntimeOpts {
             memory_limit_mb: 512,
+            memory_sample_interval_ms: 1000,
+            resource_sample_interval_ms: 0,
             worker_timeout_ms: 5 * 60 * 1000,
+            request_timeout_ms: 0,
+            cpu_time_header: None,
             low_memory_multiplier: 5,
             cpu_time_soft_limit_ms: 50,
             cpu_time_hard_limit_ms: 100,
+            shutdown_grace_period_ms: 0,
+            shutdown_grace_cpu_time_ms: 0,
 
             force_create: false,
             key: None,
@@ -53,24 +115,75 @@ impl Default for UserWorkerRuntimeOpts {
             events_msg_tx: None,
             cancel: None,
             net_access_disabled: false,
+            net_access_rules: NetAccessRules::default(),
+            fs_access_rules: None,
+            env_access_rules: EnvAccessRules::default(),
+            proxy: None,
             allow_remote_modules: true,
             custom_module_root: None,
             service_path: None,
+            version: None,
+            heap_snapshot: None,
+            cpu_profile_dir: None,
+            cpu_profile_duration_ms: None,
+            log_level: LogLevel::Debug,
+            structured_logs: false,
+            traceparent: None,
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct HeapSnapshotOpts {
+    pub dir: PathBuf,
+    pub max_size_bytes: usize,
+    /// Minimum time between two snapshots, across all workers.
+    pub min_interval_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct CpuProfileRequest {
+    /// Stops the profiler after the given duration (0 = when the worker shuts
+    /// down).
+    pub duration_ms: u64,
+    pub result_tx: Option<oneshot::Sender<Result<PathBuf, Error>>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct UserWorkerProfile {
     pub worker_request_msg_tx: mpsc::UnboundedSender<WorkerRequestMsg>,
     pub timing_tx_pair: (
-        mpsc::UnboundedSender<Arc<Notify>>,
-        mpsc::UnboundedSender<()>,
+        mpsc::UnboundedSender<RequestStart>,
+        mpsc::UnboundedSender<Uuid>,
     ),
     pub service_path: String,
+    pub version: Option<String>,
+    pub cpu_time_header: Option<HeaderName>,
+    pub cpu_profile_tx: Option<mpsc::UnboundedSender<CpuProfileRequest>>,
     pub permit: Option<Arc<OwnedSemaphorePermit>>,
     pub cancel: Arc<Notify>,
     pub status: TimingStatus,
+    pub metric_src: Option<WorkerMetricSource>,
+    pub termination_token: CancellationToken,
+    pub created_at: Instant,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserWorkerInfo {
+    pub key: Uuid,
+    pub service_path: String,
+    pub version: Option<String>,
+    pub demand: usize,
+    pub age_ms: u64,
+    pub is_retired: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserWorkerStats {
+    pub cpu_time_used_ms: usize,
+    pub heap_stats: Option<WorkerHeapStatistics>,
 }
 
 #[derive(Debug, Clone)]
@@ -78,10 +191,16 @@ pub struct MainWorkerRuntimeOpts {
     pub worker_pool_tx: mpsc::UnboundedSender<UserWorkerMsgs>,
     pub shared_metric_src: Option<SharedMetricSource>,
     pub event_worker_metric_src: Option<MetricSource>,
+    /// Paths the main worker may read from and write to.
+    pub fs_access_rules: FsAccessRules,
 }
 
-#[derive(Debug, Clone)]
-pub struct EventWorkerRuntimeOpts {}
+#[derive(Debug, Clone, Default)]
+pub struct EventWorkerRuntimeOpts {
+    /// Cancelled once the runtime of the events worker is dropped, whether it
+    /// was terminated or exited on its own.
+    pub exit_token: Option<CancellationToken>,
+}
 
 #[derive(Debug, Clone, EnumAsInner)]
 pub enum WorkerRuntimeOpts {
@@ -117,25 +236,94 @@ impl From<&WorkerRuntimeOpts> for WorkerKind {
 pub struct TimingStatus {
     pub demand: Arc<AtomicUsize>,
     pub is_retired: Arc<AtomicFlag>,
+    pub drain: Arc<Notify>,
+    pub cpu_time_used_ms: Arc<AtomicUsize>,
+    pub cpu_clock: RequestCpuClock,
+    pub served_requests: Arc<AtomicUsize>,
+}
+
+/// Attributes the CPU time spent by a worker to the requests that were in
+/// flight while it was spent. The CPU time of each poll is split evenly among
+/// the concurrent requests, so the result is a best-effort estimate when the
+/// worker serves more than one request at once.
+#[derive(Debug, Clone, Default)]
+pub struct RequestCpuClock {
+    active: Arc<AtomicUsize>,
+    share_ns: Arc<AtomicU64>,
+}
+
+impl RequestCpuClock {
+    /// Marks a request as active and returns the clock value it starts at.
+    pub fn enter(&self) -> u64 {
+        self.active.fetch_add(1, Ordering::AcqRel);
+        self.share_ns.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of requests currently active.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+
+    /// Marks a request as done and returns the CPU time attributed to it.
+    pub fn leave(&self, start: u64) -> u64 {
+        self.active.fetch_sub(1, Ordering::AcqRel);
+        self.elapsed(start)
+    }
+
+    /// Returns the CPU time attributed so far to a request started at
+    /// `start`.
+    pub fn elapsed(&self, start: u64) -> u64 {
+        self.share_ns.load(Ordering::Acquire).saturating_sub(start)
+    }
+
+    /// Distributes `cpu_time_ns` among the currently active requests.
+    pub fn advance(&self, cpu_time_ns: u64) {
+        let active = self.active.load(Ordering::Acquire) as u64;
+
+        if active > 0 {
+            self.share_ns
+                .fetch_add(cpu_time_ns / active, Ordering::AcqRel);
+        }
+    }
+}
+
+/// Sent to the isolate when the supervisor is about to terminate it. The
+/// isolate answers through the inner sender once its `beforeunload` handlers
+/// have settled.
+pub type BeforeUnloadSignal = (ShutdownReason, oneshot::Sender<()>);
+
+/// Sent to the supervisor before a request is passed to the worker. The end
+/// of the request is signalled with its `id`.
+#[derive(Debug, Clone)]
+pub struct RequestStart {
+    pub id: Uuid,
+    /// Notified by the supervisor once the request may be passed to the
+    /// worker. Only awaited under the per request policy.
+    pub fence: Arc<Notify>,
+    /// Cancelled by the supervisor once the request has exceeded
+    /// `request_timeout_ms`.
+    pub timeout: CancellationToken,
 }
 
 #[derive(Debug)]
 pub struct Timing {
     pub status: TimingStatus,
     pub req: (
-        mpsc::UnboundedReceiver<Arc<Notify>>,
-        mpsc::UnboundedReceiver<()>,
+        mpsc::UnboundedReceiver<RequestStart>,
+        mpsc::UnboundedReceiver<Uuid>,
     ),
+    pub cpu_profile_rx: Option<mpsc::UnboundedReceiver<CpuProfileRequest>>,
 }
 
 impl Default for Timing {
     fn default() -> Self {
-        let (_, dumb_start_rx) = unbounded_channel::<Arc<Notify>>();
-        let (_, dumb_end_rx) = unbounded_channel::<()>();
+        let (_, dumb_start_rx) = unbounded_channel::<RequestStart>();
+        let (_, dumb_end_rx) = unbounded_channel::<Uuid>();
 
         Self {
             status: TimingStatus::default(),
             req: (dumb_start_rx, dumb_end_rx),
+            cpu_profile_rx: None,
         }
     }
 }
@@ -146,7 +334,7 @@ pub struct WorkerContextInitOpts {
     pub no_module_cache: bool,
     pub import_map_path: Option<String>,
     pub env_vars: HashMap<String, String>,
-    pub events_rx: Option<mpsc::UnboundedReceiver<WorkerEventWithMetadata>>,
+    pub events_rx: Option<SharedEventReceiver>,
     pub timing: Option<Timing>,
     pub conf: WorkerRuntimeOpts,
     pub maybe_eszip: Option<EszipPayloadKind>,
@@ -169,9 +357,27 @@ pub enum UserWorkerMsgs {
     ),
     Idle(Uuid),
     Shutdown(Uuid),
+    BootFailed(String, WorkerBootError),
+    /// The create request that probed a quarantined service ended without
+    /// booting a worker.
+    BootProbeReleased(String),
+    List(oneshot::Sender<Vec<UserWorkerInfo>>),
+    Stats(Uuid, oneshot::Sender<Result<UserWorkerStats, Error>>),
+    Terminate(Uuid, oneshot::Sender<Result<(), Error>>),
+    Retire(Uuid, oneshot::Sender<Result<(), Error>>),
+    CpuProfile(Uuid, u64, oneshot::Sender<Result<PathBuf, Error>>),
 }
 
-pub type SendRequestResult = (Response<Body>, mpsc::UnboundedSender<()>);
+pub type SendRequestResult = (Response<Body>, mpsc::UnboundedSender<ResponseEnd>);
+
+/// Sent by the caller once it's done with the response of a user worker.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ResponseEnd {
+    /// Bytes of the response body consumed by the caller.
+    pub response_bytes: usize,
+    /// Whether the caller stopped consuming the body before its end.
+    pub aborted: bool,
+}
 
 #[derive(Debug)]
 pub struct CreateUserWorkerResult {
@@ -1,7 +1,45 @@
+use anyhow::Error;
+use deno_core::error::JsError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum WorkerError {
     #[error("request has been cancelled by supervisor")]
     RequestCancelledBySupervisor,
+    #[error("service has been quarantined due to repeated boot failures ({0})")]
+    ServiceQuarantined(WorkerBootError),
+}
+
+/// The reason a user worker failed to boot, reduced to the class and the
+/// message of the original error so that it can be cheaply cloned.
+#[derive(Error, Debug, Clone)]
+#[error("{class}: {msg}")]
+pub struct WorkerBootError {
+    pub class: String,
+    pub msg: String,
+}
+
+impl From<&Error> for WorkerBootError {
+    fn from(err: &Error) -> Self {
+        if let Some(err) = err.downcast_ref::<WorkerBootError>() {
+            return err.clone();
+        }
+
+        if let Some(err) = err.downcast_ref::<JsError>() {
+            return Self {
+                class: err.name.clone().unwrap_or_else(|| "Error".to_string()),
+                msg: err
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| err.exception_message.clone()),
+            };
+        }
+
+        Self {
+            class: sb_core::errors_rt::get_error_class_name(err)
+                .unwrap_or("Error")
+                .to_string(),
+            msg: err.to_string(),
+        }
+    }
 }
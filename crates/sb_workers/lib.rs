@@ -2,8 +2,8 @@ pub mod context;
 pub mod errors;
 
 use crate::context::{
-    CreateUserWorkerResult, UserWorkerMsgs, UserWorkerRuntimeOpts, WorkerContextInitOpts,
-    WorkerRuntimeOpts,
+    BeforeUnloadSignal, CreateUserWorkerResult, ResponseEnd, UserWorkerInfo, UserWorkerMsgs,
+    UserWorkerRuntimeOpts, UserWorkerStats, WorkerContextInitOpts, WorkerRuntimeOpts,
 };
 use anyhow::Error;
 use context::SendRequestResult;
@@ -16,15 +16,19 @@ use deno_core::{
     JsBuffer, OpState, RcRef, Resource, ResourceId, WriteOutcome,
 };
 use errors::WorkerError;
+use event_worker::events::{LogLevel, ShutdownReason};
 use hyper::body::HttpBody;
 use hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH};
 use hyper::{Body, Method, Request};
 use log::error;
 use sb_core::conn_sync::{ConnSync, ConnWatcher};
+use sb_core::http_client::ProxyConfig;
+use sb_core::permissions::{EnvAccessRules, FsAccessRules, NetAccessRules};
+use sb_core::util::checksum;
 use sb_graph::EszipPayloadKind;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::pin::Pin;
@@ -39,6 +43,13 @@ deno_core::extension!(
         op_user_worker_create,
         op_user_worker_fetch_build,
         op_user_worker_fetch_send,
+        op_user_worker_list,
+        op_user_worker_stats,
+        op_user_worker_terminate,
+        op_user_worker_retire,
+        op_user_worker_cpu_profile,
+        op_user_worker_wait_for_beforeunload,
+        op_user_worker_beforeunload_done,
     ],
     esm_entry_point = "ext:sb_user_workers/user_workers.js",
     esm = ["user_workers.js",]
@@ -51,19 +62,39 @@ pub struct UserWorkerCreateOptions {
     no_module_cache: bool,
     import_map_path: Option<String>,
     env_vars: Vec<(String, String)>,
+    allow_env: Option<Vec<String>>,
+    secret_env: Vec<String>,
     force_create: bool,
     allow_remote_modules: bool,
     net_access_disabled: bool,
+    allow_net: Option<Vec<String>>,
+    deny_net: Vec<String>,
+    deny_private_net: bool,
+    proxy: Option<String>,
+    no_proxy: Vec<String>,
+    allow_read: Option<Vec<String>>,
+    allow_write: Vec<String>,
     custom_module_root: Option<String>,
     maybe_eszip: Option<JsBuffer>,
     maybe_entrypoint: Option<String>,
     maybe_module_code: Option<String>,
+    version: Option<String>,
 
     memory_limit_mb: u64,
+    memory_sample_interval_ms: u64,
+    resource_sample_interval_ms: u64,
     low_memory_multiplier: u64,
     worker_timeout_ms: u64,
+    request_timeout_ms: u64,
+    cpu_time_header: Option<String>,
     cpu_time_soft_limit_ms: u64,
     cpu_time_hard_limit_ms: u64,
+    shutdown_grace_period_ms: u64,
+    shutdown_grace_cpu_time_ms: u64,
+    cpu_profile_duration_ms: Option<u64>,
+    log_level: Option<LogLevel>,
+    structured_logs: bool,
+    traceparent: Option<String>,
 }
 
 #[op2(async)]
@@ -82,21 +113,77 @@ pub async fn op_user_worker_create(
             no_module_cache,
             import_map_path,
             env_vars,
+            allow_env,
+            secret_env,
             force_create,
             net_access_disabled,
+            allow_net,
+            deny_net,
+            deny_private_net,
+            proxy,
+            no_proxy,
+            allow_read,
+            allow_write,
             allow_remote_modules,
             custom_module_root,
             maybe_eszip,
             maybe_entrypoint,
             maybe_module_code,
+            version,
 
             memory_limit_mb,
+            memory_sample_interval_ms,
+            resource_sample_interval_ms,
             low_memory_multiplier,
             worker_timeout_ms,
+            request_timeout_ms,
+            cpu_time_header,
             cpu_time_soft_limit_ms,
             cpu_time_hard_limit_ms,
+            shutdown_grace_period_ms,
+            shutdown_grace_cpu_time_ms,
+            cpu_profile_duration_ms,
+            log_level,
+            structured_logs,
+            traceparent,
         } = opts;
 
+        if let Some(name) = cpu_time_header.as_deref() {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() {
+                return Err(type_error(format!(
+                    "invalid cpu time header name: {}",
+                    name
+                )));
+            }
+        }
+
+        let net_access_rules = NetAccessRules {
+            deny_private_net,
+            ..NetAccessRules::parse(allow_net.as_deref(), &deny_net)?
+        };
+        let env_access_rules = EnvAccessRules::parse(allow_env.as_deref(), &secret_env)?;
+        let proxy = proxy
+            .map(|it| ProxyConfig::parse(&it, &no_proxy))
+            .transpose()?;
+
+        if let Some(proxy) = proxy.as_ref() {
+            proxy.check_net_access_rules(&net_access_rules)?;
+        }
+        let fs_access_rules = FsAccessRules::new(
+            Some(
+                allow_read
+                    .unwrap_or_else(|| vec![service_path.clone()])
+                    .into_iter()
+                    .map(PathBuf::from)
+                    .collect(),
+            ),
+            Some(allow_write.into_iter().map(PathBuf::from).collect()),
+        );
+
+        let version = version
+            .or_else(|| maybe_eszip.as_ref().map(|it| checksum::gen(&[&it[..]])))
+            .or_else(|| maybe_module_code.as_ref().map(|it| checksum::gen(&[it])));
+
         let mut env_vars_map = HashMap::new();
         for (key, value) in env_vars {
             env_vars_map.insert(key, value);
@@ -114,12 +201,22 @@ pub async fn op_user_worker_create(
             maybe_module_code: maybe_module_code.map(|v| v.into()),
             conf: WorkerRuntimeOpts::UserWorker(UserWorkerRuntimeOpts {
                 memory_limit_mb,
+                memory_sample_interval_ms,
+                resource_sample_interval_ms,
                 low_memory_multiplier,
                 worker_timeout_ms,
+                request_timeout_ms,
+                cpu_time_header,
                 cpu_time_soft_limit_ms,
                 cpu_time_hard_limit_ms,
+                shutdown_grace_period_ms,
+                shutdown_grace_cpu_time_ms,
                 force_create,
                 net_access_disabled,
+                net_access_rules,
+                fs_access_rules: Some(fs_access_rules),
+                env_access_rules,
+                proxy,
                 allow_remote_modules,
                 custom_module_root,
                 key: None,
@@ -127,6 +224,13 @@ pub async fn op_user_worker_create(
                 events_msg_tx: None,
                 cancel: None,
                 service_path: None,
+                version,
+                heap_snapshot: None,
+                cpu_profile_dir: None,
+                cpu_profile_duration_ms,
+                log_level: log_level.unwrap_or_default(),
+                structured_logs,
+                traceparent,
             }),
         };
 
@@ -145,7 +249,12 @@ pub async fn op_user_worker_create(
     // channel returns a Result<T, E>, we need to unwrap it first;
     let result = result.unwrap();
     match result {
-        Err(e) => Err(custom_error("InvalidWorkerCreation", e.to_string())),
+        Err(e) => match e.downcast_ref::<WorkerError>() {
+            Some(WorkerError::ServiceQuarantined(_)) => {
+                Err(custom_error("WorkerQuarantined", e.to_string()))
+            }
+            _ => Err(custom_error("InvalidWorkerCreation", e.to_string())),
+        },
         Ok(res) => Ok(res.key.to_string()),
     }
 }
@@ -248,7 +357,9 @@ struct UserWorkerResponseBodyResource {
     reader: AsyncRefCell<Peekable<BytesStream>>,
     cancel: CancelHandle,
     size: Option<u64>,
-    req_end_tx: mpsc::UnboundedSender<()>,
+    bytes_read: Cell<usize>,
+    is_finished: Cell<bool>,
+    req_end_tx: mpsc::UnboundedSender<ResponseEnd>,
     conn_watch: Option<watch::Receiver<ConnSync>>,
 }
 
@@ -260,6 +371,7 @@ impl Resource for UserWorkerResponseBodyResource {
     fn read(self: Rc<Self>, limit: usize) -> AsyncResult<BufView> {
         Box::pin(async move {
             let reader = RcRef::map(&self, |r| &r.reader).borrow_mut().await;
+            let this = self.clone();
 
             let fut = async move {
                 let mut reader = Pin::new(reader);
@@ -268,6 +380,7 @@ impl Resource for UserWorkerResponseBodyResource {
                         Some(Ok(chunk)) if !chunk.is_empty() => {
                             let len = std::cmp::min(limit, chunk.len());
                             let chunk = chunk.split_to(len);
+                            this.bytes_read.set(this.bytes_read.get() + len);
                             break Ok(chunk.into());
                         }
                         // This unwrap is safe because `peek_mut()` returned `Some`, and thus
@@ -280,7 +393,10 @@ impl Resource for UserWorkerResponseBodyResource {
                             Ok(chunk) => assert!(chunk.is_empty()),
                             Err(err) => break Err(type_error(err.to_string())),
                         },
-                        None => break Ok(BufView::empty()),
+                        None => {
+                            this.is_finished.set(true);
+                            break Ok(BufView::empty());
+                        }
                     }
                 }
             };
@@ -293,7 +409,11 @@ impl Resource for UserWorkerResponseBodyResource {
     fn close(self: Rc<Self>) {
         self.cancel.cancel();
 
-        let _ = self.req_end_tx.send(());
+        let response_bytes = self.bytes_read.get();
+        let _ = self.req_end_tx.send(ResponseEnd {
+            response_bytes,
+            aborted: !self.is_finished.get() && self.size != Some(response_bytes as u64),
+        });
         let Ok(this) = Rc::try_unwrap(self) else {
             return;
         };
@@ -431,7 +551,7 @@ pub async fn op_user_worker_fetch_send(
                     return Err(custom_error("WorkerRequestCancelled", err.to_string()));
                 }
 
-                None => {
+                _ => {
                     return Err(custom_error(
                         "InvalidWorkerResponse",
                         "user worker failed to respond",
@@ -469,6 +589,8 @@ pub async fn op_user_worker_fetch_send(
         reader: AsyncRefCell::new(stream.peekable()),
         cancel: CancelHandle::default(),
         size,
+        bytes_read: Cell::default(),
+        is_finished: Cell::default(),
         req_end_tx,
         conn_watch: watcher,
     });
@@ -494,3 +616,121 @@ impl Stream for BodyStream {
         self.0.poll_recv(cx)
     }
 }
+
+fn get_user_worker_msgs_tx(state: &Rc<RefCell<OpState>>) -> mpsc::UnboundedSender<UserWorkerMsgs> {
+    state
+        .borrow()
+        .borrow::<mpsc::UnboundedSender<UserWorkerMsgs>>()
+        .clone()
+}
+
+#[op2(async)]
+#[serde]
+pub async fn op_user_worker_list(
+    state: Rc<RefCell<OpState>>,
+) -> Result<Vec<UserWorkerInfo>, AnyError> {
+    let tx = get_user_worker_msgs_tx(&state);
+    let (result_tx, result_rx) = oneshot::channel::<Vec<UserWorkerInfo>>();
+
+    tx.send(UserWorkerMsgs::List(result_tx))?;
+
+    Ok(result_rx.await?)
+}
+
+#[op2(async)]
+#[serde]
+pub async fn op_user_worker_stats(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+) -> Result<UserWorkerStats, AnyError> {
+    let tx = get_user_worker_msgs_tx(&state);
+    let key_parsed = Uuid::try_parse(key.as_str())?;
+    let (result_tx, result_rx) = oneshot::channel::<Result<UserWorkerStats, Error>>();
+
+    tx.send(UserWorkerMsgs::Stats(key_parsed, result_tx))?;
+
+    result_rx.await?
+}
+
+#[op2(async)]
+pub async fn op_user_worker_terminate(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+) -> Result<(), AnyError> {
+    let tx = get_user_worker_msgs_tx(&state);
+    let key_parsed = Uuid::try_parse(key.as_str())?;
+    let (result_tx, result_rx) = oneshot::channel::<Result<(), Error>>();
+
+    tx.send(UserWorkerMsgs::Terminate(key_parsed, result_tx))?;
+
+    result_rx.await?
+}
+
+#[op2(async)]
+pub async fn op_user_worker_retire(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+) -> Result<(), AnyError> {
+    let tx = get_user_worker_msgs_tx(&state);
+    let key_parsed = Uuid::try_parse(key.as_str())?;
+    let (result_tx, result_rx) = oneshot::channel::<Result<(), Error>>();
+
+    tx.send(UserWorkerMsgs::Retire(key_parsed, result_tx))?;
+
+    result_rx.await?
+}
+
+/// Profiles the worker for the given duration and resolves with the path of
+/// the written `.cpuprofile` file.
+#[op2(async)]
+#[string]
+pub async fn op_user_worker_cpu_profile(
+    state: Rc<RefCell<OpState>>,
+    #[string] key: String,
+    #[number] duration_ms: u64,
+) -> Result<String, AnyError> {
+    let tx = get_user_worker_msgs_tx(&state);
+    let key_parsed = Uuid::try_parse(key.as_str())?;
+    let (result_tx, result_rx) = oneshot::channel::<Result<PathBuf, Error>>();
+
+    tx.send(UserWorkerMsgs::CpuProfile(
+        key_parsed,
+        duration_ms,
+        result_tx,
+    ))?;
+
+    Ok(result_rx.await??.to_string_lossy().into_owned())
+}
+
+struct BeforeUnloadAck(oneshot::Sender<()>);
+
+/// Resolves with the shutdown reason once the supervisor is about to
+/// terminate the worker. Resolves with `None` if the worker is not supervised
+/// or the supervisor has gone away.
+#[op2(async)]
+#[serde]
+pub async fn op_user_worker_wait_for_beforeunload(
+    state: Rc<RefCell<OpState>>,
+) -> Result<Option<ShutdownReason>, AnyError> {
+    let Some(rx) = state
+        .borrow_mut()
+        .try_take::<oneshot::Receiver<BeforeUnloadSignal>>()
+    else {
+        return Ok(None);
+    };
+
+    let Ok((reason, ack_tx)) = rx.await else {
+        return Ok(None);
+    };
+
+    state.borrow_mut().put(BeforeUnloadAck(ack_tx));
+
+    Ok(Some(reason))
+}
+
+#[op2(fast)]
+pub fn op_user_worker_beforeunload_done(state: &mut OpState) {
+    if let Some(BeforeUnloadAck(ack_tx)) = state.try_take::<BeforeUnloadAck>() {
+        let _ = ack_tx.send(());
+    }
+}
@@ -9,7 +9,12 @@ const ops = core.ops;
 
 const {
 	op_user_worker_fetch_send,
-	op_user_worker_create
+	op_user_worker_create,
+	op_user_worker_list,
+	op_user_worker_stats,
+	op_user_worker_terminate,
+	op_user_worker_retire,
+	op_user_worker_cpu_profile,
 } = core.ensureFastOps();
 
 // interface WorkerOptions {
@@ -121,23 +126,72 @@ Invoke \`EdgeRuntime.applyConnectionWatcher(origReq, newReq)\` if you have clone
 		});
 	}
 
+	async stats() {
+		return await op_user_worker_stats(this.key);
+	}
+
+	static async list() {
+		return await op_user_worker_list();
+	}
+
+	static get(key) {
+		return new UserWorker(key);
+	}
+
+	static async terminate(key) {
+		await op_user_worker_terminate(key);
+	}
+
+	static async retire(key) {
+		await op_user_worker_retire(key);
+	}
+
+	// Resolves with the path of the written `.cpuprofile` file. A zero
+	// duration profiles the worker until it shuts down.
+	static async cpuProfile(key, durationMs = 0) {
+		return await op_user_worker_cpu_profile(key, durationMs);
+	}
+
 	static async create(opts) {
 		const readyOptions = {
 			memoryLimitMb: 512,
+			memorySampleIntervalMs: 1000,
+			resourceSampleIntervalMs: 0,
 			lowMemoryMultiplier: 5,
 			workerTimeoutMs: 5 * 60 * 1000,
+			requestTimeoutMs: 0,
+			cpuTimeHeader: null,
 			cpuTimeSoftLimitMs: 50,
 			cpuTimeHardLimitMs: 100,
+			shutdownGracePeriodMs: 0, // 0 = `beforeunload` is not dispatched
+			shutdownGraceCpuTimeMs: 0,
+			cpuProfileDurationMs: null,
+			logLevel: null, // 'debug' | 'info' | 'warn' | 'error'
+			structuredLogs: false,
+			traceparent: null,
 			noModuleCache: false,
 			importMapPath: null,
 			envVars: [],
+			allowEnv: null, // null = any variable, e.g. ['SUPABASE_*']
+			secretEnv: [], // readable, but left out of `Deno.env.toObject()` and the logs
 			forceCreate: false,
 			netAccessDisabled: false,
+			// e.g. ['api.example.com', '*.example.com:443', '10.0.0.0/8', '[::1]:8080']
+			allowNet: null, // null = any host that is not denied
+			denyNet: [],
+			denyPrivateNet: false, // refuse loopback, private and link-local destinations
+			// e.g. 'http://proxy:3128' or 'socks5h://proxy:1080', the destinations
+			// reached through it are only checked by name against the rules above
+			proxy: null,
+			noProxy: [], // same syntax as `allowNet`
+			allowRead: null, // null = the service directory
+			allowWrite: [],
 			allowRemoteModules: true,
 			customModuleRoot: '',
 			maybeEszip: null,
 			maybeEntrypoint: null,
 			maybeModuleCode: null,
+			version: null,
 			...opts,
 		};
 
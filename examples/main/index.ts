@@ -74,6 +74,8 @@ serve(async (req: Request) => {
 			netAccessDisabled,
 			cpuTimeSoftLimitMs,
 			cpuTimeHardLimitMs,
+			// parent of the worker creation span (when tracing is enabled)
+			traceparent: req.headers.get('traceparent'),
 			// maybeEszip,
 			// maybeEntrypoint,
 			// maybeModuleCode,